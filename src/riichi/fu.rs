@@ -0,0 +1,383 @@
+use crate::riichi::shapes::{ClosedShape, CompleteShape, OpenKan, OpenShape, Shape, ShapeType};
+use crate::riichi::table::Table;
+use crate::riichi::tile::{Tile, TileType};
+use serde::Serialize;
+
+/// The wait a hand was completed on
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum WaitType {
+    /// Two-sided wait (23 waiting on 1 or 4)
+    Ryanmen,
+    /// Closed wait (13 waiting on 2)
+    Kanchan,
+    /// Edge wait (12 waiting on 3, 89 waiting on 7)
+    Penchan,
+    /// Two pairs, one becomes a triplet
+    Shanpon,
+    /// Single tile wait for the pair
+    Tanki,
+}
+
+impl WaitType {
+    /// How many fu does this wait give?
+    pub fn get_fu(&self) -> u8 {
+        match self {
+            WaitType::Ryanmen | WaitType::Shanpon => 0,
+            WaitType::Kanchan | WaitType::Penchan | WaitType::Tanki => 2,
+        }
+    }
+}
+
+/// Kinds of melds that are worth fu
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum MeldKind {
+    /// Open triplet (called, or completed by ron)
+    Minkou,
+    /// Closed triplet
+    Ankou,
+    /// Open kan (daiminkan or shouminkan)
+    Minkan,
+    /// Closed kan
+    Ankan,
+}
+
+/// Fu given by a single meld
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct MeldFu {
+    /// What kind of meld this is
+    pub kind: MeldKind,
+    /// A tile of the meld
+    pub tile: Tile,
+    /// Fu of this meld
+    pub fu: u8,
+}
+
+impl MeldFu {
+    /// Fu for a triplet or a kan of this tile.
+    pub fn new(kind: MeldKind, tile: Tile) -> MeldFu {
+        let base = match kind {
+            MeldKind::Minkou => 2,
+            MeldKind::Ankou => 4,
+            MeldKind::Minkan => 8,
+            MeldKind::Ankan => 16,
+        };
+
+        MeldFu {
+            kind,
+            tile,
+            fu: if tile.is_terminal_or_honor() {
+                base * 2
+            } else {
+                base
+            },
+        }
+    }
+}
+
+/// Itemized fu of a complete hand.
+/// See http://arcturus.su/wiki/Fu
+#[derive(Debug, Clone, Serialize)]
+pub struct FuBreakdown {
+    /// 20 for regular hands, 25 for chiitoitsu, 30 for an open hand with no other fu won by ron
+    pub base: u8,
+    /// 10 for a closed hand won by ron
+    pub menzen_ron: u8,
+    /// 2 for winning by tsumo (not given to pinfu)
+    pub tsumo: u8,
+    /// Fu from triplets and kans
+    pub melds: Vec<MeldFu>,
+    /// Fu from a yakuhai pair
+    pub pair: u8,
+    /// The wait the hand was completed on
+    pub wait: Option<WaitType>,
+    /// Fu from the wait
+    pub wait_fu: u8,
+}
+
+impl FuBreakdown {
+    /// Finds the fu breakdown of this shape variant. If the winning tile can be interpreted in more ways,
+    /// the interpretation with the most fu is used.
+    pub fn new(table: &Table, variant: &[Shape], pinfu: bool) -> FuBreakdown {
+        let tsumo = table.did_i_tsumo();
+        let closed = table.get_my_hand().is_closed();
+
+        let pairs = variant
+            .iter()
+            .filter(|s| {
+                matches!(
+                    s.get_shape_type(),
+                    ShapeType::Complete(CompleteShape::Closed(ClosedShape::Toitsu(_)))
+                )
+            })
+            .count();
+
+        if pairs == 7 {
+            // chiitoitsu is always 25 fu
+            return FuBreakdown {
+                base: 25,
+                menzen_ron: 0,
+                tsumo: 0,
+                melds: vec![],
+                pair: 0,
+                wait: Some(WaitType::Tanki),
+                wait_fu: 0,
+            };
+        }
+
+        if pinfu {
+            return FuBreakdown {
+                base: 20,
+                menzen_ron: if tsumo { 0 } else { 10 },
+                tsumo: 0,
+                melds: vec![],
+                pair: 0,
+                wait: Some(WaitType::Ryanmen),
+                wait_fu: 0,
+            };
+        }
+
+        let winning_tile = table.get_my_winning_tile();
+
+        // every closed shape with the winning tile is a possible wait
+        let mut waits = vec![];
+        for (i, shape) in variant.iter().enumerate() {
+            if let ShapeType::Complete(CompleteShape::Closed(closed_shape)) = shape.get_shape_type()
+            {
+                if let Some(wait) = FuBreakdown::wait_in_shape(closed_shape, &winning_tile) {
+                    waits.push((i, wait));
+                }
+            }
+        }
+
+        let mut best: Option<FuBreakdown> = None;
+        for (wait_index, wait) in waits.iter() {
+            let breakdown = FuBreakdown::with_wait(table, variant, Some((*wait_index, *wait)));
+            best = match best {
+                Some(b) if b.total() >= breakdown.total() => Some(b),
+                _ => Some(breakdown),
+            };
+        }
+
+        let mut breakdown = match best {
+            None => FuBreakdown::with_wait(table, variant, None),
+            Some(b) => b,
+        };
+
+        // an open hand with no fu won by ron still gets 30 fu
+        if !closed && !tsumo && breakdown.total() == 20 {
+            breakdown.base = 30;
+        }
+
+        breakdown
+    }
+
+    /// Raw fu total, before rounding up to tens
+    pub fn total(&self) -> u8 {
+        self.base
+            + self.menzen_ron
+            + self.tsumo
+            + self.melds.iter().map(|m| m.fu).sum::<u8>()
+            + self.pair
+            + self.wait_fu
+    }
+
+    /// Counts fu of a variant, where the shape at wait.0 was completed on a wait of type wait.1
+    fn with_wait(table: &Table, variant: &[Shape], wait: Option<(usize, WaitType)>) -> FuBreakdown {
+        let tsumo = table.did_i_tsumo();
+        let closed = table.get_my_hand().is_closed();
+
+        let mut melds = vec![];
+        let mut pair = 0;
+
+        for (i, shape) in variant.iter().enumerate() {
+            if let ShapeType::Complete(cs) = shape.get_shape_type() {
+                match cs {
+                    CompleteShape::Closed(closed_shape) => match closed_shape {
+                        ClosedShape::Koutsu(tiles) => {
+                            // a triplet completed by ron counts as open
+                            let completed_by_ron = !tsumo
+                                && matches!(wait, Some((index, WaitType::Shanpon)) if index == i);
+                            let kind = if completed_by_ron {
+                                MeldKind::Minkou
+                            } else {
+                                MeldKind::Ankou
+                            };
+                            melds.push(MeldFu::new(kind, tiles[0]));
+                        }
+                        ClosedShape::Kantsu(tiles) => {
+                            melds.push(MeldFu::new(MeldKind::Ankan, tiles[0]));
+                        }
+                        ClosedShape::Toitsu(tiles) => {
+                            pair += FuBreakdown::pair_fu(table, &tiles[0]);
+                        }
+                        ClosedShape::Shuntsu(_) | ClosedShape::Single(_) => {}
+                    },
+                    CompleteShape::Open(open_shape) => match open_shape {
+                        OpenShape::Chi(_) => {}
+                        OpenShape::Pon(tiles) => {
+                            melds.push(MeldFu::new(MeldKind::Minkou, tiles[0]));
+                        }
+                        OpenShape::Kan(open_kan) => {
+                            let tiles = match open_kan {
+                                OpenKan::Daiminkan(tls) | OpenKan::Shouminkan(tls) => tls,
+                            };
+
+                            melds.push(MeldFu::new(MeldKind::Minkan, tiles[0]));
+                        }
+                    },
+                }
+            }
+        }
+
+        let wait_type = wait.map(|w| w.1);
+
+        FuBreakdown {
+            base: 20,
+            menzen_ron: if closed && !tsumo { 10 } else { 0 },
+            tsumo: if tsumo { 2 } else { 0 },
+            melds,
+            pair,
+            wait: wait_type,
+            wait_fu: wait_type.map_or(0, |w| w.get_fu()),
+        }
+    }
+
+    /// Fu for a pair: dragons, the prevalent wind and my seat wind give 2 fu each.
+    /// A double wind pair gives as many fu as the rules say.
+    fn pair_fu(table: &Table, tile: &Tile) -> u8 {
+        match &tile.tile_type {
+            TileType::Number(_, _) => 0,
+            TileType::Wind(value) => {
+                let prevalent = table.get_prevalent_wind() == Some(*value);
+                let seat = table.get_my_seat_wind() == Some(*value);
+
+                if prevalent && seat {
+                    table.get_rules().unwrap_or_default().double_wind_pair_fu
+                } else if prevalent || seat {
+                    2
+                } else {
+                    0
+                }
+            }
+            TileType::Dragon(_) => 2,
+        }
+    }
+
+    /// What wait did this shape have, if it was completed with the winning tile?
+    fn wait_in_shape(shape: &ClosedShape, winning_tile: &Tile) -> Option<WaitType> {
+        match shape {
+            ClosedShape::Shuntsu(tiles) => {
+                if tiles[1].eq(winning_tile) {
+                    Some(WaitType::Kanchan)
+                } else if (tiles[0].prev_id(false, 1) == 0 && tiles[2].eq(winning_tile))
+                    || (tiles[2].next_id(false, 1) == 0 && tiles[0].eq(winning_tile))
+                {
+                    Some(WaitType::Penchan)
+                } else if tiles[0].eq(winning_tile) || tiles[2].eq(winning_tile) {
+                    Some(WaitType::Ryanmen)
+                } else {
+                    None
+                }
+            }
+            ClosedShape::Koutsu(tiles) => {
+                if tiles[0].eq(winning_tile) {
+                    Some(WaitType::Shanpon)
+                } else {
+                    None
+                }
+            }
+            ClosedShape::Toitsu(tiles) => {
+                if tiles[0].eq(winning_tile) {
+                    Some(WaitType::Tanki)
+                } else {
+                    None
+                }
+            }
+            ClosedShape::Kantsu(_) | ClosedShape::Single(_) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::riichi::hand::Hand;
+    use crate::riichi::rules::Rules;
+    use crate::riichi::shape_finder::ShapeFinder;
+    use serde_json::Map;
+
+    fn breakdown(hand: &str, tsumo: bool) -> FuBreakdown {
+        let mut table = Table::from_map(&Map::new()).unwrap();
+        let mut hand = Hand::from_text(hand, false).unwrap();
+        let variants = ShapeFinder::new().find(&mut hand);
+        table.set_my_hand(hand);
+        table.set_my_tsumo(tsumo);
+        table.set_prevalent_wind(1);
+        table.set_my_seat_wind(1);
+
+        FuBreakdown::new(&table, &variants[0], false)
+    }
+
+    #[test]
+    fn closed_ron_kanchan() {
+        let fu = breakdown("123m24s456789p22z3s", false);
+
+        assert_eq!(fu.menzen_ron, 10);
+        assert_eq!(fu.tsumo, 0);
+        assert_eq!(fu.wait, Some(WaitType::Kanchan));
+        assert_eq!(fu.total(), 32);
+    }
+
+    #[test]
+    fn shanpon_ron_is_minkou() {
+        let fu = breakdown("111m22255p777s22z5p", false);
+
+        let minkou = fu
+            .melds
+            .iter()
+            .filter(|m| m.kind == MeldKind::Minkou)
+            .count();
+        let ankou = fu
+            .melds
+            .iter()
+            .filter(|m| m.kind == MeldKind::Ankou)
+            .count();
+        assert_eq!(minkou, 1);
+        assert_eq!(ankou, 3);
+        // 20 + 10 (menzen ron) + 8 + 4 + 4 (ankou) + 2 (minkou 5p)
+        assert_eq!(fu.total(), 48);
+    }
+
+    #[test]
+    fn double_wind_pair() {
+        let fu = breakdown("123m234s456789p11z", true);
+
+        assert_eq!(fu.pair, 4);
+        assert_eq!(fu.tsumo, 2);
+        assert_eq!(fu.wait, Some(WaitType::Tanki));
+        assert_eq!(fu.total(), 28);
+    }
+
+    #[test]
+    fn double_wind_pair_by_rules() {
+        let mut table = Table::from_map(&Map::new()).unwrap();
+        table.set_prevalent_wind(1);
+        table.set_my_seat_wind(1);
+        let east = Tile::from_text("1z").unwrap();
+        let south = Tile::from_text("2z").unwrap();
+
+        assert_eq!(FuBreakdown::pair_fu(&table, &east), 4);
+
+        table.set_rules(Rules::wrc());
+        assert_eq!(FuBreakdown::pair_fu(&table, &east), 2);
+        assert_eq!(FuBreakdown::pair_fu(&table, &south), 0);
+    }
+
+    #[test]
+    fn open_pinfu_shape() {
+        let fu = breakdown("234m678s56997p(789p1)", false);
+
+        assert_eq!(fu.base, 30);
+        assert_eq!(fu.total(), 30);
+    }
+}
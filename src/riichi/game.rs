@@ -0,0 +1,1221 @@
+use crate::riichi::hand::Hand;
+use crate::riichi::placement::final_results;
+use crate::riichi::replay::{HandDraw, HandStart, HandWin, Replay, ReplayEvent};
+use crate::riichi::rules::{GameLength, Rules};
+use crate::riichi::settlement::{
+    is_nagashi_mangan, is_pao_yaku, DrawReason, DrawSettlement, Liability, Settlement, Win,
+};
+use crate::riichi::shapes::{ClosedShape, CompleteShape, OpenKan, OpenShape};
+use crate::riichi::table::{Action, HandSnapshot, RobbedKan, Table};
+use crate::riichi::tile::{Tile, TileColor, TileType};
+use crate::riichi::yaku::{HandResult, Yaku};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use std::mem::discriminant;
+
+/// Someone (or something) sitting at the table
+pub trait Player {
+    /// Choose one of the legal actions. The table shows the game from this player's point of view.
+    fn choose(&mut self, table: &Table, actions: &[Action]) -> Action;
+}
+
+/// A player that wins whenever it can, otherwise discards the drawn tile and never calls
+pub struct TsumogiriPlayer;
+
+impl Player for TsumogiriPlayer {
+    fn choose(&mut self, table: &Table, actions: &[Action]) -> Action {
+        if actions.contains(&Action::Tsumo) {
+            return Action::Tsumo;
+        }
+
+        if actions.contains(&Action::Ron) {
+            return Action::Ron;
+        }
+
+        if let Some(drawn) = table.get_my_hand().get_drawn_tile() {
+            let tsumogiri = Action::Discard(*drawn);
+            if actions.contains(&tsumogiri) {
+                return tsumogiri;
+            }
+        }
+
+        match actions.iter().find(|a| matches!(a, Action::Discard(_))) {
+            Some(discard) => *discard,
+            None => Action::Pass,
+        }
+    }
+}
+
+/// How a hand ended
+#[derive(Debug)]
+pub enum HandOutcome {
+    /// Someone won. The hand results are from the paid winners' point of view, in the same order.
+    Win {
+        /// Who got paid and how much
+        settlement: Settlement,
+        /// Yaku and score of each paid winner
+        results: Vec<HandResult>,
+    },
+    /// The wall ran out
+    ExhaustiveDraw(DrawSettlement),
+    /// The hand was aborted
+    AbortiveDraw(DrawReason),
+}
+
+/// The 136 tiles of one hand, split into the live wall and the 14 tile dead wall
+#[derive(Debug, Clone)]
+pub struct Wall {
+    live: Vec<Tile>,
+    dead: Vec<Tile>,
+    rinshan_drawn: u8,
+    dora_revealed: u8,
+}
+
+impl Wall {
+    /// Shuffle a new wall. Red 5s are added according to the rules.
+    pub fn new(rng: &mut StdRng, rules: &Rules) -> Wall {
+        let mut tiles = Vec::with_capacity(136);
+        for id in 1..=34 {
+            for copy in 0..4 {
+                let mut tile = Tile::from_id(id).unwrap();
+                if let TileType::Number(5, color) = tile.tile_type {
+                    let suit = match color {
+                        TileColor::Manzu => 0,
+                        TileColor::Pinzu => 1,
+                        TileColor::Souzu => 2,
+                    };
+                    tile.is_red = copy < rules.aka[suit];
+                }
+                tile.id_136 = Some((id - 1) * 4 + copy);
+                tiles.push(tile);
+            }
+        }
+
+        tiles.shuffle(rng);
+        let dead = tiles.split_off(122);
+
+        Wall {
+            live: tiles,
+            dead,
+            rinshan_drawn: 0,
+            dora_revealed: 1,
+        }
+    }
+
+    /// Draw the next tile from the live wall
+    pub fn draw(&mut self) -> Option<Tile> {
+        self.live.pop()
+    }
+
+    /// Draw a replacement tile after a kan. The dead wall takes the last tile of the live wall.
+    pub fn draw_rinshan(&mut self) -> Option<Tile> {
+        if self.rinshan_drawn >= 4 || self.live.is_empty() {
+            return None;
+        }
+
+        let tile = self.dead[self.rinshan_drawn as usize];
+        self.rinshan_drawn += 1;
+        self.live.remove(0);
+
+        Some(tile)
+    }
+
+    /// Reveal the next dora indicator after a kan
+    pub fn reveal_dora(&mut self) {
+        if self.dora_revealed < 5 {
+            self.dora_revealed += 1;
+        }
+    }
+
+    /// How many tiles can still be drawn from the live wall?
+    pub fn tiles_remaining(&self) -> u8 {
+        self.live.len() as u8
+    }
+
+    /// Revealed dora indicators
+    pub fn get_dora_indicators(&self) -> Vec<Tile> {
+        (0..self.dora_revealed as usize)
+            .map(|i| self.dead[4 + 2 * i])
+            .collect()
+    }
+
+    /// Ura dora indicators under the revealed dora indicators
+    pub fn get_ura_dora_indicators(&self) -> Vec<Tile> {
+        (0..self.dora_revealed as usize)
+            .map(|i| self.dead[5 + 2 * i])
+            .collect()
+    }
+}
+
+/// Where a seat finished the match
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Standing {
+    /// Seat (0 = the first dealer)
+    pub seat: u8,
+    /// Points at the end of the match
+    pub points: i32,
+    /// 1 - 4
+    pub place: u8,
+    /// Final score with uma and oka
+    pub score: f32,
+}
+
+/// Where the next tile of the player in turn comes from
+enum NextDraw {
+    Wall,
+    Rinshan,
+    Nothing,
+}
+
+/// A four player game. Seats are absolute (0-3), tables given to players are relative to their seat.
+pub struct Game {
+    rules: Rules,
+    rng: StdRng,
+    wall: Wall,
+    hands: [Hand; 4],
+    discards: [Vec<Tile>; 4],
+    discards_called: [bool; 4],
+    riichi: [bool; 4],
+    double_riichi: [bool; 4],
+    temporary_furiten: [bool; 4],
+    riichi_furiten: [bool; 4],
+    forbidden_discards: [Vec<Tile>; 4],
+    liable: [Option<(u8, Yaku)>; 4],
+    uninterrupted: bool,
+    /// The dora indicator of a daiminkan or shouminkan, revealed after the replacement discard
+    open_kan_dora: bool,
+    points: [i32; 4],
+    prevalent_wind: u8,
+    dealer: u8,
+    honba: u8,
+    riichi_sticks: u8,
+    events: Vec<ReplayEvent>,
+}
+
+impl Game {
+    /// A new game where everyone has the starting points. The same seed deals the same walls.
+    /// Seat 0 is the first dealer.
+    pub fn new(rules: Rules, seed: u64) -> Game {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let wall = Wall::new(&mut rng, &rules);
+
+        Game {
+            rules,
+            rng,
+            wall,
+            hands: Default::default(),
+            discards: Default::default(),
+            discards_called: [false; 4],
+            riichi: [false; 4],
+            double_riichi: [false; 4],
+            temporary_furiten: [false; 4],
+            riichi_furiten: [false; 4],
+            forbidden_discards: Default::default(),
+            liable: Default::default(),
+            uninterrupted: true,
+            open_kan_dora: false,
+            points: [rules.starting_points; 4],
+            prevalent_wind: 1,
+            dealer: 0,
+            honba: 0,
+            riichi_sticks: 0,
+            events: vec![],
+        }
+    }
+
+    /// Set up the next hand: prevalent wind (1 = east), dealer seat, honba and riichi sticks in the pot
+    pub fn set_round(&mut self, prevalent_wind: u8, dealer: u8, honba: u8, riichi_sticks: u8) {
+        self.prevalent_wind = prevalent_wind;
+        self.dealer = dealer;
+        self.honba = honba;
+        self.riichi_sticks = riichi_sticks;
+    }
+
+    /// Rules of this game
+    pub fn get_rules(&self) -> &Rules {
+        &self.rules
+    }
+
+    /// The wall of the current hand
+    pub fn get_wall(&self) -> &Wall {
+        &self.wall
+    }
+
+    /// Points of all seats
+    pub fn get_points(&self) -> [i32; 4] {
+        self.points
+    }
+
+    /// Set points of all seats
+    pub fn set_points(&mut self, points: [i32; 4]) {
+        self.points = points;
+    }
+
+    /// Prevalent wind (1 = east)
+    pub fn get_prevalent_wind(&self) -> u8 {
+        self.prevalent_wind
+    }
+
+    /// Seat of the dealer
+    pub fn get_dealer(&self) -> u8 {
+        self.dealer
+    }
+
+    /// Honba count
+    pub fn get_honba(&self) -> u8 {
+        self.honba
+    }
+
+    /// Riichi sticks in the pot
+    pub fn get_riichi_sticks(&self) -> u8 {
+        self.riichi_sticks
+    }
+
+    /// Everything played so far as a replay. Players have no names.
+    pub fn get_replay(&self) -> Replay {
+        Replay {
+            players: Default::default(),
+            rules: self.rules,
+            events: self.events.clone(),
+        }
+    }
+
+    /// Seat wind of a seat (1 = east)
+    pub fn seat_wind(&self, seat: u8) -> u8 {
+        (seat + 4 - self.dealer) % 4 + 1
+    }
+
+    /// The table from a seat's point of view. Other seats become players 1-3 in turn order.
+    pub fn table_for(&self, seat: u8) -> Table {
+        let dora_indicators = self.wall.get_dora_indicators();
+        let mut table = HandSnapshot {
+            rules: self.rules,
+            prevalent_wind: self.prevalent_wind,
+            dealer: self.dealer,
+            honba: self.honba,
+            riichi_sticks: self.riichi_sticks,
+            tiles_remaining: self.wall.tiles_remaining(),
+            points: self.points,
+            hands: &self.hands,
+            discards: &self.discards,
+            riichi: self.riichi,
+            double_riichi: self.double_riichi,
+            dora_indicators: &dora_indicators,
+        }
+        .table_for(seat);
+
+        // set after the discards, my discards end temporary furiten and kuikae
+        table.set_my_temporary_furiten(self.temporary_furiten[seat as usize]);
+        table.set_my_riichi_furiten(self.riichi_furiten[seat as usize]);
+        table.set_my_forbidden_discards(self.forbidden_discards[seat as usize].clone());
+
+        table
+    }
+
+    /// Play one hand, from the deal to a win or a draw. Points and riichi sticks are updated,
+    /// moving on to the next hand (dealer, honba) is up to the caller.
+    pub fn play_hand(&mut self, players: &mut [Box<dyn Player>; 4]) -> HandOutcome {
+        self.deal();
+
+        let mut seat = self.dealer;
+        let mut next_draw = NextDraw::Wall;
+        loop {
+            let mut rinshan = false;
+            match next_draw {
+                NextDraw::Wall => match self.wall.draw() {
+                    Some(tile) => self.give_tile(seat, tile),
+                    None => return self.exhaustive_draw(),
+                },
+                NextDraw::Rinshan => {
+                    self.draw_rinshan(seat);
+                    rinshan = true;
+                }
+                NextDraw::Nothing => (),
+            }
+
+            // kans are followed by a replacement tile and another decision
+            let (tile, riichi) = loop {
+                let mut table = self.table_for(seat);
+                table.set_my_rinshan(rinshan);
+                let actions = table.legal_actions(None);
+
+                match self.ask(players, seat, &table, &actions) {
+                    Action::Discard(tile) => break (tile, false),
+                    Action::Riichi(tile) => break (tile, true),
+                    Action::Tsumo => return self.tsumo(seat, rinshan),
+                    Action::KyuushuKyuuhai => {
+                        return self.abortive_draw(DrawReason::KyuushuKyuuhai)
+                    }
+                    Action::Ankan(tile) => {
+                        if let Some(outcome) = self.chankan(players, seat, tile, RobbedKan::Ankan) {
+                            return outcome;
+                        }
+                        self.ankan(seat, &tile);
+                    }
+                    Action::Shouminkan(tile) => {
+                        if let Some(outcome) =
+                            self.chankan(players, seat, tile, RobbedKan::Shouminkan)
+                        {
+                            return outcome;
+                        }
+                        self.shouminkan(seat, &tile);
+                    }
+                    action => panic!("{:?} is not a turn action", action),
+                }
+
+                self.draw_rinshan(seat);
+                rinshan = true;
+            };
+
+            self.discard(seat, tile, riichi);
+            self.reveal_open_kan_dora();
+
+            let mut ron = vec![];
+            let mut calls = vec![];
+            for offset in 1..4 {
+                let other = (seat + offset) % 4;
+                let table = self.table_for(other);
+                let actions = table.legal_actions(Some((Game::relative(other, seat), &tile)));
+                if actions.len() == 1 {
+                    continue;
+                }
+
+                match self.ask(players, other, &table, &actions) {
+                    Action::Ron => ron.push(other),
+                    Action::Pass => (),
+                    action => calls.push((other, action)),
+                }
+            }
+
+            if !ron.is_empty() {
+                return self.ron(seat, &ron, tile, None);
+            }
+            self.pass_on_tile(seat, &tile);
+
+            if riichi {
+                self.points[seat as usize] -= 1000;
+                self.riichi_sticks += 1;
+                self.events.push(ReplayEvent::RiichiAccepted { seat });
+            }
+
+            if let Some(reason) = self.table_for(seat).abortive_draw() {
+                return self.abortive_draw(reason);
+            }
+
+            // pon and kan go before chi
+            calls.sort_by_key(|(_, action)| matches!(action, Action::Chi(_, _)));
+            match calls.first() {
+                Some((caller, action)) => {
+                    next_draw = self.call(*caller, seat, tile, *action);
+                    seat = *caller;
+                }
+                None => {
+                    next_draw = NextDraw::Wall;
+                    seat = (seat + 1) % 4;
+                }
+            }
+        }
+    }
+
+    /// Play hands until the match is over and return the final standings
+    pub fn play_match(&mut self, players: &mut [Box<dyn Player>; 4]) -> Vec<Standing> {
+        loop {
+            let outcome = self.play_hand(players);
+            if !self.next_hand(&outcome) {
+                return self.get_standings();
+            }
+        }
+    }
+
+    /// Move on to the next hand after this outcome. Returns false if the match is over.
+    /// The dealer stays on after a dealer win, dealer tenpai or an abortive draw (renchan) and honba go up,
+    /// unless a non-dealer won. Riichi sticks left at the end go to the first place.
+    pub fn next_hand(&mut self, outcome: &HandOutcome) -> bool {
+        let (dealer_stays, honba) = match outcome {
+            HandOutcome::Win { settlement, .. } => {
+                if settlement.winners.contains(&self.dealer) {
+                    (true, self.honba + 1)
+                } else {
+                    (false, 0)
+                }
+            }
+            HandOutcome::ExhaustiveDraw(draw) => (draw.dealer_stays, draw.honba),
+            HandOutcome::AbortiveDraw(_) => (true, self.honba + 1),
+        };
+
+        let rounds = match self.rules.game_length {
+            GameLength::Tonpuusen => 1,
+            GameLength::Hanchan => 2,
+        };
+        let last_hand = self.prevalent_wind == rounds && self.dealer == 3;
+        let overtime = self.prevalent_wind > rounds;
+        let target_reached = self
+            .points
+            .iter()
+            .any(|points| *points >= self.rules.target_points);
+
+        let over = if self.rules.tobi && self.points.iter().any(|points| *points < 0) {
+            true
+        } else if dealer_stays {
+            let dealer_points = self.points[self.dealer as usize];
+            (last_hand || overtime)
+                && self.rules.agari_yame
+                && dealer_points >= self.rules.target_points
+                && self.points.iter().all(|points| *points <= dealer_points)
+        } else if overtime {
+            // sudden death, but the overtime round is the last one
+            target_reached || self.dealer == 3
+        } else {
+            last_hand && (target_reached || !self.rules.overtime)
+        };
+
+        if over {
+            let top = self.get_standings()[0].seat;
+            self.points[top as usize] += self.riichi_sticks as i32 * 1000;
+            self.riichi_sticks = 0;
+
+            return false;
+        }
+
+        self.honba = honba;
+        if !dealer_stays {
+            self.dealer = (self.dealer + 1) % 4;
+            if self.dealer == 0 {
+                self.prevalent_wind += 1;
+            }
+        }
+
+        true
+    }
+
+    /// Seats ordered by place, with final scores. Ties are broken according to the rules.
+    pub fn get_standings(&self) -> Vec<Standing> {
+        let results = final_results(&self.points, &self.rules);
+
+        let mut seats: Vec<u8> = (0..4).collect();
+        seats.sort_by_key(|seat| (results[*seat as usize].place, *seat));
+
+        seats
+            .iter()
+            .map(|seat| Standing {
+                seat: *seat,
+                points: self.points[*seat as usize],
+                place: results[*seat as usize].place,
+                score: results[*seat as usize].score,
+            })
+            .collect()
+    }
+
+    /// Shuffle a new wall and deal 13 tiles to everyone
+    fn deal(&mut self) {
+        self.wall = Wall::new(&mut self.rng, &self.rules);
+        self.hands = Default::default();
+        self.discards = Default::default();
+        self.discards_called = [false; 4];
+        self.riichi = [false; 4];
+        self.double_riichi = [false; 4];
+        self.temporary_furiten = [false; 4];
+        self.riichi_furiten = [false; 4];
+        self.forbidden_discards = Default::default();
+        self.liable = Default::default();
+        self.uninterrupted = true;
+        self.open_kan_dora = false;
+
+        for _ in 0..13 {
+            for offset in 0..4 {
+                let tile = self.wall.draw().unwrap();
+                self.hands[((self.dealer + offset) % 4) as usize].add_tile(tile);
+            }
+        }
+
+        self.events.push(ReplayEvent::Start(HandStart {
+            prevalent_wind: self.prevalent_wind,
+            dealer: self.dealer,
+            honba: self.honba,
+            riichi_sticks: self.riichi_sticks,
+            dora_indicator: self.wall.get_dora_indicators()[0],
+            points: self.points,
+            hands: [0, 1, 2, 3].map(|seat: usize| {
+                self.hands[seat]
+                    .get_tiles()
+                    .iter()
+                    .flatten()
+                    .copied()
+                    .collect()
+            }),
+        }));
+    }
+
+    /// Where is `other` from `seat`'s point of view? (1 = shimocha, 2 = toimen, 3 = kamicha)
+    fn relative(seat: u8, other: u8) -> u8 {
+        (other + 4 - seat) % 4
+    }
+
+    fn give_tile(&mut self, seat: u8, mut tile: Tile) {
+        self.events.push(ReplayEvent::Draw { seat, tile });
+
+        let hand = &mut self.hands[seat as usize];
+        hand.reset_drawn_tiles();
+        tile.is_draw = true;
+        hand.add_tile(tile);
+    }
+
+    fn draw_rinshan(&mut self, seat: u8) {
+        let tile = self
+            .wall
+            .draw_rinshan()
+            .expect("Kans are only legal with replacement tiles left");
+        self.give_tile(seat, tile);
+    }
+
+    fn reveal_dora(&mut self) {
+        self.wall.reveal_dora();
+        self.events.push(ReplayEvent::Dora {
+            indicator: *self.wall.get_dora_indicators().last().unwrap(),
+        });
+    }
+
+    /// Reveal the dora indicator of the last daiminkan or shouminkan, if it is still hidden
+    fn reveal_open_kan_dora(&mut self) {
+        if self.open_kan_dora {
+            self.open_kan_dora = false;
+            self.reveal_dora();
+        }
+    }
+
+    /// Closed tiles of a seat that are the same as this tile
+    fn closed_copies(&self, seat: u8, tile: &Tile) -> Vec<Tile> {
+        self.hands[seat as usize]
+            .get_tiles()
+            .iter()
+            .flatten()
+            .filter(|t| !t.is_open && !t.is_kan && t.get_id() == tile.get_id())
+            .copied()
+            .collect()
+    }
+
+    /// Ask a player to choose. Anything that is not a legal action is replaced by the first legal action.
+    fn ask(
+        &self,
+        players: &mut [Box<dyn Player>; 4],
+        seat: u8,
+        table: &Table,
+        actions: &[Action],
+    ) -> Action {
+        let action = players[seat as usize].choose(table, actions);
+
+        if actions.contains(&action) {
+            action
+        } else {
+            actions[0]
+        }
+    }
+
+    /// The hand result if this seat can win now: by tsumo, or by ron on a tile from another seat
+    fn win(
+        &self,
+        seat: u8,
+        ron: Option<(u8, &Tile)>,
+        rinshan: bool,
+        chankan: Option<RobbedKan>,
+    ) -> Option<HandResult> {
+        let mut table = self.table_for(seat);
+        if self.riichi[seat as usize] {
+            table.set_ura_dora_indicators(self.wall.get_ura_dora_indicators());
+        }
+
+        match ron {
+            None => {
+                table.set_my_tsumo(true);
+                table.set_my_rinshan(rinshan);
+
+                match table.yaku() {
+                    Some(result) if !result.yaku.is_empty() => Some(result),
+                    _ => None,
+                }
+            }
+            Some((from, tile)) => {
+                table.set_my_chankan(chankan);
+                table.ron_yaku(Game::relative(seat, from), tile)
+            }
+        }
+    }
+
+    /// A win with the liable player, if the hand has the yaku they are liable for
+    fn make_win(&self, seat: u8, result: &HandResult) -> Win {
+        let mut win = Win::new(seat, result.score.clone());
+
+        if let Some((liable, liable_yaku)) = &self.liable[seat as usize] {
+            if !is_pao_yaku(liable_yaku, &self.rules) {
+                return win;
+            }
+
+            if let Some((yaku, han)) = result
+                .yaku
+                .iter()
+                .find(|(yaku, _)| discriminant(yaku) == discriminant(liable_yaku))
+            {
+                win.liable = Some(Liability {
+                    player: *liable,
+                    yaku: yaku.clone(),
+                    yakuman: (han / 13).max(1),
+                });
+            }
+        }
+
+        win
+    }
+
+    fn discard(&mut self, seat: u8, mut tile: Tile, riichi: bool) {
+        let hand = &mut self.hands[seat as usize];
+        let drawn = hand.get_drawn_tile().copied();
+        hand.remove_tile(&tile);
+        hand.reset_drawn_tiles();
+
+        tile.is_draw = false;
+        tile.is_riichi = riichi;
+        tile.is_tsumogiri = match drawn {
+            Some(drawn) => drawn.id_136 == tile.id_136,
+            None => false,
+        };
+
+        if riichi {
+            self.riichi[seat as usize] = true;
+            self.double_riichi[seat as usize] =
+                self.uninterrupted && self.discards[seat as usize].is_empty();
+            self.events.push(ReplayEvent::Riichi { seat });
+        }
+        self.events.push(ReplayEvent::Discard { seat, tile });
+
+        self.discards[seat as usize].push(tile);
+        self.temporary_furiten[seat as usize] = false;
+        self.forbidden_discards[seat as usize] = vec![];
+    }
+
+    /// Nobody won on this tile from a seat. It makes the others furiten if it was one of their waits.
+    fn pass_on_tile(&mut self, from: u8, tile: &Tile) {
+        for offset in 1..4 {
+            let other = (from + offset) % 4;
+            let mut table = self.table_for(other);
+            table.pass_on_tile(tile);
+
+            self.temporary_furiten[other as usize] = table.get_my_temporary_furiten();
+            self.riichi_furiten[other as usize] = table.get_my_riichi_furiten();
+        }
+    }
+
+    /// Call a discard. Returns where the caller's next tile comes from.
+    fn call(&mut self, caller: u8, from: u8, mut tile: Tile, action: Action) -> NextDraw {
+        self.uninterrupted = false;
+        self.discards_called[from as usize] = true;
+
+        let copies = self.closed_copies(caller, &tile);
+        self.forbidden_discards[caller as usize] =
+            self.table_for(caller).forbidden_discards(&tile, &action);
+        tile.called_from = Game::relative(caller, from);
+        tile.is_riichi = false;
+        tile.is_tsumogiri = false;
+
+        let hand = &mut self.hands[caller as usize];
+        hand.reset_drawn_tiles();
+        hand.add_tile(tile);
+
+        let (shape, next_draw) = match action {
+            Action::Chi(first, second) => {
+                let mut tiles = [tile, first, second];
+                tiles.sort();
+                (OpenShape::Chi(tiles), NextDraw::Nothing)
+            }
+            Action::Pon(first, second) => {
+                (OpenShape::Pon([tile, first, second]), NextDraw::Nothing)
+            }
+            Action::Daiminkan => (
+                OpenShape::Kan(OpenKan::Daiminkan([tile, copies[0], copies[1], copies[2]])),
+                NextDraw::Rinshan,
+            ),
+            action => panic!("{:?} is not a call", action),
+        };
+        hand.add_open_shape(&shape);
+        self.events.push(ReplayEvent::Call {
+            seat: caller,
+            meld: CompleteShape::Open(shape),
+        });
+
+        self.update_liable(caller, from, &tile, matches!(action, Action::Daiminkan));
+        if let Action::Daiminkan = action {
+            self.open_kan_dora = true;
+        }
+
+        next_draw
+    }
+
+    /// Did this call make the caller's daisangen, daisuushii or suukantsu certain?
+    fn update_liable(&mut self, caller: u8, from: u8, tile: &Tile, daiminkan: bool) {
+        let sets: Vec<Tile> = self.hands[caller as usize]
+            .get_shapes()
+            .iter()
+            .filter_map(|shape| match shape {
+                CompleteShape::Open(OpenShape::Pon(tiles)) => Some(tiles[0]),
+                CompleteShape::Open(OpenShape::Kan(OpenKan::Daiminkan(tiles)))
+                | CompleteShape::Open(OpenShape::Kan(OpenKan::Shouminkan(tiles)))
+                | CompleteShape::Closed(ClosedShape::Kantsu(tiles)) => Some(tiles[0]),
+                _ => None,
+            })
+            .collect();
+
+        let dragons = sets
+            .iter()
+            .filter(|t| matches!(t.tile_type, TileType::Dragon(_)))
+            .count();
+        let winds = sets
+            .iter()
+            .filter(|t| matches!(t.tile_type, TileType::Wind(_)))
+            .count();
+        let kans = self.table_for(caller).count_kans(0);
+
+        let yaku = match tile.tile_type {
+            TileType::Dragon(_) if dragons == 3 => Some(Yaku::Daisangen),
+            TileType::Wind(_) if winds == 4 => Some(Yaku::Daisuushii),
+            _ if daiminkan && kans == 4 => Some(Yaku::Suukantsu),
+            _ => None,
+        };
+
+        if let Some(yaku) = yaku {
+            if is_pao_yaku(&yaku, &self.rules) {
+                self.liable[caller as usize] = Some((from, yaku));
+            }
+        }
+    }
+
+    /// A closed kan reveals its dora indicator right away
+    fn ankan(&mut self, seat: u8, tile: &Tile) {
+        self.reveal_open_kan_dora();
+        self.uninterrupted = false;
+
+        let copies = self.closed_copies(seat, tile);
+        let kan = ClosedShape::Kantsu([copies[0], copies[1], copies[2], copies[3]]);
+        self.hands[seat as usize].add_closed_kan(kan);
+        self.events.push(ReplayEvent::Call {
+            seat,
+            meld: CompleteShape::Closed(kan),
+        });
+        self.reveal_dora();
+    }
+
+    /// Add a tile to a pon
+    fn shouminkan(&mut self, seat: u8, tile: &Tile) {
+        self.reveal_open_kan_dora();
+        self.uninterrupted = false;
+
+        let hand = &mut self.hands[seat as usize];
+        hand.add_shouminkan(tile);
+        let meld = hand
+            .get_shapes()
+            .iter()
+            .find(|shape| match shape {
+                CompleteShape::Open(OpenShape::Kan(OpenKan::Shouminkan(tiles))) => {
+                    tiles[3] == *tile
+                }
+                _ => false,
+            })
+            .copied()
+            .expect("The pon became a kan");
+        self.events.push(ReplayEvent::Call { seat, meld });
+        self.open_kan_dora = true;
+    }
+
+    /// Let other players rob a kan. Returns the outcome if someone did.
+    fn chankan(
+        &mut self,
+        players: &mut [Box<dyn Player>; 4],
+        seat: u8,
+        tile: Tile,
+        robbed: RobbedKan,
+    ) -> Option<HandOutcome> {
+        let mut ron = vec![];
+        for offset in 1..4 {
+            let other = (seat + offset) % 4;
+            let mut table = self.table_for(other);
+            table.set_my_chankan(Some(robbed));
+            let actions = table.legal_actions(Some((Game::relative(other, seat), &tile)));
+            if actions.len() == 1 {
+                continue;
+            }
+
+            if self.ask(players, other, &table, &actions) == Action::Ron {
+                ron.push(other);
+            }
+        }
+
+        if ron.is_empty() {
+            self.pass_on_tile(seat, &tile);
+            None
+        } else {
+            Some(self.ron(seat, &ron, tile, Some(robbed)))
+        }
+    }
+
+    fn tsumo(&mut self, seat: u8, rinshan: bool) -> HandOutcome {
+        let result = self
+            .win(seat, None, rinshan, None)
+            .expect("Tsumo is only legal with a winning hand");
+        let win = self.make_win(seat, &result);
+        let settlement = Settlement::tsumo(&win, self.dealer, self.honba, self.riichi_sticks);
+        let tile = *self.hands[seat as usize]
+            .get_drawn_tile()
+            .expect("Tsumo is only legal after a draw");
+        self.record_win(&win, seat, tile, settlement.deltas);
+
+        self.settle(settlement, vec![result])
+    }
+
+    fn ron(
+        &mut self,
+        from: u8,
+        winners: &[u8],
+        tile: Tile,
+        chankan: Option<RobbedKan>,
+    ) -> HandOutcome {
+        let mut results: Vec<(u8, HandResult)> = winners
+            .iter()
+            .map(|winner| {
+                let result = self
+                    .win(*winner, Some((from, &tile)), false, chankan)
+                    .expect("Ron is only legal with a winning hand");
+                (*winner, result)
+            })
+            .collect();
+        let wins: Vec<Win> = results
+            .iter()
+            .map(|(winner, result)| self.make_win(*winner, result))
+            .collect();
+
+        let settlement = Settlement::ron(
+            from,
+            &wins,
+            self.dealer,
+            self.honba,
+            self.riichi_sticks,
+            &self.rules,
+        );
+        if let Some(reason) = settlement.abortive_draw {
+            return self.abortive_draw(reason);
+        }
+
+        // every win gets its own payment in the replay, sticks go to the first one
+        for (i, winner) in settlement.winners.iter().enumerate() {
+            let win = wins.iter().find(|win| win.player == *winner).unwrap();
+            let honba = if i == 0 || self.rules.multiple_ron_honba {
+                self.honba
+            } else {
+                0
+            };
+            let riichi_sticks = if i == 0 { self.riichi_sticks } else { 0 };
+            let paid = Settlement::ron(
+                from,
+                std::slice::from_ref(win),
+                self.dealer,
+                honba,
+                riichi_sticks,
+                &self.rules,
+            );
+            self.record_win(win, from, tile, paid.deltas);
+        }
+
+        results.retain(|(winner, _)| settlement.winners.contains(winner));
+        results.sort_by_key(|(winner, _)| Game::relative(from, *winner));
+
+        self.settle(
+            settlement,
+            results.into_iter().map(|(_, result)| result).collect(),
+        )
+    }
+
+    fn settle(&mut self, settlement: Settlement, results: Vec<HandResult>) -> HandOutcome {
+        for (points, delta) in self.points.iter_mut().zip(settlement.deltas.iter()) {
+            *points += delta;
+        }
+        self.riichi_sticks = 0;
+
+        HandOutcome::Win {
+            settlement,
+            results,
+        }
+    }
+
+    fn exhaustive_draw(&mut self) -> HandOutcome {
+        let mut tenpai = [false; 4];
+        let mut nagashi = [false; 4];
+        for seat in 0..4 {
+            tenpai[seat] = self.hands[seat].get_shanten() == 0;
+            nagashi[seat] = is_nagashi_mangan(&self.discards[seat], self.discards_called[seat]);
+        }
+
+        let draw =
+            DrawSettlement::exhaustive_draw(tenpai, nagashi, self.dealer, self.honba, &self.rules);
+        for (points, delta) in self.points.iter_mut().zip(draw.deltas.iter()) {
+            *points += delta;
+        }
+        let mut nagashi_mangan = [false; 4];
+        for player in draw.nagashi_mangan.iter() {
+            nagashi_mangan[*player as usize] = true;
+        }
+        self.events.push(ReplayEvent::Ryuukyoku(HandDraw {
+            reason: DrawReason::Exhaustive,
+            tenpai,
+            nagashi_mangan,
+            deltas: draw.deltas,
+        }));
+
+        HandOutcome::ExhaustiveDraw(draw)
+    }
+
+    fn abortive_draw(&mut self, reason: DrawReason) -> HandOutcome {
+        self.events.push(ReplayEvent::Ryuukyoku(HandDraw {
+            reason,
+            tenpai: [false; 4],
+            nagashi_mangan: [false; 4],
+            deltas: [0; 4],
+        }));
+
+        HandOutcome::AbortiveDraw(reason)
+    }
+
+    fn record_win(&mut self, win: &Win, from: u8, tile: Tile, deltas: [i32; 4]) {
+        let ura_dora_indicators = if self.riichi[win.player as usize] {
+            self.wall.get_ura_dora_indicators()
+        } else {
+            vec![]
+        };
+
+        self.events.push(ReplayEvent::Win(HandWin {
+            seat: win.player,
+            from,
+            liable: win.liable.as_ref().map(|liability| liability.player),
+            tile,
+            han: if win.score.yakuman > 0 {
+                0
+            } else {
+                win.score.han
+            },
+            fu: win.score.fu,
+            yakuman: win.score.yakuman,
+            points: win.score.total_points(),
+            ura_dora_indicators,
+            deltas,
+        }));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tsumogiri_players() -> [Box<dyn Player>; 4] {
+        [
+            Box::new(TsumogiriPlayer),
+            Box::new(TsumogiriPlayer),
+            Box::new(TsumogiriPlayer),
+            Box::new(TsumogiriPlayer),
+        ]
+    }
+
+    #[test]
+    fn wall_tiles() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let wall = Wall::new(&mut rng, &Rules::tenhou());
+
+        assert_eq!(wall.tiles_remaining(), 122);
+        assert_eq!(wall.live.iter().chain(wall.dead.iter()).count(), 136);
+        assert_eq!(
+            wall.live
+                .iter()
+                .chain(wall.dead.iter())
+                .filter(|t| t.is_red)
+                .count(),
+            3
+        );
+        assert_eq!(wall.get_dora_indicators().len(), 1);
+    }
+
+    #[test]
+    fn kan_dora() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut wall = Wall::new(&mut rng, &Rules::wrc());
+
+        assert!(wall.draw_rinshan().is_some());
+        assert_eq!(wall.tiles_remaining(), 121);
+        assert_eq!(wall.get_dora_indicators().len(), 1);
+
+        wall.reveal_dora();
+        assert_eq!(wall.get_dora_indicators().len(), 2);
+        assert_eq!(wall.get_ura_dora_indicators().len(), 2);
+    }
+
+    #[test]
+    fn seeded_games_are_the_same() {
+        let mut first = Game::new(Rules::tenhou(), 42);
+        let mut second = Game::new(Rules::tenhou(), 42);
+
+        for _ in 0..3 {
+            let first_outcome = format!("{:?}", first.play_hand(&mut tsumogiri_players()));
+            let second_outcome = format!("{:?}", second.play_hand(&mut tsumogiri_players()));
+
+            assert_eq!(first_outcome, second_outcome);
+            assert_eq!(first.get_points(), second.get_points());
+        }
+    }
+
+    #[test]
+    fn points_are_kept() {
+        let mut game = Game::new(Rules::tenhou(), 7);
+
+        for _ in 0..5 {
+            game.play_hand(&mut tsumogiri_players());
+            let total: i32 = game.get_points().iter().sum();
+
+            assert_eq!(total + game.get_riichi_sticks() as i32 * 1000, 100000);
+        }
+    }
+
+    fn draw(dealer_stays: bool, honba: u8) -> HandOutcome {
+        HandOutcome::ExhaustiveDraw(DrawSettlement {
+            deltas: [0; 4],
+            nagashi_mangan: vec![],
+            dealer_stays,
+            honba,
+        })
+    }
+
+    fn win(winner: u8) -> HandOutcome {
+        HandOutcome::Win {
+            settlement: Settlement {
+                winners: vec![winner],
+                deltas: [0; 4],
+                abortive_draw: None,
+            },
+            results: vec![],
+        }
+    }
+
+    #[test]
+    fn renchan_and_rotation() {
+        let mut game = Game::new(Rules::tenhou(), 1);
+
+        assert!(game.next_hand(&draw(true, 1)));
+        assert_eq!((game.get_dealer(), game.get_honba()), (0, 1));
+
+        assert!(game.next_hand(&win(0)));
+        assert_eq!((game.get_dealer(), game.get_honba()), (0, 2));
+
+        assert!(game.next_hand(&win(2)));
+        assert_eq!((game.get_dealer(), game.get_honba()), (1, 0));
+
+        game.set_round(1, 3, 0, 0);
+        assert!(game.next_hand(&draw(false, 1)));
+        assert_eq!(game.get_prevalent_wind(), 2);
+        assert_eq!((game.get_dealer(), game.get_honba()), (0, 1));
+    }
+
+    #[test]
+    fn last_hand() {
+        // nobody has 30000, so we go to the west round
+        let mut game = Game::new(Rules::tenhou(), 1);
+        game.set_round(2, 3, 0, 0);
+        assert!(game.next_hand(&win(1)));
+        assert_eq!((game.get_prevalent_wind(), game.get_dealer()), (3, 0));
+
+        // sudden death in the west round
+        game.set_points([31000, 23000, 23000, 23000]);
+        assert!(!game.next_hand(&win(1)));
+
+        // no overtime
+        let mut game = Game::new(Rules::mleague(), 1);
+        game.set_round(2, 3, 0, 0);
+        assert!(!game.next_hand(&win(1)));
+    }
+
+    #[test]
+    fn agari_yame() {
+        let mut game = Game::new(Rules::tenhou(), 1);
+        game.set_round(2, 3, 0, 1);
+        game.set_points([20000, 20000, 19000, 40000]);
+        assert!(!game.next_hand(&win(3)));
+
+        let standings = game.get_standings();
+        assert_eq!(standings[0].seat, 3);
+        assert_eq!(standings[0].points, 41000);
+        assert_eq!(standings[0].score, 51.0);
+
+        // without agari-yame the dealer keeps going
+        let mut game = Game::new(Rules::mleague(), 1);
+        game.set_round(2, 3, 0, 0);
+        game.set_points([20000, 20000, 20000, 40000]);
+        assert!(game.next_hand(&win(3)));
+        assert_eq!((game.get_dealer(), game.get_honba()), (3, 1));
+    }
+
+    #[test]
+    fn tobi() {
+        let mut game = Game::new(Rules::tenhou(), 1);
+        game.set_points([-1000, 50000, 30000, 21000]);
+        assert!(!game.next_hand(&win(1)));
+
+        let places: Vec<u8> = game.get_standings().iter().map(|s| s.seat).collect();
+        assert_eq!(places, vec![1, 2, 3, 0]);
+
+        let mut game = Game::new(Rules::wrc(), 1);
+        game.set_points([-1000, 50000, 30000, 21000]);
+        assert!(game.next_hand(&win(1)));
+    }
+
+    #[test]
+    fn full_match() {
+        let mut rules = Rules::tenhou();
+        rules.game_length = GameLength::Tonpuusen;
+        let mut game = Game::new(rules, 5);
+        let standings = game.play_match(&mut tsumogiri_players());
+
+        assert_eq!(standings.len(), 4);
+        assert_eq!(standings.iter().map(|s| s.points).sum::<i32>(), 100000);
+    }
+
+    #[test]
+    fn replay_follows_the_game() {
+        let mut game = Game::new(Rules::tenhou(), 7);
+        let outcome = game.play_hand(&mut tsumogiri_players());
+        game.next_hand(&outcome);
+        game.play_hand(&mut tsumogiri_players());
+
+        let replay = game.get_replay();
+        let starts = replay
+            .events
+            .iter()
+            .filter(|e| matches!(e, ReplayEvent::Start(_)))
+            .count();
+        assert_eq!(starts, 2);
+
+        let table = replay.table_at(replay.events.len(), 0).unwrap();
+        for player in 0..4 {
+            assert_eq!(
+                table.get_points(player),
+                Some(game.get_points()[player as usize])
+            );
+        }
+        assert_eq!(table.get_riichi_sticks(), game.get_riichi_sticks());
+    }
+
+    #[test]
+    fn table_view() {
+        let mut game = Game::new(Rules::tenhou(), 3);
+        game.set_round(1, 2, 0, 0);
+        game.deal();
+
+        let table = game.table_for(1);
+        assert_eq!(table.get_my_seat_wind(), Some(4));
+        assert_eq!(table.get_dealer_player(), Some(1));
+        assert_eq!(table.get_my_hand().count_tiles(), 13);
+        assert_eq!(table.get_tiles_remaining(), Some(70));
+    }
+}
@@ -313,9 +313,9 @@ impl Hand {
         let mut used_tiles: [u8; 34] = [0; 34];
         let mut tiles: Vec<Option<Tile>> = vec![];
 
-        // if I generated a red 5, save it here.
+        // how many red 5s I generated
         // 0 = m, 1 = p, 2 = s
-        let mut red5s: [bool; 3] = [false; 3];
+        let mut red5s: [u8; 3] = [0; 3];
 
         for i in 0..14 {
             let mut tile_id;
@@ -337,31 +337,17 @@ impl Hand {
             }
 
             if let Some(r) = rules {
-                if r.aka_ari {
-                    if let TileType::Number(value, color) = tile.tile_type {
-                        // 25% chance of having a red 5
-                        if value == 5 {
-                            match color {
-                                TileColor::Manzu => {
-                                    if !red5s[0] && rng.gen_range(0, 4) == 0 {
-                                        tile.is_red = true;
-                                        red5s[0] = true;
-                                    }
-                                }
-                                TileColor::Pinzu => {
-                                    if !red5s[1] && rng.gen_range(0, 4) == 0 {
-                                        tile.is_red = true;
-                                        red5s[1] = true;
-                                    }
-                                }
-                                TileColor::Souzu => {
-                                    if !red5s[2] && rng.gen_range(0, 4) == 0 {
-                                        tile.is_red = true;
-                                        red5s[2] = true;
-                                    }
-                                }
-                            }
-                        }
+                if let TileType::Number(5, color) = tile.tile_type {
+                    let suit = match color {
+                        TileColor::Manzu => 0,
+                        TileColor::Pinzu => 1,
+                        TileColor::Souzu => 2,
+                    };
+
+                    // 25% chance of having a red 5
+                    if red5s[suit] < r.aka[suit] && rng.gen_range(0, 4) == 0 {
+                        tile.is_red = true;
+                        red5s[suit] += 1;
                     }
                 }
             }
@@ -691,25 +677,33 @@ impl Hand {
     pub fn add_tile(&mut self, tile: Tile) {
         self.tiles.push(Some(tile));
         self.tiles.sort();
+        self.reset_shanten();
     }
 
-    /// Removes a tile from this hand
-    pub fn remove_tile(&mut self, tile: &Tile) {
-        let mut found: usize = 999;
+    /// Position of a closed tile like this one. A tile with the same redness is preferred.
+    fn closed_tile_position(&self, tile: &Tile) -> Option<usize> {
+        let mut found = None;
         for (i, hand_tile) in self.tiles.iter().enumerate() {
-            match hand_tile {
-                Some(t) => {
-                    if !t.is_open && !t.is_kan && t.get_id() == tile.get_id() {
-                        found = i;
-                        break;
+            if let Some(t) = hand_tile {
+                if !t.is_open && !t.is_kan && t.get_id() == tile.get_id() {
+                    if t.is_red == tile.is_red {
+                        return Some(i);
+                    }
+
+                    if found.is_none() {
+                        found = Some(i);
                     }
                 }
-                None => (),
             }
         }
 
-        if found != 999 {
-            self.tiles.remove(found);
+        found
+    }
+
+    /// Removes a tile from this hand. A tile with the same redness is preferred.
+    pub fn remove_tile(&mut self, tile: &Tile) {
+        if let Some(i) = self.closed_tile_position(tile) {
+            self.tiles.remove(i);
             self.reset_shanten();
         }
     }
@@ -750,82 +744,72 @@ impl Hand {
 
     /// Goes through the tiles and dedicates them to an open shape
     pub fn add_open_shape(&mut self, shape: &OpenShape) {
-        match shape {
-            OpenShape::Chi(tiles) => {
-                for tile in tiles.iter() {
-                    let mut found = false;
-                    for (i, t) in self.tiles.iter().enumerate() {
-                        match t {
-                            None => {}
-                            Some(mut hand_tile) => {
-                                if hand_tile.eq(tile) && !hand_tile.is_open && !hand_tile.is_kan {
-                                    hand_tile.is_open = true;
-                                    hand_tile.is_chi = true;
-                                    self.tiles[i] = Some(hand_tile);
-                                    found = true;
-                                    break;
-                                }
-                            }
-                        }
-                    }
+        let tiles: &[Tile] = match shape {
+            OpenShape::Chi(tiles) | OpenShape::Pon(tiles) => tiles,
+            OpenShape::Kan(OpenKan::Daiminkan(tiles))
+            | OpenShape::Kan(OpenKan::Shouminkan(tiles)) => tiles,
+        };
 
-                    if !found {
-                        panic!("Invalid tiles in open shape");
-                    }
-                }
+        for tile in tiles.iter() {
+            let hand_tile = match self.closed_tile_position(tile) {
+                Some(i) => self.tiles[i].as_mut().unwrap(),
+                None => panic!("Invalid tiles in open shape"),
+            };
+
+            hand_tile.is_open = true;
+            match shape {
+                OpenShape::Chi(_) => hand_tile.is_chi = true,
+                OpenShape::Pon(_) => hand_tile.is_pon = true,
+                OpenShape::Kan(_) => hand_tile.is_kan = true,
             }
-            OpenShape::Pon(tiles) => {
-                for tile in tiles.iter() {
-                    let mut found = false;
-                    for (i, t) in self.tiles.iter().enumerate() {
-                        match t {
-                            None => {}
-                            Some(mut hand_tile) => {
-                                if hand_tile.eq(tile) && !hand_tile.is_open && !hand_tile.is_kan {
-                                    hand_tile.is_open = true;
-                                    hand_tile.is_pon = true;
-                                    self.tiles[i] = Some(hand_tile);
-                                    found = true;
-                                    break;
-                                }
-                            }
-                        }
-                    }
+        }
 
-                    if !found {
-                        panic!("Invalid tiles in open shape");
-                    }
-                }
+        self.shapes.push(CompleteShape::Open(*shape));
+    }
+
+    /// Adds a tile to a pon in this hand, making it a shouminkan
+    pub fn add_shouminkan(&mut self, tile: &Tile) {
+        let pon_index = self.shapes.iter().position(|shape| match shape {
+            CompleteShape::Open(OpenShape::Pon(tiles)) => tiles[0].eq(tile),
+            _ => false,
+        });
+
+        let pon_index = match pon_index {
+            None => panic!("No pon to add the tile to"),
+            Some(index) => index,
+        };
+
+        let pon_tiles = match self.shapes[pon_index] {
+            CompleteShape::Open(OpenShape::Pon(tiles)) => tiles,
+            _ => unreachable!(),
+        };
+
+        let mut added = false;
+        for t in self.tiles.iter_mut().flatten() {
+            if !(*t).eq(tile) {
+                continue;
             }
-            OpenShape::Kan(open_kan) => match open_kan {
-                OpenKan::Daiminkan(tiles) | OpenKan::Shouminkan(tiles) => {
-                    for tile in tiles.iter() {
-                        let mut found = false;
-                        for (i, t) in self.tiles.iter().enumerate() {
-                            match t {
-                                None => {}
-                                Some(mut hand_tile) => {
-                                    if hand_tile.eq(tile) && !hand_tile.is_open && !hand_tile.is_kan
-                                    {
-                                        hand_tile.is_open = true;
-                                        hand_tile.is_kan = true;
-                                        self.tiles[i] = Some(hand_tile);
-                                        found = true;
-                                        break;
-                                    }
-                                }
-                            }
-                        }
 
-                        if !found {
-                            panic!("Invalid tiles in open shape");
-                        }
-                    }
-                }
-            },
+            if t.is_open && t.is_pon {
+                t.is_kan = true;
+            } else if !added && !t.is_open && !t.is_kan {
+                t.is_open = true;
+                t.is_kan = true;
+                added = true;
+            }
         }
 
-        self.shapes.push(CompleteShape::Open(*shape));
+        if !added {
+            panic!("The added tile is not in the hand");
+        }
+
+        self.shapes[pon_index] = CompleteShape::Open(OpenShape::Kan(OpenKan::Shouminkan([
+            pon_tiles[0],
+            pon_tiles[1],
+            pon_tiles[2],
+            *tile,
+        ])));
+        self.reset_shanten();
     }
 
     /// Add a closed kan to this hand
@@ -888,6 +872,31 @@ impl Hand {
         self.get_34_array(false)[(tile_id - 1) as usize]
     }
 
+    /// Different terminals and honors in a 34 array, and is any of them a pair? Kokushi shanten is counted from these.
+    pub fn kokushi_tiles(array_34: &[u8; 34]) -> (u8, bool) {
+        let mut kinds = 0;
+        let mut pair_found = false;
+
+        for (i, count) in array_34.iter().enumerate() {
+            if [1, 9, 10, 18, 19, 27].contains(&(i + 1)) || (i + 1) >= 28 {
+                // we only need 1 of each here + pair
+                if *count > 0 {
+                    kinds += 1;
+                }
+                if *count > 1 {
+                    pair_found = true;
+                }
+            }
+        }
+
+        (kinds, pair_found)
+    }
+
+    /// How many different terminals and honors are in the hand? Counted the same way as kokushi shanten.
+    pub fn count_terminal_and_honor_kinds(&self) -> u8 {
+        Hand::kokushi_tiles(&self.get_34_array(true)).0
+    }
+
     /// How many closed kans does this hand have?
     pub fn get_closed_kans(&self) -> u8 {
         let mut array_34 = [0u8; 34];
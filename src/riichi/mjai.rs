@@ -0,0 +1,1007 @@
+use crate::riichi::hand::Hand;
+use crate::riichi::riichi_error::RiichiError;
+use crate::riichi::rules::Rules;
+use crate::riichi::shapes::{ClosedShape, CompleteShape, OpenKan, OpenShape, Shape, ShapeType};
+use crate::riichi::table::{Action, Table};
+use crate::riichi::tile::Tile;
+use serde_json::{json, Map, Value};
+
+/// An MJAI protocol event. Seats are absolute, 0 is the first dealer.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MjaiEvent {
+    /// The game starts, id is the seat of the one receiving the events
+    StartGame {
+        /// My seat, if known
+        id: Option<u8>,
+        /// Player names in seat order
+        names: Vec<String>,
+    },
+    /// A new hand is dealt
+    StartKyoku {
+        /// Prevalent wind, 1 = east
+        bakaze: u8,
+        /// Hand number in the round, 1 - 4
+        kyoku: u8,
+        /// Honba (repeat) sticks
+        honba: u8,
+        /// Riichi sticks on the table
+        kyotaku: u8,
+        /// Seat of the dealer
+        oya: u8,
+        /// The first dora indicator
+        dora_marker: Tile,
+        /// Points of all seats
+        scores: [i32; 4],
+        /// Starting hands, hidden tiles are None
+        tehais: [Vec<Option<Tile>>; 4],
+    },
+    /// A seat draws a tile, None if it's hidden
+    Tsumo {
+        /// Who drew
+        actor: u8,
+        /// The drawn tile
+        pai: Option<Tile>,
+    },
+    /// A seat discards a tile
+    Dahai {
+        /// Who discarded
+        actor: u8,
+        /// The discarded tile
+        pai: Tile,
+        /// Was it the drawn tile?
+        tsumogiri: bool,
+    },
+    /// Chi of the target's discard
+    Chi {
+        /// Who called
+        actor: u8,
+        /// Whose discard was called
+        target: u8,
+        /// The called tile
+        pai: Tile,
+        /// Tiles from the caller's hand
+        consumed: [Tile; 2],
+    },
+    /// Pon of the target's discard
+    Pon {
+        /// Who called
+        actor: u8,
+        /// Whose discard was called
+        target: u8,
+        /// The called tile
+        pai: Tile,
+        /// Tiles from the caller's hand
+        consumed: [Tile; 2],
+    },
+    /// Open kan of the target's discard
+    Daiminkan {
+        /// Who called
+        actor: u8,
+        /// Whose discard was called
+        target: u8,
+        /// The called tile
+        pai: Tile,
+        /// Tiles from the caller's hand
+        consumed: [Tile; 3],
+    },
+    /// A tile added to a pon
+    Kakan {
+        /// Who added the tile
+        actor: u8,
+        /// The added tile
+        pai: Tile,
+        /// Tiles of the pon
+        consumed: [Tile; 3],
+    },
+    /// A closed kan
+    Ankan {
+        /// Who declared the kan
+        actor: u8,
+        /// The four tiles
+        consumed: [Tile; 4],
+    },
+    /// A seat declares riichi, their next discard is the riichi tile
+    Reach {
+        /// Who declared riichi
+        actor: u8,
+    },
+    /// The riichi discard was not called for a win and the deposit was paid
+    ReachAccepted {
+        /// Who paid the deposit
+        actor: u8,
+    },
+    /// A new dora indicator is revealed
+    Dora {
+        /// The revealed indicator
+        dora_marker: Tile,
+    },
+    /// A seat wins, from their own draw when the target is the actor
+    Hora {
+        /// Who won
+        actor: u8,
+        /// Who dealt in
+        target: u8,
+        /// The winning tile
+        pai: Option<Tile>,
+        /// Ura dora indicators
+        ura_markers: Vec<Tile>,
+        /// Point changes of all seats
+        deltas: Option<[i32; 4]>,
+    },
+    /// The hand ends in a draw
+    Ryukyoku {
+        /// Point changes of all seats
+        deltas: Option<[i32; 4]>,
+    },
+    /// The hand is over
+    EndKyoku,
+    /// The game is over
+    EndGame,
+}
+
+impl MjaiEvent {
+    /// Parse one MJAI JSON event
+    pub fn from_json(json: &str) -> Result<MjaiEvent, RiichiError> {
+        let event: Value = match serde_json::from_str(json) {
+            Ok(event) => event,
+            Err(_) => return Err(RiichiError::new(187, "Not an MJAI event")),
+        };
+
+        let event_type = field(&event, "type")?.as_str().unwrap_or_default();
+        let event = match event_type {
+            "start_game" => MjaiEvent::StartGame {
+                id: match event.get("id") {
+                    Some(_) => Some(seat(&event, "id")?),
+                    None => None,
+                },
+                names: match event["names"].as_array() {
+                    Some(names) => names
+                        .iter()
+                        .map(|name| name.as_str().unwrap_or_default().to_string())
+                        .collect(),
+                    None => vec![],
+                },
+            },
+            "start_kyoku" => {
+                let mut tehais: [Vec<Option<Tile>>; 4] = Default::default();
+                let hands = match field(&event, "tehais")?.as_array() {
+                    Some(hands) if hands.len() == 4 => hands,
+                    _ => return Err(RiichiError::new(187, "Wrong number of hands")),
+                };
+                for (tehai, hand) in tehais.iter_mut().zip(hands.iter()) {
+                    *tehai = match hand.as_array() {
+                        Some(tiles) => tiles.iter().map(hidden_tile).collect::<Result<_, _>>()?,
+                        None => return Err(RiichiError::new(187, "Wrong hand")),
+                    };
+                }
+
+                let bakaze = tile(&event, "bakaze")?;
+                let bakaze = match bakaze.get_id() {
+                    28..=31 => bakaze.get_id() - 27,
+                    _ => return Err(RiichiError::new(196, "Prevalent wind has to be a wind")),
+                };
+
+                MjaiEvent::StartKyoku {
+                    bakaze,
+                    kyoku: number(&event, "kyoku")? as u8,
+                    honba: number(&event, "honba")? as u8,
+                    kyotaku: number(&event, "kyotaku")? as u8,
+                    oya: seat(&event, "oya")?,
+                    dora_marker: tile(&event, "dora_marker")?,
+                    scores: deltas(field(&event, "scores")?)?,
+                    tehais,
+                }
+            }
+            "tsumo" => MjaiEvent::Tsumo {
+                actor: seat(&event, "actor")?,
+                pai: hidden_tile(field(&event, "pai")?)?,
+            },
+            "dahai" => MjaiEvent::Dahai {
+                actor: seat(&event, "actor")?,
+                pai: tile(&event, "pai")?,
+                tsumogiri: event["tsumogiri"].as_bool().unwrap_or_default(),
+            },
+            "chi" | "pon" | "daiminkan" => {
+                let actor = seat(&event, "actor")?;
+                let target = seat(&event, "target")?;
+                let pai = tile(&event, "pai")?;
+                let consumed = tiles(field(&event, "consumed")?)?;
+
+                match (event_type, &consumed[..]) {
+                    ("chi", [a, b]) => MjaiEvent::Chi {
+                        actor,
+                        target,
+                        pai,
+                        consumed: [*a, *b],
+                    },
+                    ("pon", [a, b]) => MjaiEvent::Pon {
+                        actor,
+                        target,
+                        pai,
+                        consumed: [*a, *b],
+                    },
+                    ("daiminkan", [a, b, c]) => MjaiEvent::Daiminkan {
+                        actor,
+                        target,
+                        pai,
+                        consumed: [*a, *b, *c],
+                    },
+                    _ => return Err(RiichiError::new(191, "These tiles can't make this call")),
+                }
+            }
+            "kakan" => match tiles(field(&event, "consumed")?)?[..] {
+                [a, b, c] => MjaiEvent::Kakan {
+                    actor: seat(&event, "actor")?,
+                    pai: tile(&event, "pai")?,
+                    consumed: [a, b, c],
+                },
+                _ => return Err(RiichiError::new(191, "These tiles can't make this call")),
+            },
+            "ankan" => match tiles(field(&event, "consumed")?)?[..] {
+                [a, b, c, d] => MjaiEvent::Ankan {
+                    actor: seat(&event, "actor")?,
+                    consumed: [a, b, c, d],
+                },
+                _ => return Err(RiichiError::new(191, "These tiles can't make this call")),
+            },
+            "reach" => MjaiEvent::Reach {
+                actor: seat(&event, "actor")?,
+            },
+            "reach_accepted" => MjaiEvent::ReachAccepted {
+                actor: seat(&event, "actor")?,
+            },
+            "dora" => MjaiEvent::Dora {
+                dora_marker: tile(&event, "dora_marker")?,
+            },
+            "hora" => MjaiEvent::Hora {
+                actor: seat(&event, "actor")?,
+                target: seat(&event, "target")?,
+                pai: match event.get("pai") {
+                    Some(pai) => hidden_tile(pai)?,
+                    None => None,
+                },
+                ura_markers: match event.get("ura_markers") {
+                    Some(markers) => tiles(markers)?,
+                    None => vec![],
+                },
+                deltas: match event.get("deltas") {
+                    Some(changes) => Some(deltas(changes)?),
+                    None => None,
+                },
+            },
+            "ryukyoku" => MjaiEvent::Ryukyoku {
+                deltas: match event.get("deltas") {
+                    Some(changes) => Some(deltas(changes)?),
+                    None => None,
+                },
+            },
+            "end_kyoku" => MjaiEvent::EndKyoku,
+            "end_game" => MjaiEvent::EndGame,
+            event_type => {
+                return Err(RiichiError::new(
+                    187,
+                    &format!("Unknown MJAI event {}", event_type)[..],
+                ))
+            }
+        };
+
+        Ok(event)
+    }
+
+    /// The event as MJAI JSON
+    pub fn to_json(&self) -> String {
+        let names = |tiles: &[Tile]| -> Vec<String> { tiles.iter().map(Tile::to_mjai).collect() };
+        let hidden = |tile: &Option<Tile>| match tile {
+            Some(tile) => tile.to_mjai(),
+            None => String::from("?"),
+        };
+
+        let event = match self {
+            MjaiEvent::StartGame { id, names } => {
+                let mut event = json!({"type": "start_game", "names": names});
+                if let Some(id) = id {
+                    event["id"] = json!(id);
+                }
+                event
+            }
+            MjaiEvent::StartKyoku {
+                bakaze,
+                kyoku,
+                honba,
+                kyotaku,
+                oya,
+                dora_marker,
+                scores,
+                tehais,
+            } => {
+                let tehais: Vec<Vec<String>> = tehais
+                    .iter()
+                    .map(|tehai| tehai.iter().map(hidden).collect())
+                    .collect();
+                let bakaze = ["E", "S", "W", "N"][(*bakaze as usize + 3) % 4];
+                json!({
+                    "type": "start_kyoku",
+                    "bakaze": bakaze,
+                    "kyoku": kyoku,
+                    "honba": honba,
+                    "kyotaku": kyotaku,
+                    "oya": oya,
+                    "dora_marker": dora_marker.to_mjai(),
+                    "scores": scores,
+                    "tehais": tehais,
+                })
+            }
+            MjaiEvent::Tsumo { actor, pai } => {
+                json!({"type": "tsumo", "actor": actor, "pai": hidden(pai)})
+            }
+            MjaiEvent::Dahai {
+                actor,
+                pai,
+                tsumogiri,
+            } => json!({
+                "type": "dahai",
+                "actor": actor,
+                "pai": pai.to_mjai(),
+                "tsumogiri": tsumogiri,
+            }),
+            MjaiEvent::Chi {
+                actor,
+                target,
+                pai,
+                consumed,
+            } => json!({
+                "type": "chi",
+                "actor": actor,
+                "target": target,
+                "pai": pai.to_mjai(),
+                "consumed": names(consumed),
+            }),
+            MjaiEvent::Pon {
+                actor,
+                target,
+                pai,
+                consumed,
+            } => json!({
+                "type": "pon",
+                "actor": actor,
+                "target": target,
+                "pai": pai.to_mjai(),
+                "consumed": names(consumed),
+            }),
+            MjaiEvent::Daiminkan {
+                actor,
+                target,
+                pai,
+                consumed,
+            } => json!({
+                "type": "daiminkan",
+                "actor": actor,
+                "target": target,
+                "pai": pai.to_mjai(),
+                "consumed": names(consumed),
+            }),
+            MjaiEvent::Kakan {
+                actor,
+                pai,
+                consumed,
+            } => json!({
+                "type": "kakan",
+                "actor": actor,
+                "pai": pai.to_mjai(),
+                "consumed": names(consumed),
+            }),
+            MjaiEvent::Ankan { actor, consumed } => {
+                json!({"type": "ankan", "actor": actor, "consumed": names(consumed)})
+            }
+            MjaiEvent::Reach { actor } => json!({"type": "reach", "actor": actor}),
+            MjaiEvent::ReachAccepted { actor } => {
+                json!({"type": "reach_accepted", "actor": actor})
+            }
+            MjaiEvent::Dora { dora_marker } => {
+                json!({"type": "dora", "dora_marker": dora_marker.to_mjai()})
+            }
+            MjaiEvent::Hora {
+                actor,
+                target,
+                pai,
+                ura_markers,
+                deltas,
+            } => {
+                let mut event = json!({
+                    "type": "hora",
+                    "actor": actor,
+                    "target": target,
+                    "ura_markers": names(ura_markers),
+                });
+                if let Some(pai) = pai {
+                    event["pai"] = json!(pai.to_mjai());
+                }
+                if let Some(deltas) = deltas {
+                    event["deltas"] = json!(deltas);
+                }
+                event
+            }
+            MjaiEvent::Ryukyoku { deltas } => {
+                let mut event = json!({"type": "ryukyoku"});
+                if let Some(deltas) = deltas {
+                    event["deltas"] = json!(deltas);
+                }
+                event
+            }
+            MjaiEvent::EndKyoku => json!({"type": "end_kyoku"}),
+            MjaiEvent::EndGame => json!({"type": "end_game"}),
+        };
+
+        event.to_string()
+    }
+}
+
+/// A seat's table, kept up to date by the MJAI events the seat receives
+pub struct MjaiTable {
+    seat: Option<u8>,
+    table: Table,
+    /// Another seat's discard or added kan I didn't win on yet
+    passed_tile: Option<Tile>,
+}
+
+impl MjaiTable {
+    /// A table with these rules, waiting for start_game
+    pub fn new(rules: Rules) -> MjaiTable {
+        let mut table = Table::from_map(&Map::new()).unwrap();
+        table.set_rules(rules);
+
+        MjaiTable {
+            seat: None,
+            table,
+            passed_tile: None,
+        }
+    }
+
+    /// The current table, from my point of view
+    pub fn get_table(&self) -> &Table {
+        &self.table
+    }
+
+    /// My seat, known after start_game (or set by hand)
+    pub fn get_seat(&self) -> Option<u8> {
+        self.seat
+    }
+
+    /// Set my seat, for servers that don't send it in start_game
+    pub fn set_seat(&mut self, seat: u8) {
+        self.seat = Some(seat);
+        self.table.set_my_initial_seat_wind(seat + 1);
+    }
+
+    /// Update the table with the next event
+    pub fn apply(&mut self, event: &MjaiEvent) -> Result<(), RiichiError> {
+        if let MjaiEvent::StartGame { id, .. } = event {
+            if let Some(id) = id {
+                self.set_seat(*id);
+            }
+            return Ok(());
+        }
+
+        let me = match self.seat {
+            Some(seat) => seat,
+            None => return Err(RiichiError::new(193, "My seat is not known yet")),
+        };
+        let player = |seat: u8| (seat + 4 - me) % 4;
+
+        // I didn't win on the last discard, it can make me furiten
+        if let Some(tile) = self.passed_tile.take() {
+            if !matches!(event, MjaiEvent::Hora { actor, .. } if *actor == me) {
+                self.table.pass_on_tile(&tile);
+            }
+        }
+
+        let table = &mut self.table;
+        match event {
+            MjaiEvent::StartGame { .. } => {}
+            MjaiEvent::StartKyoku {
+                bakaze,
+                kyoku,
+                honba,
+                kyotaku,
+                oya,
+                dora_marker,
+                scores,
+                tehais,
+            } => {
+                let mut hand = vec![];
+                for tile in tehais[me as usize].iter() {
+                    match tile {
+                        Some(tile) => hand.push(Some(*tile)),
+                        None => return Err(RiichiError::new(195, "My hand is not known")),
+                    }
+                }
+
+                let mut new_table = Table::from_map(&Map::new()).unwrap();
+                if let Some(rules) = table.get_rules() {
+                    new_table.set_rules(*rules);
+                }
+                *table = new_table;
+
+                table.set_my_initial_seat_wind(me + 1);
+                table.set_my_hand(Hand::new(hand));
+                table.set_prevalent_wind(*bakaze);
+                table.set_my_seat_wind((me + 4 - *oya) % 4 + 1);
+                table.set_dealer_turn(*kyoku);
+                table.set_tsumibo(*honba);
+                table.set_riichi_sticks(*kyotaku);
+                table.add_dora_indicator(*dora_marker);
+                // 136 tiles - 14 in the dead wall - 4 * 13 dealt
+                table.set_tiles_remaining(70);
+                for seat in 0..4 {
+                    table.set_points(player(seat), scores[seat as usize]);
+                }
+            }
+            MjaiEvent::Tsumo { actor, pai } => {
+                table.decrement_tiles_remaining();
+                if *actor == me {
+                    let mut tile = match pai {
+                        Some(tile) => *tile,
+                        None => return Err(RiichiError::new(197, "My draw is hidden")),
+                    };
+                    tile.is_draw = true;
+
+                    let mut hand = table.get_my_hand().clone();
+                    hand.reset_drawn_tiles();
+                    hand.add_tile(tile);
+                    table.set_my_hand(hand);
+                }
+            }
+            MjaiEvent::Dahai {
+                actor,
+                pai,
+                tsumogiri,
+            } => {
+                let mut tile = *pai;
+                tile.is_tsumogiri = *tsumogiri;
+                if table.get_riichi_declaring_player() == Some(player(*actor)) {
+                    tile.is_riichi = true;
+                    table.unset_riichi_declaring_player();
+                }
+
+                if *actor == me {
+                    let mut hand = my_hand_with(table, &[*pai])?;
+                    hand.remove_tile(pai);
+                    hand.reset_drawn_tiles();
+                    table.set_my_hand(hand);
+                    table.set_my_rinshan(false);
+                } else {
+                    self.passed_tile = Some(tile);
+                }
+
+                table.add_tile_to_discards(player(*actor), tile);
+                table.add_tile_to_visible_tiles(tile);
+            }
+            MjaiEvent::Chi {
+                actor,
+                target,
+                pai,
+                consumed,
+            }
+            | MjaiEvent::Pon {
+                actor,
+                target,
+                pai,
+                consumed,
+            } => {
+                let is_chi = matches!(event, MjaiEvent::Chi { .. });
+                let action = if is_chi {
+                    Action::Chi(consumed[0], consumed[1])
+                } else {
+                    Action::Pon(consumed[0], consumed[1])
+                };
+                let called = called_tile(pai, *actor, *target)?;
+                let shape = if is_chi {
+                    let mut tiles = [called, consumed[0], consumed[1]];
+                    tiles.sort();
+                    OpenShape::Chi(tiles)
+                } else {
+                    OpenShape::Pon([called, consumed[0], consumed[1]])
+                };
+
+                if *actor == me {
+                    let forbidden = table.forbidden_discards(pai, &action);
+                    call(table, called, consumed, &shape)?;
+                    table.set_my_forbidden_discards(forbidden);
+                } else {
+                    add_call(table, player(*actor), consumed, CompleteShape::Open(shape));
+                }
+            }
+            MjaiEvent::Daiminkan {
+                actor,
+                target,
+                pai,
+                consumed,
+            } => {
+                let called = called_tile(pai, *actor, *target)?;
+                let shape = OpenShape::Kan(OpenKan::Daiminkan([
+                    called,
+                    consumed[0],
+                    consumed[1],
+                    consumed[2],
+                ]));
+
+                if *actor == me {
+                    call(table, called, consumed, &shape)?;
+                    table.set_my_rinshan(true);
+                } else {
+                    add_call(table, player(*actor), consumed, CompleteShape::Open(shape));
+                }
+            }
+            MjaiEvent::Kakan { actor, pai, .. } => {
+                table.add_shouminkan(player(*actor), pai)?;
+                if *actor == me {
+                    table.set_my_rinshan(true);
+                } else {
+                    table.add_tile_to_visible_tiles(*pai);
+                    // the added tile can be robbed
+                    self.passed_tile = Some(*pai);
+                }
+            }
+            MjaiEvent::Ankan { actor, consumed } => {
+                if consumed.iter().any(|tile| *tile != consumed[0]) {
+                    return Err(RiichiError::new(191, "These tiles can't make this call"));
+                }
+
+                let kan = ClosedShape::Kantsu(*consumed);
+                if *actor == me {
+                    let mut hand = my_hand_with(table, consumed)?;
+                    hand.reset_drawn_tiles();
+                    hand.add_closed_kan(kan);
+                    table.set_my_hand(hand);
+                    table.set_my_rinshan(true);
+                } else {
+                    add_call(table, player(*actor), consumed, CompleteShape::Closed(kan));
+                }
+            }
+            MjaiEvent::Reach { actor } => {
+                table.set_riichi_declaring_player(player(*actor));
+                if *actor == me {
+                    if table.get_my_discards().is_empty() && !table.any_calls() {
+                        table.set_my_double_riichi(true);
+                    } else {
+                        table.set_my_riichi(true);
+                    }
+                }
+            }
+            MjaiEvent::ReachAccepted { actor } => {
+                let mut deposit = [0; 4];
+                deposit[player(*actor) as usize] = -1000;
+                table.apply_point_deltas(&deposit);
+                table.set_riichi_sticks(table.get_riichi_sticks() + 1);
+            }
+            MjaiEvent::Dora { dora_marker } => table.add_dora_indicator(*dora_marker),
+            MjaiEvent::Hora {
+                ura_markers,
+                deltas,
+                ..
+            } => {
+                table.set_ura_dora_indicators(ura_markers.clone());
+                if let Some(deltas) = deltas {
+                    table.apply_point_deltas(&relative(deltas, me));
+                    table.set_riichi_sticks(0);
+                }
+            }
+            MjaiEvent::Ryukyoku { deltas } => {
+                if let Some(deltas) = deltas {
+                    table.apply_point_deltas(&relative(deltas, me));
+                }
+            }
+            MjaiEvent::EndKyoku | MjaiEvent::EndGame => {}
+        }
+
+        Ok(())
+    }
+}
+
+/// The called tile knows who it came from (1 = shimocha, 2 = toimen, 3 = kamicha)
+fn called_tile(pai: &Tile, actor: u8, target: u8) -> Result<Tile, RiichiError> {
+    if actor == target {
+        return Err(RiichiError::new(
+            190,
+            "Tiles can only be called from players 1 - 3",
+        ));
+    }
+
+    let mut tile = *pai;
+    tile.called_from = (target + 4 - actor) % 4;
+    tile.is_riichi = false;
+    tile.is_tsumogiri = false;
+
+    Ok(tile)
+}
+
+/// A copy of my hand, if it has all of these tiles closed
+fn my_hand_with(table: &Table, tiles: &[Tile]) -> Result<Hand, RiichiError> {
+    let hand = match table.get_my_hand_option() {
+        Some(hand) => hand.clone(),
+        None => return Err(RiichiError::new(181, "No hand was dealt yet")),
+    };
+
+    let mut closed = hand.get_34_array(true);
+    for tile in tiles.iter() {
+        let count = &mut closed[tile.get_id_minus_1() as usize];
+        if *count == 0 {
+            return Err(RiichiError::new(183, "Called tiles are not in the hand"));
+        }
+        *count -= 1;
+    }
+
+    Ok(hand)
+}
+
+/// I call a discard with these tiles from my hand
+fn call(
+    table: &mut Table,
+    called: Tile,
+    consumed: &[Tile],
+    shape: &OpenShape,
+) -> Result<(), RiichiError> {
+    let mut hand = my_hand_with(table, consumed)?;
+    hand.reset_drawn_tiles();
+    hand.add_tile(called);
+    hand.add_open_shape(shape);
+    table.set_my_hand(hand);
+
+    Ok(())
+}
+
+/// Another player's call, their tiles from the hand become visible
+fn add_call(table: &mut Table, player: u8, consumed: &[Tile], shape: CompleteShape) {
+    for tile in consumed.iter() {
+        table.add_tile_to_visible_tiles(*tile);
+    }
+
+    let is_open = matches!(shape, CompleteShape::Open(_));
+    let tile_count = match shape {
+        CompleteShape::Open(OpenShape::Kan(_)) | CompleteShape::Closed(_) => 4,
+        _ => 3,
+    };
+    table.add_open_shape(
+        player,
+        Shape::new(ShapeType::Complete(shape), tile_count, is_open),
+    );
+}
+
+/// Seat order deltas from my point of view (0 = me, 1 = shimocha...)
+fn relative(deltas: &[i32; 4], me: u8) -> [i32; 4] {
+    let mut relative = [0; 4];
+    for (player, delta) in relative.iter_mut().enumerate() {
+        *delta = deltas[(me as usize + player) % 4];
+    }
+
+    relative
+}
+
+fn field<'a>(event: &'a Value, name: &str) -> Result<&'a Value, RiichiError> {
+    match event.get(name) {
+        Some(value) => Ok(value),
+        None => Err(RiichiError::new(
+            188,
+            &format!("Missing attribute {}", name)[..],
+        )),
+    }
+}
+
+fn number(event: &Value, name: &str) -> Result<u32, RiichiError> {
+    match field(event, name)?.as_u64() {
+        Some(number) => Ok(number as u32),
+        None => Err(RiichiError::new(
+            189,
+            &format!("{} is not a number", name)[..],
+        )),
+    }
+}
+
+fn seat(event: &Value, name: &str) -> Result<u8, RiichiError> {
+    match number(event, name)? {
+        seat if seat < 4 => Ok(seat as u8),
+        _ => Err(RiichiError::new(180, "Seat has to be 0 - 3")),
+    }
+}
+
+fn tile(event: &Value, name: &str) -> Result<Tile, RiichiError> {
+    Tile::from_mjai(field(event, name)?.as_str().unwrap_or_default())
+}
+
+/// "?" is a tile I can't see
+fn hidden_tile(value: &Value) -> Result<Option<Tile>, RiichiError> {
+    match value.as_str() {
+        Some("?") => Ok(None),
+        name => Ok(Some(Tile::from_mjai(name.unwrap_or_default())?)),
+    }
+}
+
+fn tiles(value: &Value) -> Result<Vec<Tile>, RiichiError> {
+    match value.as_array() {
+        Some(tiles) => tiles
+            .iter()
+            .map(|tile| Tile::from_mjai(tile.as_str().unwrap_or_default()))
+            .collect(),
+        None => Err(RiichiError::new(187, "Wrong tile list")),
+    }
+}
+
+fn deltas(value: &Value) -> Result<[i32; 4], RiichiError> {
+    let numbers: Vec<i32> = match value.as_array() {
+        Some(numbers) => numbers
+            .iter()
+            .filter_map(|number| number.as_i64().map(|number| number as i32))
+            .collect(),
+        None => vec![],
+    };
+
+    match numbers[..] {
+        [a, b, c, d] => Ok([a, b, c, d]),
+        _ => Err(RiichiError::new(187, "Wrong number of points")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_round_trip() {
+        let events = [
+            r#"{"type":"start_game","id":1,"names":["a","b","c","d"]}"#,
+            r#"{"type":"tsumo","actor":2,"pai":"?"}"#,
+            r#"{"type":"dahai","actor":0,"pai":"5mr","tsumogiri":true}"#,
+            r#"{"type":"chi","actor":1,"target":0,"pai":"5mr","consumed":["4m","6m"]}"#,
+            r#"{"type":"pon","actor":3,"target":1,"pai":"P","consumed":["P","P"]}"#,
+            r#"{"type":"daiminkan","actor":2,"target":3,"pai":"9s","consumed":["9s","9s","9s"]}"#,
+            r#"{"type":"kakan","actor":3,"pai":"P","consumed":["P","P","P"]}"#,
+            r#"{"type":"ankan","actor":0,"consumed":["N","N","N","N"]}"#,
+            r#"{"type":"reach","actor":2}"#,
+            r#"{"type":"reach_accepted","actor":2}"#,
+            r#"{"type":"dora","dora_marker":"C"}"#,
+            r#"{"type":"hora","actor":2,"target":0,"pai":"3p","ura_markers":["1s"],"deltas":[-3900,0,4900,0]}"#,
+            r#"{"type":"ryukyoku","deltas":[1500,-1500,1500,-1500]}"#,
+            r#"{"type":"end_kyoku"}"#,
+            r#"{"type":"end_game"}"#,
+        ];
+
+        for json in events.iter() {
+            let event = MjaiEvent::from_json(json).unwrap();
+            assert_eq!(MjaiEvent::from_json(&event.to_json()[..]).unwrap(), event);
+            let expected: Value = serde_json::from_str(json).unwrap();
+            let emitted: Value = serde_json::from_str(&event.to_json()[..]).unwrap();
+            assert_eq!(emitted, expected);
+        }
+
+        let red = MjaiEvent::from_json(events[2]).unwrap();
+        match red {
+            MjaiEvent::Dahai { pai, .. } => assert!(pai.is_red),
+            _ => panic!("Not a discard"),
+        }
+    }
+
+    #[test]
+    fn start_kyoku_round_trip() {
+        let json = r#"{"type":"start_kyoku","bakaze":"S","kyoku":2,"honba":1,"kyotaku":0,"oya":1,"dora_marker":"7p","scores":[25000,25000,25000,25000],"tehais":[["1m","2m","3m","4p","5p","6p","7s","8s","9s","E","E","S","S"],["?","?","?","?","?","?","?","?","?","?","?","?","?"],["?","?","?","?","?","?","?","?","?","?","?","?","?"],["?","?","?","?","?","?","?","?","?","?","?","?","?"]]}"#;
+        let event = MjaiEvent::from_json(json).unwrap();
+        match &event {
+            MjaiEvent::StartKyoku { bakaze, tehais, .. } => {
+                assert_eq!(*bakaze, 2);
+                assert_eq!(tehais[0].len(), 13);
+                assert_eq!(tehais[1][0], None);
+            }
+            _ => panic!("Not a hand start"),
+        }
+
+        let expected: Value = serde_json::from_str(json).unwrap();
+        let emitted: Value = serde_json::from_str(&event.to_json()[..]).unwrap();
+        assert_eq!(emitted, expected);
+    }
+
+    #[test]
+    fn table_follows_events() {
+        let events = [
+            r#"{"type":"start_game","id":0,"names":["a","b","c","d"]}"#,
+            r#"{"type":"start_kyoku","bakaze":"E","kyoku":1,"honba":0,"kyotaku":0,"oya":0,"dora_marker":"1m","scores":[25000,25000,25000,25000],"tehais":[["1m","2m","3m","4p","5p","6p","7s","8s","9s","E","E","S","S"],["?","?","?","?","?","?","?","?","?","?","?","?","?"],["?","?","?","?","?","?","?","?","?","?","?","?","?"],["?","?","?","?","?","?","?","?","?","?","?","?","?"]]}"#,
+            r#"{"type":"tsumo","actor":0,"pai":"W"}"#,
+            r#"{"type":"dahai","actor":0,"pai":"W","tsumogiri":true}"#,
+            r#"{"type":"tsumo","actor":1,"pai":"?"}"#,
+            r#"{"type":"dahai","actor":1,"pai":"E","tsumogiri":false}"#,
+            r#"{"type":"pon","actor":0,"target":1,"pai":"E","consumed":["E","E"]}"#,
+            r#"{"type":"dahai","actor":0,"pai":"1m","tsumogiri":false}"#,
+            r#"{"type":"tsumo","actor":1,"pai":"?"}"#,
+            r#"{"type":"reach","actor":1}"#,
+            r#"{"type":"dahai","actor":1,"pai":"9m","tsumogiri":true}"#,
+            r#"{"type":"reach_accepted","actor":1}"#,
+            r#"{"type":"chi","actor":2,"target":1,"pai":"9m","consumed":["7m","8m"]}"#,
+            r#"{"type":"dahai","actor":2,"pai":"N","tsumogiri":false}"#,
+        ];
+
+        let mut mjai = MjaiTable::new(Rules::default());
+        for json in events.iter() {
+            mjai.apply(&MjaiEvent::from_json(json).unwrap()).unwrap();
+        }
+
+        let table = mjai.get_table();
+        assert_eq!(mjai.get_seat(), Some(0));
+        assert_eq!(table.get_my_seat_wind(), Some(1));
+        assert_eq!(table.get_tiles_remaining(), Some(67));
+        assert_eq!(table.get_my_hand().get_shapes().len(), 1);
+        assert_eq!(table.get_my_hand().count_tiles(), 13);
+        assert_eq!(table.get_my_discards().len(), 2);
+        assert!(table.get_discards(1)[1].is_riichi);
+        assert!(table.get_p1_riichi());
+        assert_eq!(table.get_riichi_declaring_player(), None);
+        assert_eq!(table.get_points(1), Some(24000));
+        assert_eq!(table.get_riichi_sticks(), 1);
+        assert_eq!(table.get_discards(2).len(), 1);
+        assert!(table.any_calls());
+
+        let hora = r#"{"type":"hora","actor":2,"target":0,"pai":"N","ura_markers":[],"deltas":[-2000,0,3000,0]}"#;
+        mjai.apply(&MjaiEvent::from_json(hora).unwrap()).unwrap();
+        assert_eq!(mjai.get_table().get_points(0), Some(23000));
+        assert_eq!(mjai.get_table().get_points(2), Some(28000));
+        assert_eq!(mjai.get_table().get_riichi_sticks(), 0);
+    }
+
+    #[test]
+    fn wrong_events() {
+        assert_eq!(MjaiEvent::from_json("{").unwrap_err().code, 187);
+        assert_eq!(
+            MjaiEvent::from_json(r#"{"type":"nuki","actor":0}"#)
+                .unwrap_err()
+                .code,
+            187
+        );
+        assert_eq!(
+            MjaiEvent::from_json(r#"{"type":"reach"}"#)
+                .unwrap_err()
+                .code,
+            188
+        );
+        assert_eq!(
+            MjaiEvent::from_json(r#"{"type":"dahai","actor":0,"pai":"0m"}"#)
+                .unwrap_err()
+                .code,
+            104
+        );
+
+        let mut mjai = MjaiTable::new(Rules::default());
+        let reach = MjaiEvent::Reach { actor: 0 };
+        assert_eq!(mjai.apply(&reach).unwrap_err().code, 193);
+
+        mjai.set_seat(0);
+        let discard = MjaiEvent::Dahai {
+            actor: 0,
+            pai: Tile::from_mjai("E").unwrap(),
+            tsumogiri: false,
+        };
+        assert_eq!(mjai.apply(&discard).unwrap_err().code, 181);
+
+        let tsumo = MjaiEvent::Tsumo {
+            actor: 0,
+            pai: None,
+        };
+        assert_eq!(mjai.apply(&tsumo).unwrap_err().code, 197);
+
+        let e = Tile::from_mjai("E").unwrap();
+        let kakan = MjaiEvent::Kakan {
+            actor: 2,
+            pai: e,
+            consumed: [e, e, e],
+        };
+        assert_eq!(mjai.apply(&kakan).unwrap_err().code, 194);
+
+        let start = r#"{"type":"start_kyoku","bakaze":"E","kyoku":1,"honba":0,"kyotaku":0,"oya":0,"dora_marker":"1m","scores":[25000,25000,25000,25000],"tehais":[["1m","2m","3m","4p","5p","6p","7s","8s","9s","E","E","S","S"],["?","?","?","?","?","?","?","?","?","?","?","?","?"],["?","?","?","?","?","?","?","?","?","?","?","?","?"],["?","?","?","?","?","?","?","?","?","?","?","?","?"]]}"#;
+        assert_eq!(
+            MjaiEvent::from_json(&start.replace(r#""bakaze":"E""#, r#""bakaze":"1m""#))
+                .unwrap_err()
+                .code,
+            196
+        );
+
+        mjai.set_seat(1);
+        let start = MjaiEvent::from_json(start).unwrap();
+        assert_eq!(mjai.apply(&start).unwrap_err().code, 195);
+    }
+}
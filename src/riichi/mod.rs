@@ -1,14 +1,26 @@
 #[cfg(feature = "fast_shanten")]
 /// Fast shanten calculator
 mod fast_hand_calculator;
+/// Fu counting module
+pub mod fu;
+/// Game engine module
+pub mod game;
 /// Hand representation module
 pub mod hand;
+/// MJAI protocol module
+pub mod mjai;
+/// Final placement module
+pub mod placement;
+/// Replay formats module
+pub mod replay;
 /// Defines the error struct
 pub mod riichi_error;
 /// Rules module
 pub mod rules;
 /// Score handling module
 pub mod scores;
+/// Win settlement module
+pub mod settlement;
 #[cfg(not(feature = "fast_shanten"))]
 /// Slow shanten calculator
 mod shanten;
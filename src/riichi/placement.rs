@@ -0,0 +1,92 @@
+use crate::riichi::rules::{Rules, TieBreak};
+
+/// Place and final score of a player at the end of a match
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FinalResult {
+    /// 1 - 4, tied players can share a place
+    pub place: u8,
+    /// Final score in thousands of points with uma and oka (45.3, -12.0...)
+    pub score: f32,
+}
+
+/// Places and final scores from the points at the end of a match.
+/// Players have to be in seat order from the first dealer, so ties can be broken by seat order.
+pub fn final_results(points: &[i32; 4], rules: &Rules) -> [FinalResult; 4] {
+    let mut order: Vec<usize> = (0..4).collect();
+    order.sort_by_key(|player| (-points[*player], *player));
+
+    let mut results = [FinalResult {
+        place: 0,
+        score: 0.0,
+    }; 4];
+    let mut i = 0;
+    while i < 4 {
+        // players order[i..j] share a place
+        let mut j = i + 1;
+        if rules.tie_break == TieBreak::Shared {
+            while j < 4 && points[order[j]] == points[order[i]] {
+                j += 1;
+            }
+        }
+
+        let mut bonus: i32 = rules.uma[i..j].iter().sum();
+        if i == 0 {
+            bonus += rules.oka;
+        }
+        let bonus = bonus as f32 / (j - i) as f32;
+
+        for player in order[i..j].iter() {
+            let score = (points[*player] - rules.return_points) as f32 + bonus;
+            results[*player] = FinalResult {
+                place: i as u8 + 1,
+                score: (score / 100.0).round() / 10.0,
+            };
+        }
+
+        i = j;
+    }
+
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scores(results: &[FinalResult; 4]) -> Vec<f32> {
+        results.iter().map(|r| r.score).collect()
+    }
+
+    #[test]
+    fn uma_and_oka() {
+        let points = [45300, 25000, 18000, 11700];
+
+        let results = final_results(&points, &Rules::tenhou());
+        assert_eq!(scores(&results), vec![55.3, 5.0, -22.0, -38.3]);
+        assert_eq!(results[3].place, 4);
+
+        let results = final_results(&points, &Rules::mleague());
+        assert_eq!(scores(&results), vec![65.3, 5.0, -22.0, -48.3]);
+
+        let results = final_results(&points, &Rules::mahjong_soul());
+        assert_eq!(scores(&results), vec![35.3, 5.0, -12.0, -28.3]);
+    }
+
+    #[test]
+    fn seat_order_ties() {
+        let results = final_results(&[25000; 4], &Rules::tenhou());
+
+        let places: Vec<u8> = results.iter().map(|r| r.place).collect();
+        assert_eq!(places, vec![1, 2, 3, 4]);
+        assert_eq!(scores(&results), vec![35.0, 5.0, -15.0, -25.0]);
+    }
+
+    #[test]
+    fn shared_ties() {
+        let results = final_results(&[20000, 40000, 40000, 20000], &Rules::wrc());
+
+        let places: Vec<u8> = results.iter().map(|r| r.place).collect();
+        assert_eq!(places, vec![3, 1, 1, 3]);
+        assert_eq!(scores(&results), vec![-20.0, 20.0, 20.0, -20.0]);
+    }
+}
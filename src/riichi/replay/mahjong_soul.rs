@@ -0,0 +1,662 @@
+use crate::riichi::replay::{HandDraw, HandStart, HandWin, Replay, ReplayEvent};
+use crate::riichi::riichi_error::RiichiError;
+use crate::riichi::rules::{GameLength, Rules};
+use crate::riichi::settlement::DrawReason;
+use crate::riichi::shapes::{ClosedShape, CompleteShape, OpenKan, OpenShape};
+use crate::riichi::tile::{Tile, TileColor, TileType};
+use serde_json::Value;
+
+impl Replay {
+    /// Reads a decoded Mahjong Soul paipu (game record) JSON, with the game's head and its records
+    pub fn from_mahjong_soul(json: &str) -> Result<Replay, RiichiError> {
+        let paipu: Value = match serde_json::from_str(json) {
+            Ok(paipu) => paipu,
+            Err(_) => return Err(RiichiError::new(187, "Not a Mahjong Soul paipu")),
+        };
+
+        let mut parser = PaipuParser {
+            replay: Replay {
+                players: Default::default(),
+                rules: Rules::mahjong_soul(),
+                events: vec![],
+            },
+            dealer: 0,
+            riichi_sticks: 0,
+            dora_count: 0,
+            pons: Default::default(),
+            last_discard: None,
+        };
+        parser.head(&paipu["head"])?;
+
+        let records = [
+            &paipu["data"]["data"]["records"],
+            &paipu["data"]["records"],
+            &paipu["records"],
+        ];
+        let records: Vec<&Value> = match records.iter().find_map(|records| records.as_array()) {
+            Some(records) => records.iter().collect(),
+            // newer paipus wrap records in actions
+            None => match paipu["data"]["data"]["actions"].as_array() {
+                Some(actions) => actions
+                    .iter()
+                    .map(|action| &action["result"])
+                    .filter(|result| result.is_object())
+                    .collect(),
+                None => return Err(RiichiError::new(188, "Missing attribute records")),
+            },
+        };
+
+        for record in records {
+            let name = record["name"].as_str().unwrap_or_default();
+            let data = &record["data"];
+
+            match name.trim_start_matches(".lq.") {
+                "RecordNewRound" => parser.new_round(data)?,
+                "RecordDealTile" => parser.deal_tile(data)?,
+                "RecordDiscardTile" => parser.discard_tile(data)?,
+                "RecordChiPengGang" => parser.chi_peng_gang(data)?,
+                "RecordAnGangAddGang" => parser.an_gang_add_gang(data)?,
+                "RecordHule" => parser.hule(data)?,
+                "RecordNoTile" => parser.no_tile(data)?,
+                "RecordLiuJu" => parser.liu_ju(data)?,
+                _ => {}
+            }
+        }
+
+        Ok(parser.replay)
+    }
+}
+
+struct PaipuParser {
+    replay: Replay,
+    dealer: u8,
+    riichi_sticks: u8,
+    /// Dora indicators revealed so far, records send all of them every time
+    dora_count: usize,
+    /// Pons that can become an added kan
+    pons: [Vec<[Tile; 3]>; 4],
+    /// Who discarded (or added to a kan) last, and the tile
+    last_discard: Option<(u8, Tile)>,
+}
+
+impl PaipuParser {
+    fn push(&mut self, event: ReplayEvent) {
+        self.replay.events.push(event);
+    }
+
+    /// Player names and the game mode: 1 = east, 2 = south, 11 and 12 are 3 player modes
+    fn head(&mut self, head: &Value) -> Result<(), RiichiError> {
+        if let Some(accounts) = head["accounts"].as_array() {
+            for account in accounts.iter() {
+                let seat = account["seat"].as_u64().unwrap_or_default() as usize;
+                if seat < 4 {
+                    self.replay.players[seat] =
+                        account["nickname"].as_str().unwrap_or_default().to_string();
+                }
+            }
+        }
+
+        let config = &head["config"];
+        if let Some(mode) = config["mode"]["mode"].as_u64() {
+            if mode >= 10 {
+                return Err(RiichiError::new(186, "Only 4 player games are supported"));
+            }
+
+            self.replay.rules.game_length = if mode == 1 {
+                GameLength::Tonpuusen
+            } else {
+                GameLength::Hanchan
+            };
+        }
+
+        // the number of red 5s, the 4th one is a pinzu
+        if let Some(count) = config["mode"]["detail_rule"]["dora_count"].as_u64() {
+            self.replay.rules.aka = match count {
+                0 => [0, 0, 0],
+                4 => [1, 2, 1],
+                _ => [1, 1, 1],
+            };
+        }
+
+        Ok(())
+    }
+
+    /// The dealer gets 14 tiles, the last one is their first draw
+    fn new_round(&mut self, data: &Value) -> Result<(), RiichiError> {
+        let dealer = seat(data, "ju")?;
+        let scores = numbers(field(data, "scores")?)?;
+        if scores.len() < 4 {
+            return Err(RiichiError::new(187, "Wrong number of scores"));
+        }
+
+        let mut hands: [Vec<Tile>; 4] = Default::default();
+        for (seat, hand) in hands.iter_mut().enumerate() {
+            *hand = tiles(field(data, &format!("tiles{}", seat))?)?;
+        }
+        let first_draw = match hands[dealer as usize].len() {
+            14 => hands[dealer as usize].pop(),
+            _ => None,
+        };
+
+        let doras = match data["doras"].as_array() {
+            Some(_) => tiles(&data["doras"])?,
+            None => vec![tile(field(data, "dora")?)?],
+        };
+        if doras.is_empty() {
+            return Err(RiichiError::new(188, "Missing attribute doras"));
+        }
+
+        self.dealer = dealer;
+        self.riichi_sticks = data["liqibang"].as_u64().unwrap_or_default() as u8;
+        self.dora_count = 1;
+        self.pons = Default::default();
+        self.last_discard = None;
+
+        self.push(ReplayEvent::Start(HandStart {
+            prevalent_wind: data["chang"].as_u64().unwrap_or_default() as u8 + 1,
+            dealer,
+            honba: data["ben"].as_u64().unwrap_or_default() as u8,
+            riichi_sticks: self.riichi_sticks,
+            dora_indicator: doras[0],
+            points: [scores[0], scores[1], scores[2], scores[3]],
+            hands,
+        }));
+        self.doras(data)?;
+
+        if let Some(tile) = first_draw {
+            self.push(ReplayEvent::Draw { seat: dealer, tile });
+        }
+
+        Ok(())
+    }
+
+    fn deal_tile(&mut self, data: &Value) -> Result<(), RiichiError> {
+        self.riichi_accepted(data)?;
+        self.doras(data)?;
+
+        let seat = seat(data, "seat")?;
+        let tile = tile(field(data, "tile")?)?;
+        self.push(ReplayEvent::Draw { seat, tile });
+
+        Ok(())
+    }
+
+    /// A riichi discard is marked with is_liqi, or is_wliqi for double riichi
+    fn discard_tile(&mut self, data: &Value) -> Result<(), RiichiError> {
+        let seat = seat(data, "seat")?;
+        let mut tile = tile(field(data, "tile")?)?;
+        tile.is_tsumogiri = data["moqie"].as_bool().unwrap_or_default();
+        tile.is_riichi = data["is_liqi"].as_bool().unwrap_or_default()
+            || data["is_wliqi"].as_bool().unwrap_or_default();
+
+        if tile.is_riichi {
+            self.push(ReplayEvent::Riichi { seat });
+        }
+        self.push(ReplayEvent::Discard { seat, tile });
+        self.last_discard = Some((seat, tile));
+
+        // open kans reveal their dora after the discard
+        self.doras(data)
+    }
+
+    /// Chi (type 0), pon (1) or daiminkan (2), froms are the seats the tiles came from
+    fn chi_peng_gang(&mut self, data: &Value) -> Result<(), RiichiError> {
+        self.riichi_accepted(data)?;
+
+        let seat = seat(data, "seat")?;
+        let mut tiles = tiles(field(data, "tiles")?)?;
+        let froms = numbers(field(data, "froms")?)?;
+        let called = match froms.iter().position(|from| *from != seat as i32) {
+            Some(called) if froms.len() == tiles.len() && (0..4).contains(&froms[called]) => called,
+            _ => return Err(RiichiError::new(184, "Call without a called tile")),
+        };
+
+        // the called tile goes first, like in the game
+        let mut called_tile = tiles.remove(called);
+        called_tile.called_from = (froms[called] as u8 + 4 - seat) % 4;
+        tiles.insert(0, called_tile);
+
+        let invalid = || RiichiError::new(191, "These tiles can't make this call");
+        let same = tiles.iter().all(|tile| *tile == tiles[0]);
+        let meld = match (field(data, "type")?.as_u64(), tiles.len()) {
+            (Some(0), 3) => {
+                let mut chi = [tiles[0], tiles[1], tiles[2]];
+                chi.sort();
+                if chi[0].next(false) != Some(chi[1]) || chi[1].next(false) != Some(chi[2]) {
+                    return Err(invalid());
+                }
+
+                OpenShape::Chi(chi)
+            }
+            (Some(1), 3) if same => {
+                let pon = [tiles[0], tiles[1], tiles[2]];
+                self.pons[seat as usize].push(pon);
+                OpenShape::Pon(pon)
+            }
+            (Some(2), 4) if same => {
+                OpenShape::Kan(OpenKan::Daiminkan([tiles[0], tiles[1], tiles[2], tiles[3]]))
+            }
+            _ => return Err(invalid()),
+        };
+
+        self.push(ReplayEvent::Call {
+            seat,
+            meld: CompleteShape::Open(meld),
+        });
+
+        Ok(())
+    }
+
+    /// Ankan (type 3) or added kan (2) of a tile
+    fn an_gang_add_gang(&mut self, data: &Value) -> Result<(), RiichiError> {
+        let seat = seat(data, "seat")?;
+        let tile = tile(field(data, "tiles")?)?;
+        let invalid = || RiichiError::new(191, "These tiles can't make this call");
+
+        let meld = match field(data, "type")?.as_u64() {
+            Some(3) => {
+                let mut plain = tile;
+                plain.is_red = false;
+                let mut kan = [plain; 4];
+                for copy in kan.iter_mut().take(self.reds(&plain) as usize) {
+                    copy.is_red = true;
+                }
+
+                CompleteShape::Closed(ClosedShape::Kantsu(kan))
+            }
+            Some(2) => {
+                let pons = &mut self.pons[seat as usize];
+                let pon = match pons.iter().position(|pon| pon[0] == tile) {
+                    Some(pon) => pons.remove(pon),
+                    None => return Err(invalid()),
+                };
+
+                CompleteShape::Open(OpenShape::Kan(OpenKan::Shouminkan([
+                    pon[0], pon[1], pon[2], tile,
+                ])))
+            }
+            _ => return Err(invalid()),
+        };
+
+        self.push(ReplayEvent::Call { seat, meld });
+        // an added kan can be robbed
+        self.last_discard = Some((seat, tile));
+
+        self.doras(data)
+    }
+
+    /// One or more wins. The liable player (baopai) is their seat + 1, 0 is nobody.
+    fn hule(&mut self, data: &Value) -> Result<(), RiichiError> {
+        let hules = match field(data, "hules")?.as_array() {
+            Some(hules) if !hules.is_empty() => hules,
+            _ => return Err(RiichiError::new(187, "Wrong number of wins")),
+        };
+        let delta_scores = deltas(field(data, "delta_scores")?)?;
+        let liable = match data["baopai"].as_u64() {
+            Some(baopai) if (1..=4).contains(&baopai) => Some(baopai as u8 - 1),
+            _ => None,
+        };
+
+        for (i, hule) in hules.iter().enumerate() {
+            let seat = seat(hule, "seat")?;
+            let tsumo = hule["zimo"].as_bool().unwrap_or_default();
+            let from = match (tsumo, self.last_discard) {
+                (true, _) => seat,
+                (false, Some((from, _))) => from,
+                (false, None) => return Err(RiichiError::new(187, "Ron without a discard")),
+            };
+
+            let points = if !tsumo {
+                number(hule, "point_rong")?
+            } else if seat == self.dealer {
+                number(hule, "point_zimo_xian")? * 3
+            } else {
+                number(hule, "point_zimo_qin")? + number(hule, "point_zimo_xian")? * 2
+            };
+
+            // the deltas of a multiple ron are split, riichi sticks go to the first winner
+            let deltas = if hules.len() == 1 {
+                delta_scores
+            } else {
+                let mut deltas = [0; 4];
+                let sticks = if i == 0 { self.riichi_sticks as i32 } else { 0 };
+                deltas[seat as usize] = delta_scores[seat as usize];
+                deltas[from as usize] = sticks * 1000 - delta_scores[seat as usize];
+                deltas
+            };
+
+            let yakuman = match hule["yiman"].as_bool() {
+                Some(true) => number(hule, "count")? as u8,
+                _ => 0,
+            };
+
+            let win = HandWin {
+                seat,
+                from,
+                liable: liable.filter(|liable| *liable != seat),
+                tile: tile(field(hule, "hu_tile")?)?,
+                han: if yakuman > 0 {
+                    0
+                } else {
+                    number(hule, "count")? as u8
+                },
+                fu: hule["fu"].as_u64().unwrap_or_default() as u8,
+                yakuman,
+                points,
+                ura_dora_indicators: match hule["li_doras"].as_array() {
+                    Some(_) => tiles(&hule["li_doras"])?,
+                    None => vec![],
+                },
+                deltas,
+            };
+            self.push(ReplayEvent::Win(win));
+        }
+        self.riichi_sticks = 0;
+
+        Ok(())
+    }
+
+    /// Exhaustive draw. Noten payments and nagashi mangan come in separate score changes.
+    fn no_tile(&mut self, data: &Value) -> Result<(), RiichiError> {
+        let mut tenpai = [false; 4];
+        if let Some(players) = data["players"].as_array() {
+            for (seat, player) in players.iter().take(4).enumerate() {
+                tenpai[seat] = player["tingpai"].as_bool().unwrap_or_default();
+            }
+        }
+
+        // every nagashi mangan has its own score change with the seat that made it
+        let liujumanguan = data["liujumanguan"].as_bool().unwrap_or_default();
+        let mut nagashi_mangan = [false; 4];
+        let mut draw_deltas = [0; 4];
+        if let Some(scores) = data["scores"].as_array() {
+            for score in scores.iter() {
+                if liujumanguan {
+                    if let Some(nagashi) = score["seat"].as_u64() {
+                        nagashi_mangan[nagashi as usize % 4] = true;
+                    }
+                }
+
+                if score["delta_scores"].is_array() {
+                    let score_deltas = deltas(&score["delta_scores"])?;
+                    for (delta, score_delta) in draw_deltas.iter_mut().zip(score_deltas.iter()) {
+                        *delta += score_delta;
+                    }
+                }
+            }
+        }
+
+        self.push(ReplayEvent::Ryuukyoku(HandDraw {
+            reason: DrawReason::Exhaustive,
+            tenpai,
+            nagashi_mangan,
+            deltas: draw_deltas,
+        }));
+
+        Ok(())
+    }
+
+    /// Abortive draw: 1 kyuushu kyuuhai, 2 suufon renda, 3 suukaikan, 4 suucha riichi, 5 sanchahou
+    fn liu_ju(&mut self, data: &Value) -> Result<(), RiichiError> {
+        self.riichi_accepted(data)?;
+
+        let reason = match field(data, "type")?.as_u64() {
+            Some(1) => DrawReason::KyuushuKyuuhai,
+            Some(2) => DrawReason::SuufonRenda,
+            Some(3) => DrawReason::Suukaikan,
+            Some(4) => DrawReason::SuuchaRiichi,
+            Some(5) => DrawReason::Sanchahou,
+            _ => return Err(RiichiError::new(187, "Wrong abortive draw type")),
+        };
+
+        self.push(ReplayEvent::Ryuukyoku(HandDraw {
+            reason,
+            tenpai: [false; 4],
+            nagashi_mangan: [false; 4],
+            deltas: [0; 4],
+        }));
+
+        Ok(())
+    }
+
+    /// The deposit of the last riichi is paid with the next action (liqi)
+    fn riichi_accepted(&mut self, data: &Value) -> Result<(), RiichiError> {
+        if data["liqi"].is_object() {
+            let seat = seat(&data["liqi"], "seat")?;
+            self.riichi_sticks += 1;
+            self.push(ReplayEvent::RiichiAccepted { seat });
+        }
+
+        Ok(())
+    }
+
+    /// New dora indicators after a kan
+    fn doras(&mut self, data: &Value) -> Result<(), RiichiError> {
+        if !data["doras"].is_array() {
+            return Ok(());
+        }
+
+        let doras = tiles(&data["doras"])?;
+        for indicator in doras.iter().skip(self.dora_count) {
+            self.push(ReplayEvent::Dora {
+                indicator: *indicator,
+            });
+        }
+        self.dora_count = self.dora_count.max(doras.len());
+
+        Ok(())
+    }
+
+    /// How many red copies of this tile are in the game
+    fn reds(&self, tile: &Tile) -> u8 {
+        match tile.tile_type {
+            TileType::Number(5, TileColor::Manzu) => self.replay.rules.aka[0],
+            TileType::Number(5, TileColor::Pinzu) => self.replay.rules.aka[1],
+            TileType::Number(5, TileColor::Souzu) => self.replay.rules.aka[2],
+            _ => 0,
+        }
+    }
+}
+
+/// Tiles are like "1m" and "5z", a red 5 is "0m"
+fn tile(value: &Value) -> Result<Tile, RiichiError> {
+    match value.as_str() {
+        Some(text) if text.len() == 2 && text.starts_with(|c: char| c.is_ascii_digit()) => {
+            Tile::from_text(text)
+        }
+        _ => Err(RiichiError::new(
+            104,
+            &format!("Invalid tile definition {}", value)[..],
+        )),
+    }
+}
+
+fn tiles(value: &Value) -> Result<Vec<Tile>, RiichiError> {
+    match value.as_array() {
+        Some(tiles) => tiles.iter().map(tile).collect(),
+        None => Err(RiichiError::new(187, "Wrong tile list")),
+    }
+}
+
+fn field<'a>(data: &'a Value, name: &str) -> Result<&'a Value, RiichiError> {
+    match data.get(name) {
+        Some(value) => Ok(value),
+        None => Err(RiichiError::new(
+            188,
+            &format!("Missing attribute {}", name)[..],
+        )),
+    }
+}
+
+fn number(data: &Value, name: &str) -> Result<u32, RiichiError> {
+    match field(data, name)?.as_u64() {
+        Some(number) => Ok(number as u32),
+        None => Err(RiichiError::new(
+            189,
+            &format!("{} is not a number", name)[..],
+        )),
+    }
+}
+
+fn numbers(value: &Value) -> Result<Vec<i32>, RiichiError> {
+    match value.as_array() {
+        Some(numbers) => numbers
+            .iter()
+            .map(|number| match number.as_i64() {
+                Some(number) => Ok(number as i32),
+                None => Err(RiichiError::new(
+                    189,
+                    &format!("{} is not a number", number)[..],
+                )),
+            })
+            .collect(),
+        None => Err(RiichiError::new(187, "Wrong number list")),
+    }
+}
+
+fn deltas(value: &Value) -> Result<[i32; 4], RiichiError> {
+    match numbers(value)?[..] {
+        [a, b, c, d, ..] => Ok([a, b, c, d]),
+        _ => Err(RiichiError::new(187, "Wrong number of point changes")),
+    }
+}
+
+/// Seats are 0 - 3 in the starting order, the first dealer is 0
+fn seat(data: &Value, name: &str) -> Result<u8, RiichiError> {
+    match number(data, name)? {
+        seat if seat < 4 => Ok(seat as u8),
+        seat => Err(RiichiError::new(187, &format!("Wrong seat {}", seat)[..])),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // dealer riichi, a pon that becomes an added kan, a ron on the riichi wait and a drawn hand
+    const PAIPU: &str = r#"{
+        "head": {
+            "accounts": [{"seat": 0, "nickname": "A"}, {"seat": 2, "nickname": "C"}],
+            "config": {"mode": {"mode": 1, "detail_rule": {"dora_count": 3}}}
+        },
+        "data": {"name": ".lq.GameDetailRecords", "data": {"records": [
+            {"name": ".lq.RecordNewRound", "data": {"chang": 0, "ju": 0, "ben": 0, "liqibang": 0,
+                "doras": ["2m"], "scores": [25000, 25000, 25000, 25000],
+                "tiles0": ["1m","2m","3m","4p","0p","6p","7s","8s","9s","1z","1z","2p","3p","5z"],
+                "tiles1": ["5z","5z","9m","1p","2p","3p","4s","5s","6s","7m","8m","9m","1s"],
+                "tiles2": ["1m","1m","2s","3s","4s","6z","6z","7z","7z","8p","8p","9p","9p"],
+                "tiles3": ["4m","5m","6m","4z","4z","2z","3z","6p","7p","8p","2s","2s","3s"]}},
+            {"name": ".lq.RecordDiscardTile", "data": {"seat": 0, "tile": "5z", "is_liqi": true, "moqie": true}},
+            {"name": ".lq.RecordChiPengGang", "data": {"seat": 1, "type": 1, "tiles": ["5z","5z","5z"],
+                "froms": [1, 1, 0], "liqi": {"seat": 0, "score": 24000, "liqibang": 1}}},
+            {"name": ".lq.RecordDiscardTile", "data": {"seat": 1, "tile": "9m", "moqie": false}},
+            {"name": ".lq.RecordDealTile", "data": {"seat": 2, "tile": "2p"}},
+            {"name": ".lq.RecordDiscardTile", "data": {"seat": 2, "tile": "2p", "moqie": true}},
+            {"name": ".lq.RecordDealTile", "data": {"seat": 3, "tile": "4m"}},
+            {"name": ".lq.RecordDiscardTile", "data": {"seat": 3, "tile": "4m", "moqie": true}},
+            {"name": ".lq.RecordDealTile", "data": {"seat": 0, "tile": "6m"}},
+            {"name": ".lq.RecordDiscardTile", "data": {"seat": 0, "tile": "6m", "moqie": true}},
+            {"name": ".lq.RecordDealTile", "data": {"seat": 1, "tile": "5z"}},
+            {"name": ".lq.RecordAnGangAddGang", "data": {"seat": 1, "type": 2, "tiles": "5z"}},
+            {"name": ".lq.RecordDealTile", "data": {"seat": 1, "tile": "3s", "doras": ["2m", "7p"]}},
+            {"name": ".lq.RecordDiscardTile", "data": {"seat": 1, "tile": "1p", "moqie": false}},
+            {"name": ".lq.RecordHule", "data": {"hules": [{"seat": 0, "zimo": false, "hu_tile": "1p",
+                "count": 3, "fu": 40, "yiman": false, "li_doras": ["9s", "1z"], "point_rong": 7700}],
+                "delta_scores": [8700, -7700, 0, 0], "baopai": 0}},
+            {"name": ".lq.RecordNewRound", "data": {"chang": 0, "ju": 0, "ben": 1, "liqibang": 0,
+                "doras": ["3z"], "scores": [32700, 17300, 25000, 25000],
+                "tiles0": ["1m","2m","3m","4p","0p","6p","7s","8s","9s","1z","1z","2p","3p","5z"],
+                "tiles1": ["5z","5z","9m","1p","2p","3p","4s","5s","6s","7m","8m","9m","1s"],
+                "tiles2": ["1m","1m","2s","3s","4s","6z","6z","7z","7z","8p","8p","9p","9p"],
+                "tiles3": ["4m","5m","6m","4z","4z","2z","3z","6p","7p","8p","2s","2s","3s"]}},
+            {"name": ".lq.RecordDiscardTile", "data": {"seat": 0, "tile": "5z", "moqie": true}},
+            {"name": ".lq.RecordNoTile", "data": {"liujumanguan": false,
+                "players": [{"tingpai": true}, {"tingpai": false}, {"tingpai": false}, {"tingpai": false}],
+                "scores": [{"delta_scores": [3000, -1000, -1000, -1000]}]}}
+        ]}}
+    }"#;
+
+    #[test]
+    fn paipu_events() {
+        let replay = Replay::from_mahjong_soul(PAIPU).unwrap();
+
+        assert_eq!(replay.players[0], "A");
+        assert_eq!(replay.players[2], "C");
+        assert_eq!(replay.rules.game_length, GameLength::Tonpuusen);
+        assert_eq!(replay.events.len(), 23);
+
+        match &replay.events[0] {
+            ReplayEvent::Start(start) => {
+                assert_eq!(start.dealer, 0);
+                assert_eq!(start.hands[0].len(), 13);
+                assert!(start.hands[0][4].is_red);
+            }
+            event => panic!("{:?} is not the start", event),
+        }
+        match &replay.events[1] {
+            ReplayEvent::Draw { seat: 0, tile } => assert_eq!(tile.to_string(), "5z"),
+            event => panic!("{:?} is not the dealer's draw", event),
+        }
+        assert_eq!(replay.events[2], ReplayEvent::Riichi { seat: 0 });
+        assert_eq!(replay.events[4], ReplayEvent::RiichiAccepted { seat: 0 });
+        match &replay.events[5] {
+            ReplayEvent::Call {
+                seat: 1,
+                meld: CompleteShape::Open(OpenShape::Pon(tiles)),
+            } => assert_eq!(tiles[0].called_from, 3),
+            event => panic!("{:?} is not the pon", event),
+        }
+        match &replay.events[14] {
+            ReplayEvent::Call {
+                seat: 1,
+                meld: CompleteShape::Open(OpenShape::Kan(OpenKan::Shouminkan(_))),
+            } => {}
+            event => panic!("{:?} is not the added kan", event),
+        }
+        match &replay.events[15] {
+            ReplayEvent::Dora { indicator } => assert_eq!(indicator.to_string(), "7p"),
+            event => panic!("{:?} is not the kan dora", event),
+        }
+        match &replay.events[18] {
+            ReplayEvent::Win(win) => {
+                assert_eq!((win.seat, win.from, win.liable), (0, 1, None));
+                assert_eq!(win.tile.to_string(), "1p");
+                assert_eq!((win.han, win.fu, win.points), (3, 40, 7700));
+                assert_eq!(win.ura_dora_indicators.len(), 2);
+                assert_eq!(win.deltas, [8700, -7700, 0, 0]);
+            }
+            event => panic!("{:?} is not the win", event),
+        }
+        match &replay.events[22] {
+            ReplayEvent::Ryuukyoku(draw) => {
+                assert_eq!(draw.reason, DrawReason::Exhaustive);
+                assert_eq!(draw.tenpai, [true, false, false, false]);
+                assert_eq!(draw.deltas, [3000, -1000, -1000, -1000]);
+            }
+            event => panic!("{:?} is not the draw", event),
+        }
+
+        let table = replay.table_at(18, 1).unwrap();
+        assert_eq!(table.get_my_hand().count_tiles(), 13);
+        assert_eq!(table.get_dora_indicators().len(), 2);
+        assert_eq!(table.get_points(3), Some(24000));
+
+        let table = replay.table_at(19, 0).unwrap();
+        assert_eq!(table.get_points(0), Some(32700));
+    }
+
+    #[test]
+    fn paipu_errors() {
+        assert_eq!(Replay::from_mahjong_soul("[").unwrap_err().code, 187);
+        assert_eq!(Replay::from_mahjong_soul("{}").unwrap_err().code, 188);
+
+        let sanma = PAIPU.replace(r#""mode": 1"#, r#""mode": 11"#);
+        assert_eq!(Replay::from_mahjong_soul(&sanma).unwrap_err().code, 186);
+
+        let wrong_tile = PAIPU.replace(r#""tile": "9m""#, r#""tile": "xm""#);
+        assert_eq!(
+            Replay::from_mahjong_soul(&wrong_tile).unwrap_err().code,
+            104
+        );
+    }
+}
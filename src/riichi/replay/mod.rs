@@ -0,0 +1,324 @@
+use crate::riichi::hand::Hand;
+use crate::riichi::riichi_error::RiichiError;
+use crate::riichi::rules::Rules;
+use crate::riichi::settlement::DrawReason;
+use crate::riichi::shapes::{ClosedShape, CompleteShape, OpenKan, OpenShape};
+use crate::riichi::table::{HandSnapshot, Table};
+use crate::riichi::tile::Tile;
+
+/// Mahjong Soul paipu JSON
+pub mod mahjong_soul;
+/// Tenhou mjlog replays and meld codes
+pub mod tenhou;
+/// tenhou.net/6 JSON logs
+pub mod tenhou_json;
+
+/// The deal at the start of a hand
+#[derive(Debug, Clone, PartialEq)]
+pub struct HandStart {
+    /// 1 = east, 2 = south, 3 = west, 4 = north
+    pub prevalent_wind: u8,
+    /// Seat of the dealer
+    pub dealer: u8,
+    /// Honba (repeat) sticks
+    pub honba: u8,
+    /// Riichi sticks left on the table from previous hands
+    pub riichi_sticks: u8,
+    /// The first dora indicator
+    pub dora_indicator: Tile,
+    /// Points of all seats
+    pub points: [i32; 4],
+    /// Starting 13 tiles of all seats
+    pub hands: [Vec<Tile>; 4],
+}
+
+/// A win at the end of a hand. A multiple ron is more of these in a row.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HandWin {
+    /// Who won
+    pub seat: u8,
+    /// Who dealt in, the winner's own seat for tsumo
+    pub from: u8,
+    /// The player liable for a yakuman (pao), if any
+    pub liable: Option<u8>,
+    /// The winning tile
+    pub tile: Tile,
+    /// Han of the hand, 0 for yakuman
+    pub han: u8,
+    /// Fu of the hand
+    pub fu: u8,
+    /// How many yakuman the hand has
+    pub yakuman: u8,
+    /// Value of the hand without honba and riichi sticks
+    pub points: u32,
+    /// Ura dora indicators, if the winner was in riichi
+    pub ura_dora_indicators: Vec<Tile>,
+    /// Point changes of all seats, honba and riichi sticks included
+    pub deltas: [i32; 4],
+}
+
+/// A hand ending without a win
+#[derive(Debug, Clone, PartialEq)]
+pub struct HandDraw {
+    /// Why the hand ended
+    pub reason: DrawReason,
+    /// Who was tenpai at an exhaustive draw
+    pub tenpai: [bool; 4],
+    /// Who was paid for nagashi mangan at an exhaustive draw
+    pub nagashi_mangan: [bool; 4],
+    /// Point changes of all seats (noten payments, nagashi mangan)
+    pub deltas: [i32; 4],
+}
+
+/// One thing that happened in a game. Seats are absolute, 0 is the first dealer.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReplayEvent {
+    /// A new hand is dealt
+    Start(HandStart),
+    /// A seat draws a tile, also a replacement tile after a kan
+    Draw {
+        /// Who drew
+        seat: u8,
+        /// The drawn tile
+        tile: Tile,
+    },
+    /// A seat discards a tile. The tile knows if it was tsumogiri or a riichi discard.
+    Discard {
+        /// Who discarded
+        seat: u8,
+        /// The discarded tile
+        tile: Tile,
+    },
+    /// A seat calls chi, pon or a kan. Called tiles know who they were called from.
+    Call {
+        /// Who called
+        seat: u8,
+        /// The shape made by the call, a closed kan is a ClosedShape::Kantsu
+        meld: CompleteShape,
+    },
+    /// A seat declares riichi, their next discard is the riichi tile
+    Riichi {
+        /// Who declared riichi
+        seat: u8,
+    },
+    /// The riichi discard was not called for a win and the deposit was paid
+    RiichiAccepted {
+        /// Who paid the deposit
+        seat: u8,
+    },
+    /// A new dora indicator is revealed
+    Dora {
+        /// The revealed indicator
+        indicator: Tile,
+    },
+    /// A seat wins the hand
+    Win(HandWin),
+    /// The hand ends in a draw
+    Ryuukyoku(HandDraw),
+}
+
+/// A whole game as a list of events
+#[derive(Debug, Clone)]
+pub struct Replay {
+    /// Player names in seat order
+    pub players: [String; 4],
+    /// Rules the game was played with
+    pub rules: Rules,
+    /// Everything that happened, in order
+    pub events: Vec<ReplayEvent>,
+}
+
+impl Replay {
+    /// The table as seen by a seat after the first `event_count` events.
+    /// There has to be a hand start among them.
+    pub fn table_at(&self, event_count: usize, seat: u8) -> Result<Table, RiichiError> {
+        if seat > 3 {
+            return Err(RiichiError::new(180, "Seat has to be 0 - 3"));
+        }
+
+        let mut state: Option<ReplayState> = None;
+        for event in self.events.iter().take(event_count) {
+            match event {
+                ReplayEvent::Start(start) => state = Some(ReplayState::new(start)),
+                event => match state.as_mut() {
+                    None => return Err(RiichiError::new(181, "No hand was dealt yet")),
+                    Some(state) => state.apply(event)?,
+                },
+            }
+        }
+
+        match state {
+            None => Err(RiichiError::new(181, "No hand was dealt yet")),
+            Some(state) => Ok(state.table_for(seat, &self.rules)),
+        }
+    }
+}
+
+/// State of one hand, updated event by event
+struct ReplayState {
+    prevalent_wind: u8,
+    dealer: u8,
+    honba: u8,
+    riichi_sticks: u8,
+    points: [i32; 4],
+    hands: [Hand; 4],
+    discards: [Vec<Tile>; 4],
+    riichi: [bool; 4],
+    double_riichi: [bool; 4],
+    uninterrupted: bool,
+    dora_indicators: Vec<Tile>,
+    tiles_remaining: u8,
+}
+
+impl ReplayState {
+    fn new(start: &HandStart) -> ReplayState {
+        let hands = [0, 1, 2, 3]
+            .map(|seat| Hand::new(start.hands[seat].iter().map(|tile| Some(*tile)).collect()));
+
+        ReplayState {
+            prevalent_wind: start.prevalent_wind,
+            dealer: start.dealer,
+            honba: start.honba,
+            riichi_sticks: start.riichi_sticks,
+            points: start.points,
+            hands,
+            discards: Default::default(),
+            riichi: [false; 4],
+            double_riichi: [false; 4],
+            uninterrupted: true,
+            dora_indicators: vec![start.dora_indicator],
+            // 136 tiles - 14 in the dead wall - 4 * 13 dealt
+            tiles_remaining: 70,
+        }
+    }
+
+    fn apply(&mut self, event: &ReplayEvent) -> Result<(), RiichiError> {
+        match event {
+            ReplayEvent::Start(_) => {}
+            ReplayEvent::Draw { seat, tile } => {
+                let hand = &mut self.hands[*seat as usize];
+                let mut tile = *tile;
+                tile.is_draw = true;
+                hand.reset_drawn_tiles();
+                hand.add_tile(tile);
+                self.tiles_remaining = self.tiles_remaining.saturating_sub(1);
+            }
+            ReplayEvent::Discard { seat, tile } => {
+                let hand = &mut self.hands[*seat as usize];
+                if hand.get_34_array(true)[tile.get_id_minus_1() as usize] == 0 {
+                    return Err(RiichiError::new(182, "Discarded tile is not in the hand"));
+                }
+
+                hand.remove_tile(tile);
+                hand.reset_drawn_tiles();
+                self.discards[*seat as usize].push(*tile);
+            }
+            ReplayEvent::Call { seat, meld } => {
+                self.uninterrupted = false;
+                self.call(*seat, meld)?;
+            }
+            ReplayEvent::Riichi { seat } => {
+                self.riichi[*seat as usize] = true;
+                self.double_riichi[*seat as usize] =
+                    self.uninterrupted && self.discards[*seat as usize].is_empty();
+            }
+            ReplayEvent::RiichiAccepted { seat } => {
+                self.points[*seat as usize] -= 1000;
+                self.riichi_sticks += 1;
+            }
+            ReplayEvent::Dora { indicator } => self.dora_indicators.push(*indicator),
+            ReplayEvent::Win(win) => {
+                self.apply_deltas(&win.deltas);
+                self.riichi_sticks = 0;
+            }
+            ReplayEvent::Ryuukyoku(draw) => self.apply_deltas(&draw.deltas),
+        }
+
+        Ok(())
+    }
+
+    fn call(&mut self, seat: u8, meld: &CompleteShape) -> Result<(), RiichiError> {
+        let hand = &mut self.hands[seat as usize];
+        let closed = hand.get_34_array(true);
+        let missing = |tiles: &[Tile]| {
+            let mut needed = [0u8; 34];
+            for tile in tiles.iter() {
+                needed[tile.get_id_minus_1() as usize] += 1;
+            }
+
+            needed.iter().zip(closed.iter()).any(|(n, c)| n > c)
+        };
+        let error = RiichiError::new(183, "Called tiles are not in the hand");
+
+        match meld {
+            CompleteShape::Open(OpenShape::Kan(OpenKan::Shouminkan(tiles))) => {
+                if missing(&tiles[3..]) {
+                    return Err(error);
+                }
+
+                hand.reset_drawn_tiles();
+                hand.add_shouminkan(&tiles[3]);
+            }
+            CompleteShape::Open(open) => {
+                let tiles: &[Tile] = match open {
+                    OpenShape::Chi(tiles) | OpenShape::Pon(tiles) => tiles,
+                    OpenShape::Kan(OpenKan::Daiminkan(tiles))
+                    | OpenShape::Kan(OpenKan::Shouminkan(tiles)) => tiles,
+                };
+                let called = match tiles.iter().find(|tile| tile.called_from != 0) {
+                    Some(called) => *called,
+                    None => return Err(RiichiError::new(184, "Call without a called tile")),
+                };
+                let own: Vec<Tile> = tiles
+                    .iter()
+                    .filter(|tile| tile.called_from == 0)
+                    .copied()
+                    .collect();
+                if missing(&own) {
+                    return Err(error);
+                }
+
+                hand.reset_drawn_tiles();
+                hand.add_tile(called);
+                hand.add_open_shape(open);
+            }
+            CompleteShape::Closed(kan @ ClosedShape::Kantsu(tiles)) => {
+                if missing(tiles) {
+                    return Err(error);
+                }
+
+                hand.reset_drawn_tiles();
+                hand.add_closed_kan(*kan);
+            }
+            CompleteShape::Closed(_) => {
+                return Err(RiichiError::new(185, "Only a kan can be a closed call"))
+            }
+        }
+
+        Ok(())
+    }
+
+    fn apply_deltas(&mut self, deltas: &[i32; 4]) {
+        for (points, delta) in self.points.iter_mut().zip(deltas.iter()) {
+            *points += delta;
+        }
+    }
+
+    fn table_for(&self, seat: u8, rules: &Rules) -> Table {
+        HandSnapshot {
+            rules: *rules,
+            prevalent_wind: self.prevalent_wind,
+            dealer: self.dealer,
+            honba: self.honba,
+            riichi_sticks: self.riichi_sticks,
+            tiles_remaining: self.tiles_remaining,
+            points: self.points,
+            hands: &self.hands,
+            discards: &self.discards,
+            riichi: self.riichi,
+            double_riichi: self.double_riichi,
+            dora_indicators: &self.dora_indicators,
+        }
+        .table_for(seat)
+    }
+}
@@ -0,0 +1,787 @@
+use crate::riichi::replay::{HandDraw, HandStart, HandWin, Replay, ReplayEvent};
+use crate::riichi::riichi_error::RiichiError;
+use crate::riichi::rules::{GameLength, Rules};
+use crate::riichi::settlement::DrawReason;
+use crate::riichi::shapes::{ClosedShape, CompleteShape, OpenKan, OpenShape};
+use crate::riichi::tile::{Tile, TileType};
+use regex::Regex;
+use std::collections::HashMap;
+
+impl Replay {
+    /// Reads a tenhou mjlog XML replay (unzipped)
+    pub fn from_mjlog(xml: &str) -> Result<Replay, RiichiError> {
+        lazy_static! {
+            static ref TAG: Regex = Regex::new(r"<(\w+)([^>]*)>").unwrap();
+            static ref ATTRIBUTE: Regex = Regex::new(r#"(\w+)="([^"]*)""#).unwrap();
+            static ref TILE_TAG: Regex = Regex::new(r"^([TUVWDEFG])(\d+)$").unwrap();
+        }
+
+        let mut parser = MjlogParser {
+            replay: Replay {
+                players: Default::default(),
+                rules: Rules::tenhou(),
+                events: vec![],
+            },
+            last_draws: [None; 4],
+            riichi_declared: [false; 4],
+        };
+
+        for tag in TAG.captures_iter(xml) {
+            let name = &tag[1];
+            if let Some(tile_tag) = TILE_TAG.captures(name) {
+                let seat = "TUVWDEFG".find(&tile_tag[1]).unwrap() as u8;
+                parser.tile_tag(seat, number(&tile_tag[2])?)?;
+                continue;
+            }
+
+            let attributes: HashMap<&str, &str> = ATTRIBUTE
+                .captures_iter(&tag[2])
+                .map(|a| (a.get(1).unwrap().as_str(), a.get(2).unwrap().as_str()))
+                .collect();
+
+            match name {
+                "GO" => parser.go(&attributes)?,
+                "UN" => parser.un(&attributes),
+                "INIT" => parser.init(&attributes)?,
+                "N" => parser.call(&attributes)?,
+                "REACH" => parser.reach(&attributes)?,
+                "DORA" => {
+                    let indicator = parser.tile(number(attribute(&attributes, "hai")?)?)?;
+                    parser.push(ReplayEvent::Dora { indicator });
+                }
+                "AGARI" => parser.agari(&attributes)?,
+                "RYUUKYOKU" => parser.ryuukyoku(&attributes)?,
+                _ => {}
+            }
+        }
+
+        Ok(parser.replay)
+    }
+}
+
+struct MjlogParser {
+    replay: Replay,
+    /// 136 ids of the last drawn tiles, to find tsumogiri
+    last_draws: [Option<u32>; 4],
+    /// The next discard is a riichi tile
+    riichi_declared: [bool; 4],
+}
+
+impl MjlogParser {
+    fn push(&mut self, event: ReplayEvent) {
+        self.replay.events.push(event);
+    }
+
+    /// Red 5s are only red in lobbies that play with them
+    fn tile(&self, id_136: u32) -> Result<Tile, RiichiError> {
+        if id_136 > 135 {
+            return Err(RiichiError::new(
+                108,
+                &format!("Wrong 136 tile ID {}", id_136)[..],
+            ));
+        }
+
+        let mut tile = Tile::from_id_136(id_136 as u8)?;
+        tile.is_red = tile.is_red && self.replay.rules.aka_ari();
+
+        Ok(tile)
+    }
+
+    fn tiles(&self, value: &str) -> Result<Vec<Tile>, RiichiError> {
+        numbers(value)?
+            .iter()
+            .map(|id_136| self.tile(*id_136 as u32))
+            .collect()
+    }
+
+    /// Lobby type flags: 0x02 no red 5s, 0x04 no open tanyao, 0x08 hanchan, 0x10 3 players
+    fn go(&mut self, attributes: &HashMap<&str, &str>) -> Result<(), RiichiError> {
+        let flags = number(attribute(attributes, "type")?)?;
+        if flags & 0x10 != 0 {
+            return Err(RiichiError::new(186, "Only 4 player games are supported"));
+        }
+
+        let rules = &mut self.replay.rules;
+        if flags & 0x02 != 0 {
+            rules.aka = [0, 0, 0];
+        }
+        rules.kuitan_ari = flags & 0x04 == 0;
+        rules.game_length = if flags & 0x08 != 0 {
+            GameLength::Hanchan
+        } else {
+            GameLength::Tonpuusen
+        };
+
+        Ok(())
+    }
+
+    /// Player names are URL encoded. UN without names is a reconnect.
+    fn un(&mut self, attributes: &HashMap<&str, &str>) {
+        for seat in 0..4 {
+            if let Some(name) = attributes.get(&format!("n{}", seat)[..]) {
+                self.replay.players[seat] = url_decode(name);
+            }
+        }
+    }
+
+    fn init(&mut self, attributes: &HashMap<&str, &str>) -> Result<(), RiichiError> {
+        // round, honba, riichi sticks, 2 dice, dora indicator
+        let seed = numbers(attribute(attributes, "seed")?)?;
+        if seed.len() != 6 {
+            return Err(RiichiError::new(187, "Wrong INIT seed"));
+        }
+
+        let points = scores(attribute(attributes, "ten")?)?;
+        let mut hands: [Vec<Tile>; 4] = Default::default();
+        for (seat, hand) in hands.iter_mut().enumerate() {
+            *hand = self.tiles(attribute(attributes, &format!("hai{}", seat)[..])?)?;
+        }
+
+        self.last_draws = [None; 4];
+        self.riichi_declared = [false; 4];
+
+        let start = HandStart {
+            prevalent_wind: (seed[0] / 4 + 1) as u8,
+            dealer: number(attribute(attributes, "oya")?)? as u8,
+            honba: seed[1] as u8,
+            riichi_sticks: seed[2] as u8,
+            dora_indicator: self.tile(seed[5] as u32)?,
+            points: [points[0], points[1], points[2], points[3]],
+            hands,
+        };
+        self.push(ReplayEvent::Start(start));
+
+        Ok(())
+    }
+
+    /// T, U, V, W are draws, D, E, F, G discards of seats 0 - 3
+    fn tile_tag(&mut self, seat: u8, id_136: u32) -> Result<(), RiichiError> {
+        let mut tile = self.tile(id_136)?;
+        let seat_index = (seat % 4) as usize;
+
+        if seat < 4 {
+            self.last_draws[seat_index] = Some(id_136);
+            self.push(ReplayEvent::Draw { seat, tile });
+        } else {
+            tile.is_tsumogiri = self.last_draws[seat_index] == Some(id_136);
+            tile.is_riichi = self.riichi_declared[seat_index];
+            self.last_draws[seat_index] = None;
+            self.riichi_declared[seat_index] = false;
+            self.push(ReplayEvent::Discard {
+                seat: seat % 4,
+                tile,
+            });
+        }
+
+        Ok(())
+    }
+
+    fn call(&mut self, attributes: &HashMap<&str, &str>) -> Result<(), RiichiError> {
+        let seat = seat(attributes, "who")?;
+        let meld = self.meld(number(attribute(attributes, "m")?)?)?;
+
+        self.last_draws[seat as usize] = None;
+        self.push(ReplayEvent::Call { seat, meld });
+
+        Ok(())
+    }
+
+    fn meld(&self, code: u32) -> Result<CompleteShape, RiichiError> {
+        let mut meld = meld_from_code(code)?;
+        if !self.replay.rules.aka_ari() {
+            meld_tiles_mut(&mut meld)
+                .iter_mut()
+                .for_each(|tile| tile.is_red = false);
+        }
+
+        Ok(meld)
+    }
+
+    /// Step 1 is the declaration, step 2 the paid deposit
+    fn reach(&mut self, attributes: &HashMap<&str, &str>) -> Result<(), RiichiError> {
+        let seat = seat(attributes, "who")?;
+        match attribute(attributes, "step")? {
+            "1" => {
+                self.riichi_declared[seat as usize] = true;
+                self.push(ReplayEvent::Riichi { seat });
+            }
+            _ => self.push(ReplayEvent::RiichiAccepted { seat }),
+        }
+
+        Ok(())
+    }
+
+    fn agari(&mut self, attributes: &HashMap<&str, &str>) -> Result<(), RiichiError> {
+        // fu, points, limit
+        let ten = numbers(attribute(attributes, "ten")?)?;
+        if ten.len() < 2 {
+            return Err(RiichiError::new(187, "Wrong AGARI ten"));
+        }
+
+        // yaku id, han, yaku id, han...
+        let han = match attributes.get("yaku") {
+            None => 0,
+            Some(yaku) => numbers(yaku)?.iter().skip(1).step_by(2).sum::<i32>() as u8,
+        };
+        let yakuman = match attributes.get("yakuman") {
+            None => 0,
+            Some(yakuman) => numbers(yakuman)?.len() as u8,
+        };
+        let ura_dora_indicators = match attributes.get("doraHaiUra") {
+            None => vec![],
+            Some(ura) => self.tiles(ura)?,
+        };
+        let liable = match attributes.get("paoWho") {
+            None => None,
+            Some(_) => Some(seat(attributes, "paoWho")?),
+        };
+
+        let win = HandWin {
+            seat: seat(attributes, "who")?,
+            from: seat(attributes, "fromWho")?,
+            liable,
+            tile: self.tile(number(attribute(attributes, "machi")?)?)?,
+            han,
+            fu: ten[0] as u8,
+            yakuman,
+            points: ten[1] as u32,
+            ura_dora_indicators,
+            deltas: deltas(attribute(attributes, "sc")?)?,
+        };
+        self.push(ReplayEvent::Win(win));
+
+        Ok(())
+    }
+
+    /// Hands of tenpai players are shown at an exhaustive draw
+    fn ryuukyoku(&mut self, attributes: &HashMap<&str, &str>) -> Result<(), RiichiError> {
+        let reason = match attributes.get("type") {
+            None | Some(&"nm") => DrawReason::Exhaustive,
+            Some(&"yao9") => DrawReason::KyuushuKyuuhai,
+            Some(&"kaze4") => DrawReason::SuufonRenda,
+            Some(&"reach4") => DrawReason::SuuchaRiichi,
+            Some(&"kan4") => DrawReason::Suukaikan,
+            Some(&"ron3") => DrawReason::Sanchahou,
+            Some(other) => {
+                return Err(RiichiError::new(
+                    187,
+                    &format!("Unknown RYUUKYOKU type {}", other)[..],
+                ))
+            }
+        };
+
+        let mut tenpai = [false; 4];
+        if reason == DrawReason::Exhaustive {
+            for (seat, is_tenpai) in tenpai.iter_mut().enumerate() {
+                *is_tenpai = attributes.contains_key(&format!("hai{}", seat)[..]);
+            }
+        }
+
+        let deltas = deltas(attribute(attributes, "sc")?)?;
+        // nagashi mangan is paid instead of the noten payments
+        let nagashi_mangan = match attributes.get("type") {
+            Some(&"nm") => deltas.map(|d| d > 0),
+            _ => [false; 4],
+        };
+
+        let draw = HandDraw {
+            reason,
+            tenpai,
+            nagashi_mangan,
+            deltas,
+        };
+        self.push(ReplayEvent::Ryuukyoku(draw));
+
+        Ok(())
+    }
+}
+
+/// Tenhou packs a call (N m="...") into one number: the kind of call, the 136 ids of the tiles
+/// and who it was called from. Called tiles get called_from, a closed kan is a ClosedShape::Kantsu.
+pub fn meld_from_code(code: u32) -> Result<CompleteShape, RiichiError> {
+    let from = (code & 3) as u8;
+
+    if code & 0x04 != 0 {
+        // chi: the lowest tile, index of the called tile and the copies of all 3 tiles
+        let run = (code >> 10) / 3;
+        let called = (code >> 10) % 3;
+        let lowest = (run / 7) * 9 + run % 7;
+
+        let mut tiles = [Tile::from_id(1)?; 3];
+        for (i, tile) in tiles.iter_mut().enumerate() {
+            let copy = (code >> (3 + 2 * i)) & 3;
+            *tile = tile_136((lowest + i as u32) * 4 + copy)?;
+            if i as u32 == called {
+                tile.called_from = from;
+            }
+        }
+
+        return Ok(CompleteShape::Open(OpenShape::Chi(tiles)));
+    }
+
+    if code & 0x18 != 0 {
+        // pon and shouminkan: the kind, index of the called tile and the copy not in the pon
+        let kind = (code >> 9) / 3;
+        let called = (code >> 9) % 3;
+        let unused = (code >> 5) & 3;
+
+        let mut tiles = [Tile::from_id(1)?; 3];
+        let copies = (0..4).filter(|copy| *copy != unused);
+        for (i, (tile, copy)) in tiles.iter_mut().zip(copies).enumerate() {
+            *tile = tile_136(kind * 4 + copy)?;
+            if i as u32 == called {
+                tile.called_from = from;
+            }
+        }
+
+        return Ok(if code & 0x08 != 0 {
+            CompleteShape::Open(OpenShape::Pon(tiles))
+        } else {
+            let added = tile_136(kind * 4 + unused)?;
+            CompleteShape::Open(OpenShape::Kan(OpenKan::Shouminkan([
+                tiles[0], tiles[1], tiles[2], added,
+            ])))
+        });
+    }
+
+    if code & 0x20 != 0 {
+        return Err(RiichiError::new(186, "Only 4 player games are supported"));
+    }
+
+    // kans: the called tile, called from nobody for a closed kan
+    let called = code >> 8;
+    let mut tiles = [Tile::from_id(1)?; 4];
+    for (copy, tile) in tiles.iter_mut().enumerate() {
+        let id_136 = (called / 4) * 4 + copy as u32;
+        *tile = tile_136(id_136)?;
+        if id_136 == called {
+            tile.called_from = from;
+        }
+    }
+
+    Ok(if from == 0 {
+        CompleteShape::Closed(ClosedShape::Kantsu(tiles))
+    } else {
+        CompleteShape::Open(OpenShape::Kan(OpenKan::Daiminkan(tiles)))
+    })
+}
+
+/// The tenhou number of a call, the reverse of meld_from_code. Tiles without a 136 id get a free copy,
+/// the red one for red 5s.
+pub fn meld_to_code(meld: &CompleteShape) -> Result<u32, RiichiError> {
+    let mut meld_copy = *meld;
+    let tiles = meld_tiles_mut(&mut meld_copy).to_vec();
+    let ids = ids_136(&tiles)?;
+    let kind = ids[0] / 4;
+    let called = tiles.iter().position(|tile| tile.called_from != 0);
+    let from = match called {
+        Some(i) => tiles[i].called_from as u32,
+        None => 0,
+    };
+    if from > 3 {
+        return Err(RiichiError::new(
+            190,
+            "Tiles can only be called from players 1 - 3",
+        ));
+    }
+
+    let same_kind = ids.iter().all(|id_136| id_136 / 4 == kind);
+    let invalid = || RiichiError::new(191, "These tiles can't make this call");
+
+    match meld {
+        CompleteShape::Open(OpenShape::Chi(_)) => {
+            let mut order: Vec<usize> = (0..3).collect();
+            order.sort_by_key(|i| ids[*i]);
+            let lowest = ids[order[0]] / 4;
+            let is_run = lowest < 27
+                && lowest % 9 <= 6
+                && order
+                    .iter()
+                    .enumerate()
+                    .all(|(i, tile)| ids[*tile] / 4 == lowest + i as u32);
+            let called = match called {
+                Some(called) if is_run => order.iter().position(|i| *i == called).unwrap() as u32,
+                _ => return Err(invalid()),
+            };
+
+            let run = (lowest / 9) * 7 + lowest % 9;
+            let mut code = ((run * 3 + called) << 10) | 0x04 | from;
+            for (i, tile) in order.iter().enumerate() {
+                code |= (ids[*tile] % 4) << (3 + 2 * i);
+            }
+
+            Ok(code)
+        }
+        CompleteShape::Open(OpenShape::Pon(_))
+        | CompleteShape::Open(OpenShape::Kan(OpenKan::Shouminkan(_))) => {
+            let is_pon = matches!(meld, CompleteShape::Open(OpenShape::Pon(_)));
+            let mut pon: Vec<usize> = (0..3).collect();
+            pon.sort_by_key(|i| ids[*i]);
+            let called = match called {
+                Some(called) if same_kind && called < 3 => {
+                    pon.iter().position(|i| *i == called).unwrap() as u32
+                }
+                _ => return Err(invalid()),
+            };
+            let unused = if is_pon {
+                (0..4)
+                    .find(|copy| pon.iter().all(|i| ids[*i] % 4 != *copy))
+                    .unwrap()
+            } else {
+                ids[3] % 4
+            };
+
+            let flag = if is_pon { 0x08 } else { 0x10 };
+            Ok(((kind * 3 + called) << 9) | (unused << 5) | flag | from)
+        }
+        CompleteShape::Open(OpenShape::Kan(OpenKan::Daiminkan(_))) => match called {
+            Some(called) if same_kind => Ok((ids[called] << 8) | from),
+            _ => Err(invalid()),
+        },
+        CompleteShape::Closed(ClosedShape::Kantsu(_)) if same_kind && from == 0 => {
+            Ok((kind * 4) << 8)
+        }
+        _ => Err(invalid()),
+    }
+}
+
+fn tile_136(id_136: u32) -> Result<Tile, RiichiError> {
+    if id_136 > 135 {
+        return Err(RiichiError::new(
+            108,
+            &format!("Wrong 136 tile ID {}", id_136)[..],
+        ));
+    }
+
+    Tile::from_id_136(id_136 as u8)
+}
+
+/// 136 ids of the tiles, free copies are found for tiles without one
+fn ids_136(tiles: &[Tile]) -> Result<Vec<u32>, RiichiError> {
+    let mut ids: Vec<Option<u32>> = tiles.iter().map(|t| t.id_136.map(|id| id as u32)).collect();
+    for (i, tile) in tiles.iter().enumerate() {
+        if ids[i].is_some() {
+            continue;
+        }
+
+        let kind = tile.get_id_minus_1() as u32;
+        let is_five = matches!(tile.tile_type, TileType::Number(5, _));
+        ids[i] = (0..4)
+            .map(|copy| kind * 4 + copy)
+            .filter(|id_136| !is_five || tile.is_red == [16, 52, 88].contains(id_136))
+            .find(|id_136| !ids.contains(&Some(*id_136)));
+    }
+
+    let ids: Vec<u32> = ids.into_iter().flatten().collect();
+    let mut unique = ids.clone();
+    unique.sort_unstable();
+    unique.dedup();
+    if ids.len() != tiles.len() || unique.len() != ids.len() {
+        return Err(RiichiError::new(191, "These tiles can't make this call"));
+    }
+
+    Ok(ids)
+}
+
+/// All tiles of a shape
+fn meld_tiles_mut(meld: &mut CompleteShape) -> &mut [Tile] {
+    match meld {
+        CompleteShape::Open(OpenShape::Chi(tiles)) | CompleteShape::Open(OpenShape::Pon(tiles)) => {
+            tiles
+        }
+        CompleteShape::Open(OpenShape::Kan(OpenKan::Daiminkan(tiles)))
+        | CompleteShape::Open(OpenShape::Kan(OpenKan::Shouminkan(tiles)))
+        | CompleteShape::Closed(ClosedShape::Kantsu(tiles)) => tiles,
+        CompleteShape::Closed(ClosedShape::Shuntsu(tiles))
+        | CompleteShape::Closed(ClosedShape::Koutsu(tiles)) => tiles,
+        CompleteShape::Closed(ClosedShape::Toitsu(tiles)) => tiles,
+        CompleteShape::Closed(ClosedShape::Single(tile)) => std::slice::from_mut(tile),
+    }
+}
+
+fn attribute<'a>(attributes: &HashMap<&str, &'a str>, name: &str) -> Result<&'a str, RiichiError> {
+    match attributes.get(name) {
+        Some(value) => Ok(value),
+        None => Err(RiichiError::new(
+            188,
+            &format!("Missing attribute {}", name)[..],
+        )),
+    }
+}
+
+fn number(value: &str) -> Result<u32, RiichiError> {
+    match value.parse() {
+        Ok(number) => Ok(number),
+        Err(_) => Err(RiichiError::new(
+            189,
+            &format!("{} is not a number", value)[..],
+        )),
+    }
+}
+
+fn numbers(value: &str) -> Result<Vec<i32>, RiichiError> {
+    if value.is_empty() {
+        return Ok(vec![]);
+    }
+
+    value
+        .split(',')
+        .map(|n| match n.parse() {
+            Ok(number) => Ok(number),
+            Err(_) => Err(RiichiError::new(189, &format!("{} is not a number", n)[..])),
+        })
+        .collect()
+}
+
+fn seat(attributes: &HashMap<&str, &str>, name: &str) -> Result<u8, RiichiError> {
+    match number(attribute(attributes, name)?)? {
+        seat if seat < 4 => Ok(seat as u8),
+        seat => Err(RiichiError::new(187, &format!("Wrong seat {}", seat)[..])),
+    }
+}
+
+/// Points are in hundreds
+fn scores(value: &str) -> Result<Vec<i32>, RiichiError> {
+    let scores: Vec<i32> = numbers(value)?.iter().map(|s| s * 100).collect();
+    if scores.len() < 4 {
+        return Err(RiichiError::new(187, "Wrong number of scores"));
+    }
+
+    Ok(scores)
+}
+
+/// Points before and changes of all seats, in hundreds
+fn deltas(sc: &str) -> Result<[i32; 4], RiichiError> {
+    let sc = scores(sc)?;
+    if sc.len() < 8 {
+        return Err(RiichiError::new(187, "Wrong number of scores"));
+    }
+
+    Ok([sc[1], sc[3], sc[5], sc[7]])
+}
+
+fn url_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = vec![];
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = String::from_utf8_lossy(&bytes[i + 1..i + 3]);
+            if let Ok(byte) = u8::from_str_radix(&hex, 16) {
+                decoded.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MJLOG: &str = r#"<mjloggm ver="2.3"><SHUFFLE seed="mt19937ar-sha512-n288-base64,x" ref=""/><GO type="169" lobby="0"/><UN n0="%41lice" n1="Bob" n2="%E3%81%82" n3="Dan" dan="0,0,0,0" rate="1500.00,1500.00,1500.00,1500.00" sx="M,M,F,M"/><TAIKYOKU oya="0"/><INIT seed="0,0,0,2,3,121" ten="250,250,250,250" oya="0" hai0="0,4,8,36,40,44,72,76,80,108,109,124,125" hai1="12,16,20,48,52,56,84,88,92,112,116,120,126" hai2="24,28,60,64,68,96,100,104,128,132,5,9,13" hai3="32,33,34,1,37,41,45,73,77,81,110,113,117"/><T2/><D2/><U6/><E126/><N who="0" m="48745" /><D108/><V129/><REACH who="2" step="1"/><F129/><REACH who="2" ten="250,250,240,250" step="2"/><W35/><N who="3" m="8192" /><DORA hai="122" /><W3/><G110/><AGARI ba="0,1" hai="0,4,8,36,40,44,72,76,80,109,110" m="48745" machi="110" ten="40,2000,0" yaku="18,1" doraHai="121,122" who="0" fromWho="3" sc="250,30,250,0,240,0,250,-20" /></mjloggm>"#;
+
+    #[test]
+    fn mjlog_events() {
+        let replay = Replay::from_mjlog(MJLOG).unwrap();
+        assert_eq!(replay.players[0], "Alice");
+        assert_eq!(replay.players[2], "あ");
+        assert_eq!(replay.rules.game_length, GameLength::Hanchan);
+        assert_eq!(replay.events.len(), 17);
+
+        match &replay.events[0] {
+            ReplayEvent::Start(start) => {
+                assert_eq!(start.dealer, 0);
+                assert_eq!(start.prevalent_wind, 1);
+                assert_eq!(start.points, [25000; 4]);
+                assert_eq!(start.dora_indicator, Tile::from_text("4z").unwrap());
+                assert!(start.hands[1][1].is_red);
+            }
+            event => panic!("{:?} is not a start", event),
+        }
+
+        match replay.events[2] {
+            ReplayEvent::Discard { seat: 0, tile } => assert!(tile.is_tsumogiri),
+            ref event => panic!("{:?} is not a discard", event),
+        }
+        match replay.events[4] {
+            ReplayEvent::Discard { seat: 1, tile } => assert!(!tile.is_tsumogiri),
+            ref event => panic!("{:?} is not a discard", event),
+        }
+        match replay.events[9] {
+            ReplayEvent::Discard { seat: 2, tile } => assert!(tile.is_riichi),
+            ref event => panic!("{:?} is not a discard", event),
+        }
+
+        match replay.events[5] {
+            ReplayEvent::Call {
+                seat: 0,
+                meld: CompleteShape::Open(OpenShape::Pon(tiles)),
+            } => {
+                assert_eq!(tiles[0], Tile::from_text("5z").unwrap());
+                assert_eq!(tiles[2].called_from, 1);
+                assert_eq!(tiles[2].id_136, Some(126));
+            }
+            ref event => panic!("{:?} is not a pon", event),
+        }
+        match replay.events[12] {
+            ReplayEvent::Call {
+                seat: 3,
+                meld: CompleteShape::Closed(ClosedShape::Kantsu(tiles)),
+            } => assert_eq!(tiles[3].id_136, Some(35)),
+            ref event => panic!("{:?} is not a closed kan", event),
+        }
+
+        match &replay.events[16] {
+            ReplayEvent::Win(win) => {
+                assert_eq!((win.seat, win.from), (0, 3));
+                assert_eq!((win.han, win.fu, win.points), (1, 40, 2000));
+                assert_eq!(win.deltas, [3000, 0, 0, -2000]);
+            }
+            event => panic!("{:?} is not a win", event),
+        }
+    }
+
+    #[test]
+    fn mjlog_table() {
+        let replay = Replay::from_mjlog(MJLOG).unwrap();
+
+        // seat 1 before the win, seat 2 is shimocha and seat 0 kamicha
+        let table = replay.table_at(16, 1).unwrap();
+        assert_eq!(table.get_my_hand().count_tiles(), 13);
+        assert!(table.get_p1_riichi());
+        assert_eq!(table.get_points(1), Some(24000));
+        assert_eq!(table.get_riichi_sticks(), 1);
+        assert_eq!(table.get_tiles_remaining(), Some(65));
+        assert_eq!(table.get_dora_indicators().len(), 2);
+        assert_eq!(table.get_my_seat_wind(), Some(2));
+        assert_eq!(table.get_discards(3).len(), 2);
+        assert_eq!(table.count_kans(2), 1);
+
+        let table = replay.table_at(16, 0).unwrap();
+        assert_eq!(table.get_my_hand().get_open_shapes().len(), 1);
+
+        let table = replay.table_at(17, 0).unwrap();
+        assert_eq!(table.get_points(0), Some(28000));
+        assert_eq!(table.get_riichi_sticks(), 0);
+
+        assert!(replay.table_at(0, 0).is_err());
+        assert!(replay.table_at(5, 4).is_err());
+    }
+
+    #[test]
+    fn mjlog_chi_and_draw() {
+        // 3p 4p 5p chi of the 4p from kamicha
+        let xml = r#"<GO type="169"/><INIT seed="4,1,2,0,0,10" ten="250,250,250,250" oya="1" hai0="12,16,20,24,28,32,36,50,53,64,68,72,76" hai1="0" hai2="1" hai3="2"/><N who="0" m="28871"/><RYUUKYOKU ba="1,2" sc="250,15,250,-15,250,15,250,-15" hai0="1,2,3" hai2="4,5,6"/>"#;
+        let replay = Replay::from_mjlog(xml).unwrap();
+
+        match &replay.events[0] {
+            ReplayEvent::Start(start) => {
+                assert_eq!(start.prevalent_wind, 2);
+                assert_eq!((start.dealer, start.honba, start.riichi_sticks), (1, 1, 2));
+            }
+            event => panic!("{:?} is not a start", event),
+        }
+        match replay.events[1] {
+            ReplayEvent::Call {
+                meld: CompleteShape::Open(OpenShape::Chi(tiles)),
+                ..
+            } => {
+                let ids: Vec<Option<u8>> = tiles.iter().map(|t| t.id_136).collect();
+                assert_eq!(ids, vec![Some(44), Some(50), Some(53)]);
+                assert_eq!(tiles[1].called_from, 3);
+            }
+            ref event => panic!("{:?} is not a chi", event),
+        }
+        match &replay.events[2] {
+            ReplayEvent::Ryuukyoku(draw) => {
+                assert_eq!(draw.reason, DrawReason::Exhaustive);
+                assert_eq!(draw.tenpai, [true, false, true, false]);
+                assert_eq!(draw.deltas, [1500, -1500, 1500, -1500]);
+            }
+            event => panic!("{:?} is not a draw", event),
+        }
+    }
+
+    #[test]
+    fn meld_codes() {
+        // chi, pon, shouminkan, daiminkan, ankan
+        for code in [28871, 48745, 6162, 34307, 8192].iter() {
+            let meld = meld_from_code(*code).unwrap();
+            assert_eq!(meld_to_code(&meld).unwrap(), *code);
+        }
+
+        // the red 5m was added to a pon of 5m from toimen
+        match meld_from_code(6162).unwrap() {
+            CompleteShape::Open(OpenShape::Kan(OpenKan::Shouminkan(tiles))) => {
+                assert!(tiles[3].is_red && tiles[3].id_136 == Some(16));
+                assert!(!tiles[0].is_red);
+                assert_eq!(tiles[0].called_from, 2);
+            }
+            meld => panic!("{:?} is not a shouminkan", meld),
+        }
+
+        // 7z called from kamicha
+        match meld_from_code(34307).unwrap() {
+            CompleteShape::Open(OpenShape::Kan(OpenKan::Daiminkan(tiles))) => {
+                assert_eq!(tiles[2].called_from, 3);
+                assert_eq!(tiles[2].id_136, Some(134));
+            }
+            meld => panic!("{:?} is not a daiminkan", meld),
+        }
+    }
+
+    #[test]
+    fn meld_code_without_136_ids() {
+        let mut called = Tile::from_text("0p").unwrap();
+        called.called_from = 3;
+        let chi = CompleteShape::Open(OpenShape::Chi([
+            Tile::from_text("4p").unwrap(),
+            called,
+            Tile::from_text("6p").unwrap(),
+        ]));
+
+        match meld_from_code(meld_to_code(&chi).unwrap()).unwrap() {
+            CompleteShape::Open(OpenShape::Chi(tiles)) => {
+                assert_eq!(tiles[1].id_136, Some(52));
+                assert!(tiles[1].is_red);
+                assert_eq!(tiles[1].called_from, 3);
+            }
+            meld => panic!("{:?} is not a chi", meld),
+        }
+
+        let mut called = Tile::from_text("5s").unwrap();
+        called.called_from = 1;
+        let five = Tile::from_text("5s").unwrap();
+        let pon = CompleteShape::Open(OpenShape::Pon([called, five, five]));
+        match meld_from_code(meld_to_code(&pon).unwrap()).unwrap() {
+            CompleteShape::Open(OpenShape::Pon(tiles)) => {
+                assert!(tiles.iter().all(|t| !t.is_red));
+                assert_eq!(tiles[0].called_from, 1);
+            }
+            meld => panic!("{:?} is not a pon", meld),
+        }
+
+        let bad_chi = CompleteShape::Open(OpenShape::Chi([
+            Tile::from_text("8p").unwrap(),
+            Tile::from_text("9p").unwrap(),
+            called,
+        ]));
+        assert!(meld_to_code(&bad_chi).is_err());
+        let pair = CompleteShape::Closed(ClosedShape::Toitsu([five, five]));
+        assert!(meld_to_code(&pair).is_err());
+    }
+
+    #[test]
+    fn mjlog_errors() {
+        assert!(Replay::from_mjlog(r#"<INIT seed="0,0" ten="250,250,250,250" oya="0"/>"#).is_err());
+        assert!(Replay::from_mjlog(r#"<GO type="25"/>"#).is_err());
+        assert!(Replay::from_mjlog("<T999/>").is_err());
+    }
+}
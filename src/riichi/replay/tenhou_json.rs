@@ -0,0 +1,1135 @@
+use crate::riichi::replay::{HandDraw, HandStart, HandWin, Replay, ReplayEvent};
+use crate::riichi::riichi_error::RiichiError;
+use crate::riichi::rules::{GameLength, Rules};
+use crate::riichi::settlement::DrawReason;
+use crate::riichi::shapes::{ClosedShape, CompleteShape, OpenKan, OpenShape};
+use crate::riichi::tile::{Tile, TileColor, TileType};
+use serde_json::{json, Value};
+
+/// Tsumogiri in a discard list
+const TSUMOGIRI: u8 = 60;
+
+impl Replay {
+    /// Reads a tenhou.net/6 JSON log.
+    /// Yaku names are not kept, han of limit hands without a yaku list are 0.
+    pub fn from_tenhou_json(json: &str) -> Result<Replay, RiichiError> {
+        let log: Value = match serde_json::from_str(json) {
+            Ok(log) => log,
+            Err(_) => return Err(RiichiError::new(187, "Not a tenhou.net/6 log")),
+        };
+
+        let mut replay = Replay {
+            players: Default::default(),
+            rules: Rules::tenhou(),
+            events: vec![],
+        };
+
+        if let Some(names) = log["name"].as_array() {
+            for (seat, name) in names.iter().take(4).enumerate() {
+                replay.players[seat] = name.as_str().unwrap_or_default().to_string();
+            }
+        }
+
+        let rule = &log["rule"];
+        if let Some(disp) = rule["disp"].as_str() {
+            if disp.contains('東') {
+                replay.rules.game_length = GameLength::Tonpuusen;
+            } else if disp.contains('南') {
+                replay.rules.game_length = GameLength::Hanchan;
+            }
+        }
+        if rule["aka51"].is_number() {
+            for (suit, name) in ["aka51", "aka52", "aka53"].iter().enumerate() {
+                replay.rules.aka[suit] = rule[*name].as_u64().unwrap_or_default() as u8;
+            }
+        } else if let Some(aka) = rule["aka"].as_u64() {
+            replay.rules.aka = [aka as u8; 3];
+        }
+
+        let rounds = match log["log"].as_array() {
+            Some(rounds) => rounds,
+            None => return Err(RiichiError::new(188, "Missing attribute log")),
+        };
+        for round in rounds.iter() {
+            RoundImport::new(round)?.import(&mut replay.events)?;
+        }
+
+        Ok(replay)
+    }
+
+    /// Writes the replay as a tenhou.net/6 JSON log that tenhou's viewer can open.
+    /// Yaku are not known here, wins only show their value.
+    pub fn to_tenhou_json(&self) -> Result<String, RiichiError> {
+        let mut rounds = vec![];
+        let mut round: Option<RoundExport> = None;
+
+        for event in self.events.iter() {
+            if let ReplayEvent::Start(start) = event {
+                if let Some(round) = round.take() {
+                    rounds.push(round.to_json());
+                }
+
+                round = Some(RoundExport::new(start));
+                continue;
+            }
+
+            match round.as_mut() {
+                None => return Err(RiichiError::new(181, "No hand was dealt yet")),
+                Some(round) => round.add(event)?,
+            }
+        }
+        if let Some(round) = round {
+            rounds.push(round.to_json());
+        }
+
+        let rules = &self.rules;
+        let disp = format!(
+            "{}{}{}",
+            match rules.game_length {
+                GameLength::Tonpuusen => "東",
+                GameLength::Hanchan => "南",
+            },
+            if rules.kuitan_ari { "喰" } else { "" },
+            if rules.aka_ari() { "赤" } else { "" }
+        );
+
+        Ok(json!({
+            "title": ["", ""],
+            "name": self.players,
+            "rule": {
+                "disp": disp,
+                "aka": if rules.aka_ari() { 1 } else { 0 },
+                "aka51": rules.aka[0],
+                "aka52": rules.aka[1],
+                "aka53": rules.aka[2],
+            },
+            "log": rounds,
+        })
+        .to_string())
+    }
+}
+
+/// One round of a log: the starting hands and then separate draw and discard lists of every seat.
+/// The order of play has to be found again from who called what.
+struct RoundImport<'a> {
+    round: &'a [Value],
+    draws: [&'a [Value]; 4],
+    discards: [&'a [Value]; 4],
+    next_draw: [usize; 4],
+    next_discard: [usize; 4],
+    dora_indicators: Vec<Tile>,
+    dora_revealed: usize,
+    events: Vec<ReplayEvent>,
+}
+
+impl<'a> RoundImport<'a> {
+    fn new(round: &'a Value) -> Result<RoundImport<'a>, RiichiError> {
+        let round = match round.as_array() {
+            Some(round) if round.len() >= 17 => round,
+            _ => return Err(RiichiError::new(187, "Wrong tenhou.net/6 round")),
+        };
+
+        let mut draws: [&[Value]; 4] = Default::default();
+        let mut discards: [&[Value]; 4] = Default::default();
+        for seat in 0..4 {
+            draws[seat] = list(&round[5 + 3 * seat])?;
+            discards[seat] = list(&round[6 + 3 * seat])?;
+        }
+
+        Ok(RoundImport {
+            round,
+            draws,
+            discards,
+            next_draw: [0; 4],
+            next_discard: [0; 4],
+            dora_indicators: tiles(&round[2])?,
+            dora_revealed: 1,
+            events: vec![],
+        })
+    }
+
+    fn import(mut self, events: &mut Vec<ReplayEvent>) -> Result<(), RiichiError> {
+        let header = numbers(&self.round[0])?;
+        let points = numbers(&self.round[1])?;
+        if header.len() < 3 || points.len() < 4 || self.dora_indicators.is_empty() {
+            return Err(RiichiError::new(187, "Wrong tenhou.net/6 round"));
+        }
+
+        let mut hands: [Vec<Tile>; 4] = Default::default();
+        for (seat, hand) in hands.iter_mut().enumerate() {
+            *hand = tiles(&self.round[4 + 3 * seat])?;
+        }
+
+        let dealer = (header[0] % 4) as u8;
+        self.events.push(ReplayEvent::Start(HandStart {
+            prevalent_wind: (header[0] / 4 + 1) as u8,
+            dealer,
+            honba: header[1] as u8,
+            riichi_sticks: header[2] as u8,
+            dora_indicator: self.dora_indicators[0],
+            points: [points[0], points[1], points[2], points[3]],
+            hands,
+        }));
+
+        let mut seat = dealer as usize;
+        let mut last_draw: Option<Tile> = None;
+        let mut last_discard: Option<Tile> = None;
+        let mut riichi = [false; 4];
+        let mut riichi_pending: Option<u8> = None;
+        // daiminkan and shouminkan reveal their dora after the replacement discard
+        let mut open_kan_dora = false;
+
+        'turns: while let Some(draw) = self.draws[seat].get(self.next_draw[seat]) {
+            self.next_draw[seat] += 1;
+
+            if let Some(riichi_seat) = riichi_pending.take() {
+                self.events
+                    .push(ReplayEvent::RiichiAccepted { seat: riichi_seat });
+            }
+
+            match draw {
+                Value::String(call) => {
+                    let meld = meld_from_string(call)?;
+                    self.events.push(ReplayEvent::Call {
+                        seat: seat as u8,
+                        meld,
+                    });
+                    last_draw = None;
+
+                    if let CompleteShape::Open(OpenShape::Kan(_)) = meld {
+                        // the discard list has a 0 where the daiminkan was
+                        self.next_discard[seat] += 1;
+                        open_kan_dora = true;
+                        continue 'turns;
+                    }
+                }
+                draw => {
+                    let tile = tile(draw)?;
+                    self.events.push(ReplayEvent::Draw {
+                        seat: seat as u8,
+                        tile,
+                    });
+                    last_draw = Some(tile);
+                }
+            }
+
+            let discard = match self.discards[seat].get(self.next_discard[seat]) {
+                Some(discard) => discard,
+                None => break,
+            };
+            self.next_discard[seat] += 1;
+
+            let (number, is_riichi) = match discard {
+                Value::String(discard) if discard.starts_with('r') => {
+                    (parse_number(&discard[1..])?, true)
+                }
+                Value::String(kan) => {
+                    let meld = meld_from_string(kan)?;
+                    if open_kan_dora {
+                        open_kan_dora = false;
+                        self.reveal_dora();
+                    }
+                    self.events.push(ReplayEvent::Call {
+                        seat: seat as u8,
+                        meld,
+                    });
+                    // a kan can be robbed
+                    last_discard = match meld {
+                        CompleteShape::Open(OpenShape::Kan(OpenKan::Shouminkan(tiles))) => {
+                            Some(tiles[3])
+                        }
+                        CompleteShape::Closed(ClosedShape::Kantsu(tiles)) => Some(tiles[0]),
+                        _ => last_discard,
+                    };
+                    match meld {
+                        CompleteShape::Closed(_) => self.reveal_dora(),
+                        _ => open_kan_dora = true,
+                    }
+                    continue 'turns;
+                }
+                discard => match discard.as_u64() {
+                    Some(number) => (number as u8, false),
+                    None => return Err(RiichiError::new(187, "Wrong tenhou.net/6 discard")),
+                },
+            };
+
+            let mut tile = match (number, last_draw) {
+                (TSUMOGIRI, Some(drawn)) => {
+                    let mut drawn = drawn;
+                    drawn.is_tsumogiri = true;
+                    drawn
+                }
+                (TSUMOGIRI, None) => {
+                    return Err(RiichiError::new(187, "Tsumogiri without a drawn tile"))
+                }
+                (number, _) => tile_from_number(number)?,
+            };
+            tile.is_riichi = is_riichi;
+
+            if is_riichi {
+                riichi[seat] = true;
+                riichi_pending = Some(seat as u8);
+                self.events.push(ReplayEvent::Riichi { seat: seat as u8 });
+            }
+            self.events.push(ReplayEvent::Discard {
+                seat: seat as u8,
+                tile,
+            });
+            last_discard = Some(tile);
+            if open_kan_dora {
+                open_kan_dora = false;
+                self.reveal_dora();
+            }
+
+            seat = self.next_seat(seat, &tile);
+        }
+
+        let result = match self.round[16].as_array() {
+            Some(result) if !result.is_empty() => result,
+            _ => return Err(RiichiError::new(187, "Wrong tenhou.net/6 result")),
+        };
+        match result[0].as_str() {
+            Some("和了") => {
+                for win in result[1..].chunks(2) {
+                    if win.len() < 2 {
+                        return Err(RiichiError::new(187, "Wrong tenhou.net/6 result"));
+                    }
+
+                    let win = self.win(&win[0], &win[1], last_draw, last_discard, &riichi)?;
+                    self.events.push(ReplayEvent::Win(win));
+                }
+            }
+            Some(name) => {
+                let (reason, tenpai) = match name {
+                    "九種九牌" => (DrawReason::KyuushuKyuuhai, None),
+                    "四風連打" => (DrawReason::SuufonRenda, None),
+                    "四家立直" => (DrawReason::SuuchaRiichi, None),
+                    "四槓散了" => (DrawReason::Suukaikan, None),
+                    "三家和了" => (DrawReason::Sanchahou, None),
+                    "全員聴牌" => (DrawReason::Exhaustive, Some(true)),
+                    "全員不聴" => (DrawReason::Exhaustive, Some(false)),
+                    // the log doesn't say who was tenpai
+                    "流し満貫" => (DrawReason::Exhaustive, Some(false)),
+                    _ => (DrawReason::Exhaustive, None),
+                };
+                let deltas = match result.get(1) {
+                    Some(deltas) => deltas_from(deltas)?,
+                    None => [0; 4],
+                };
+                let nagashi_mangan = match name {
+                    "流し満貫" => deltas.map(|d| d > 0),
+                    _ => [false; 4],
+                };
+
+                // the riichi stick of a ron on the riichi tile is not paid
+                if let Some(riichi_seat) = riichi_pending {
+                    if reason != DrawReason::Sanchahou {
+                        self.events
+                            .push(ReplayEvent::RiichiAccepted { seat: riichi_seat });
+                    }
+                }
+
+                self.events.push(ReplayEvent::Ryuukyoku(HandDraw {
+                    reason,
+                    tenpai: match tenpai {
+                        Some(all) => [all; 4],
+                        None if reason == DrawReason::Exhaustive => deltas.map(|d| d > 0),
+                        None => [false; 4],
+                    },
+                    nagashi_mangan,
+                    deltas,
+                }));
+            }
+            None => return Err(RiichiError::new(187, "Wrong tenhou.net/6 result")),
+        }
+
+        events.append(&mut self.events);
+
+        Ok(())
+    }
+
+    /// Someone calling this seat's discard is next, otherwise the next seat draws.
+    /// Pon and kan go before chi.
+    fn next_seat(&self, seat: usize, discard: &Tile) -> usize {
+        let mut caller = None;
+        for offset in 1..4 {
+            let other = (seat + offset) % 4;
+            if let Some(Value::String(call)) = self.draws[other].get(self.next_draw[other]) {
+                // the call has to be of this tile, it can be a later one from the same seat
+                if call_from(call) != Some(4 - offset as u8)
+                    || called_tile(call).as_ref() != Some(discard)
+                {
+                    continue;
+                }
+
+                if !call.contains('c') {
+                    return other;
+                }
+                caller = Some(other);
+            }
+        }
+
+        caller.unwrap_or((seat + 1) % 4)
+    }
+
+    fn reveal_dora(&mut self) {
+        if let Some(indicator) = self.dora_indicators.get(self.dora_revealed) {
+            self.events.push(ReplayEvent::Dora {
+                indicator: *indicator,
+            });
+            self.dora_revealed += 1;
+        }
+    }
+
+    /// A win from its point changes and [who, from, liable, score, yaku...]
+    fn win(
+        &self,
+        deltas: &Value,
+        info: &Value,
+        last_draw: Option<Tile>,
+        last_discard: Option<Tile>,
+        riichi: &[bool; 4],
+    ) -> Result<HandWin, RiichiError> {
+        let wrong = || RiichiError::new(187, "Wrong tenhou.net/6 win");
+        let info = match info.as_array() {
+            Some(info) if info.len() >= 4 => info,
+            _ => return Err(wrong()),
+        };
+
+        let mut seats = [0u8; 3];
+        for (i, seat) in seats.iter_mut().enumerate() {
+            *seat = match info[i].as_u64() {
+                Some(seat) if seat < 4 => seat as u8,
+                _ => return Err(wrong()),
+            };
+        }
+        let [seat, from, liable] = seats;
+
+        let score = info[3].as_str().ok_or_else(wrong)?;
+        let yaku: Vec<&str> = info[4..].iter().filter_map(|yaku| yaku.as_str()).collect();
+        let han = if yaku.is_empty() {
+            number_before(score, '飜')
+        } else {
+            yaku.iter().map(|yaku| number_before(yaku, '飜')).sum()
+        };
+        let yakuman = yaku.iter().filter(|yaku| yaku.contains("役満")).count() as u8;
+
+        let tile = if seat == from {
+            last_draw
+        } else {
+            last_discard
+        };
+
+        Ok(HandWin {
+            seat,
+            from,
+            liable: if liable == seat { None } else { Some(liable) },
+            tile: tile.ok_or_else(wrong)?,
+            han: if yakuman > 0 { 0 } else { han as u8 },
+            fu: number_before(score, '符') as u8,
+            yakuman,
+            points: score_points(score).ok_or_else(wrong)?,
+            ura_dora_indicators: if riichi[seat as usize] {
+                tiles(&self.round[3])?
+            } else {
+                vec![]
+            },
+            deltas: deltas_from(deltas)?,
+        })
+    }
+}
+
+/// One round being written: lists are filled event by event
+struct RoundExport {
+    start: HandStart,
+    dora_indicators: Vec<u8>,
+    ura_dora_indicators: Vec<u8>,
+    draws: [Vec<Value>; 4],
+    discards: [Vec<Value>; 4],
+    result: Vec<Value>,
+}
+
+impl RoundExport {
+    fn new(start: &HandStart) -> RoundExport {
+        RoundExport {
+            start: start.clone(),
+            dora_indicators: vec![tile_to_number(&start.dora_indicator)],
+            ura_dora_indicators: vec![],
+            draws: Default::default(),
+            discards: Default::default(),
+            result: vec![],
+        }
+    }
+
+    fn add(&mut self, event: &ReplayEvent) -> Result<(), RiichiError> {
+        match event {
+            ReplayEvent::Start(_)
+            | ReplayEvent::Riichi { .. }
+            | ReplayEvent::RiichiAccepted { .. } => {}
+            ReplayEvent::Draw { seat, tile } => {
+                self.draws[*seat as usize].push(json!(tile_to_number(tile)));
+            }
+            ReplayEvent::Discard { seat, tile } => {
+                let number = if tile.is_tsumogiri {
+                    TSUMOGIRI
+                } else {
+                    tile_to_number(tile)
+                };
+
+                self.discards[*seat as usize].push(if tile.is_riichi {
+                    json!(format!("r{}", number))
+                } else {
+                    json!(number)
+                });
+            }
+            ReplayEvent::Call { seat, meld } => {
+                let call = json!(meld_to_string(meld)?);
+                let seat = *seat as usize;
+                match meld {
+                    CompleteShape::Open(OpenShape::Kan(OpenKan::Daiminkan(_))) => {
+                        self.draws[seat].push(call);
+                        self.discards[seat].push(json!(0));
+                    }
+                    CompleteShape::Open(OpenShape::Kan(OpenKan::Shouminkan(_)))
+                    | CompleteShape::Closed(_) => self.discards[seat].push(call),
+                    CompleteShape::Open(_) => self.draws[seat].push(call),
+                }
+            }
+            ReplayEvent::Dora { indicator } => self.dora_indicators.push(tile_to_number(indicator)),
+            ReplayEvent::Win(win) => {
+                if self.result.is_empty() {
+                    self.result.push(json!("和了"));
+                }
+                if self.ura_dora_indicators.is_empty() {
+                    self.ura_dora_indicators =
+                        win.ura_dora_indicators.iter().map(tile_to_number).collect();
+                }
+
+                self.result.push(json!(win.deltas));
+                self.result.push(json!([
+                    win.seat,
+                    win.from,
+                    win.liable.unwrap_or(win.seat),
+                    self.score(win)
+                ]));
+            }
+            ReplayEvent::Ryuukyoku(draw) => {
+                let name = match draw.reason {
+                    DrawReason::KyuushuKyuuhai => "九種九牌",
+                    DrawReason::SuufonRenda => "四風連打",
+                    DrawReason::SuuchaRiichi => "四家立直",
+                    DrawReason::Suukaikan => "四槓散了",
+                    DrawReason::Sanchahou => "三家和了",
+                    DrawReason::Exhaustive if draw.nagashi_mangan.contains(&true) => "流し満貫",
+                    DrawReason::Exhaustive if draw.deltas == [0; 4] && draw.tenpai == [true; 4] => {
+                        "全員聴牌"
+                    }
+                    DrawReason::Exhaustive
+                        if draw.deltas == [0; 4] && draw.tenpai == [false; 4] =>
+                    {
+                        "全員不聴"
+                    }
+                    DrawReason::Exhaustive => "流局",
+                };
+
+                self.result.push(json!(name));
+                if name == "流局" || name == "流し満貫" {
+                    self.result.push(json!(draw.deltas));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Like "30符1飜1000点", "満貫2000-4000点" or "跳満6000点∀". Limits are found from the points.
+    fn score(&self, win: &HandWin) -> String {
+        let dealer = win.seat == self.start.dealer;
+        let base = win.points / if dealer { 6 } else { 4 };
+        let value = match base {
+            _ if win.yakuman > 0 => String::from("役満"),
+            8000..=u32::MAX => String::from("役満"),
+            6000..=7999 => String::from("三倍満"),
+            4000..=5999 => String::from("倍満"),
+            3000..=3999 => String::from("跳満"),
+            2000..=2999 => String::from("満貫"),
+            _ => format!("{}符{}飜", win.fu, win.han),
+        };
+
+        let payment = if win.seat != win.from {
+            win.points.to_string()
+        } else if dealer {
+            (win.points / 3).to_string()
+        } else {
+            // what a non-dealer and the dealer paid, without honba
+            let honba = self.start.honba as i32 * 100;
+            let paid = |seat: u8| (-win.deltas[seat as usize] - honba).max(0);
+            let ko = (0..4)
+                .find(|seat| *seat != win.seat && *seat != self.start.dealer)
+                .map(paid)
+                .unwrap_or_default();
+            format!("{}-{}", ko, paid(self.start.dealer))
+        };
+
+        if win.seat == win.from && dealer {
+            format!("{}{}点∀", value, payment)
+        } else {
+            format!("{}{}点", value, payment)
+        }
+    }
+
+    fn to_json(&self) -> Value {
+        let start = &self.start;
+        let mut round = vec![
+            json!([
+                (start.prevalent_wind - 1) * 4 + start.dealer,
+                start.honba,
+                start.riichi_sticks
+            ]),
+            json!(start.points),
+            json!(self.dora_indicators),
+            json!(self.ura_dora_indicators),
+        ];
+
+        for seat in 0..4 {
+            let hand: Vec<u8> = start.hands[seat].iter().map(tile_to_number).collect();
+            round.push(json!(hand));
+            round.push(json!(self.draws[seat]));
+            round.push(json!(self.discards[seat]));
+        }
+        round.push(json!(self.result));
+
+        json!(round)
+    }
+}
+
+/// 11-19 manzu, 21-29 pinzu, 31-39 souzu, 41-47 honors, 51-53 red 5s
+fn tile_to_number(tile: &Tile) -> u8 {
+    match tile.tile_type {
+        TileType::Number(number, color) => {
+            let suit = match color {
+                TileColor::Manzu => 1,
+                TileColor::Pinzu => 2,
+                TileColor::Souzu => 3,
+            };
+
+            if tile.is_red {
+                50 + suit
+            } else {
+                suit * 10 + number
+            }
+        }
+        TileType::Wind(_) | TileType::Dragon(_) => 40 + tile.get_id() - 27,
+    }
+}
+
+fn tile_from_number(number: u8) -> Result<Tile, RiichiError> {
+    let id = match (number / 10, number % 10) {
+        (suit @ 1..=3, n @ 1..=9) => (suit - 1) * 9 + n,
+        (4, n @ 1..=7) => 27 + n,
+        (5, suit @ 1..=3) => (suit - 1) * 9 + 5,
+        _ => {
+            return Err(RiichiError::new(
+                192,
+                &format!("Wrong tenhou.net/6 tile {}", number)[..],
+            ))
+        }
+    };
+
+    let mut tile = Tile::from_id(id)?;
+    tile.is_red = number > 50;
+
+    Ok(tile)
+}
+
+fn tile(value: &Value) -> Result<Tile, RiichiError> {
+    match value.as_u64() {
+        Some(number) if number < 100 => tile_from_number(number as u8),
+        _ => Err(RiichiError::new(
+            192,
+            &format!("Wrong tenhou.net/6 tile {}", value)[..],
+        )),
+    }
+}
+
+fn tiles(value: &Value) -> Result<Vec<Tile>, RiichiError> {
+    list(value)?.iter().map(tile).collect()
+}
+
+fn list(value: &Value) -> Result<&[Value], RiichiError> {
+    match value.as_array() {
+        Some(list) => Ok(list),
+        None => Err(RiichiError::new(187, "Wrong tenhou.net/6 round")),
+    }
+}
+
+fn numbers(value: &Value) -> Result<Vec<i32>, RiichiError> {
+    list(value)?
+        .iter()
+        .map(|number| match number.as_i64() {
+            Some(number) => Ok(number as i32),
+            None => Err(RiichiError::new(
+                189,
+                &format!("{} is not a number", number)[..],
+            )),
+        })
+        .collect()
+}
+
+fn deltas_from(value: &Value) -> Result<[i32; 4], RiichiError> {
+    match numbers(value)?[..] {
+        [a, b, c, d, ..] => Ok([a, b, c, d]),
+        _ => Err(RiichiError::new(187, "Wrong number of point changes")),
+    }
+}
+
+fn parse_number(value: &str) -> Result<u8, RiichiError> {
+    match value.parse() {
+        Ok(number) => Ok(number),
+        Err(_) => Err(RiichiError::new(
+            189,
+            &format!("{} is not a number", value)[..],
+        )),
+    }
+}
+
+/// The number written right before a character, like the 30 in "30符", 0 if there is none
+fn number_before(text: &str, mark: char) -> u32 {
+    match text.find(mark) {
+        Some(end) => {
+            let digits: String = text[..end]
+                .chars()
+                .rev()
+                .take_while(|c| c.is_ascii_digit())
+                .collect();
+            digits
+                .chars()
+                .rev()
+                .collect::<String>()
+                .parse()
+                .unwrap_or(0)
+        }
+        None => 0,
+    }
+}
+
+/// Value of the hand from "…1000点", "…300-500点" or "…1000点∀"
+fn score_points(score: &str) -> Option<u32> {
+    let end = score.find('点')?;
+    let start = score[..end]
+        .rfind(|c: char| !c.is_ascii_digit() && c != '-')
+        .map(|i| i + score[i..].chars().next().unwrap().len_utf8())
+        .unwrap_or(0);
+    let payment = &score[start..end];
+
+    match payment.split_once('-') {
+        Some((ko, oya)) => Some(ko.parse::<u32>().ok()? * 2 + oya.parse::<u32>().ok()?),
+        None if score.ends_with('∀') => Some(payment.parse::<u32>().ok()? * 3),
+        None => payment.parse().ok(),
+    }
+}
+
+/// Who the tile of a call string came from (1 = shimocha, 2 = toimen, 3 = kamicha), None for ankan
+fn call_from(call: &str) -> Option<u8> {
+    let position = call.find(|c: char| c.is_ascii_alphabetic())?;
+    match (&call[position..=position], position) {
+        ("a", _) => None,
+        ("c", _) | (_, 0) => Some(3),
+        (_, 2) => Some(2),
+        _ => Some(1),
+    }
+}
+
+/// The tile called from another seat in a call string
+fn called_tile(call: &str) -> Option<Tile> {
+    let tiles: Vec<Tile> = match meld_from_string(call).ok()? {
+        CompleteShape::Open(OpenShape::Chi(tiles)) | CompleteShape::Open(OpenShape::Pon(tiles)) => {
+            tiles.to_vec()
+        }
+        CompleteShape::Open(OpenShape::Kan(OpenKan::Daiminkan(tiles))) => tiles.to_vec(),
+        _ => return None,
+    };
+
+    tiles.into_iter().find(|tile| tile.called_from != 0)
+}
+
+/// Call strings have the called tile after a letter and the letter's position shows who it came from:
+/// "c131112" chi, "p" pon, "m" daiminkan, "k" added kan (the added tile, then the called one) and "a" ankan.
+fn meld_from_string(call: &str) -> Result<CompleteShape, RiichiError> {
+    let invalid = || RiichiError::new(191, &format!("Wrong call {}", call)[..]);
+
+    let position = match call.find(|c: char| c.is_ascii_alphabetic()) {
+        Some(position) if position % 2 == 0 => position,
+        _ => return Err(invalid()),
+    };
+    let kind = &call[position..=position];
+    let numbers = format!("{}{}", &call[..position], &call[position + 1..]);
+    if numbers.len() % 2 != 0 || !numbers.is_ascii() {
+        return Err(invalid());
+    }
+
+    let mut tiles = vec![];
+    for i in (0..numbers.len()).step_by(2) {
+        tiles.push(tile_from_number(parse_number(&numbers[i..i + 2])?)?);
+    }
+
+    // the added tile of a kakan comes before the called one
+    let added = if kind == "k" && position / 2 < tiles.len() {
+        Some(tiles.remove(position / 2))
+    } else {
+        None
+    };
+    if kind != "a" && position / 2 < tiles.len() {
+        // the called tile goes first, like in the game
+        let mut called = tiles.remove(position / 2);
+        called.called_from = call_from(call).unwrap();
+        tiles.insert(0, called);
+    }
+    let same = |tiles: &[Tile]| tiles.iter().all(|tile| *tile == tiles[0]);
+
+    match (kind, tiles.len()) {
+        ("c", 3) => {
+            let mut chi = [tiles[0], tiles[1], tiles[2]];
+            chi.sort();
+            if chi[0].next(false) != Some(chi[1]) || chi[1].next(false) != Some(chi[2]) {
+                return Err(invalid());
+            }
+
+            Ok(CompleteShape::Open(OpenShape::Chi(chi)))
+        }
+        ("p", 3) if same(&tiles) => Ok(CompleteShape::Open(OpenShape::Pon([
+            tiles[0], tiles[1], tiles[2],
+        ]))),
+        ("m", 4) if same(&tiles) => Ok(CompleteShape::Open(OpenShape::Kan(OpenKan::Daiminkan([
+            tiles[0], tiles[1], tiles[2], tiles[3],
+        ])))),
+        ("k", 3) if same(&tiles) && added == Some(tiles[0]) => {
+            Ok(CompleteShape::Open(OpenShape::Kan(OpenKan::Shouminkan([
+                tiles[0],
+                tiles[1],
+                tiles[2],
+                added.unwrap(),
+            ]))))
+        }
+        ("a", 4) if same(&tiles) => Ok(CompleteShape::Closed(ClosedShape::Kantsu([
+            tiles[0], tiles[1], tiles[2], tiles[3],
+        ]))),
+        _ => Err(invalid()),
+    }
+}
+
+fn meld_to_string(meld: &CompleteShape) -> Result<String, RiichiError> {
+    let invalid = || RiichiError::new(191, "These tiles can't make this call");
+    let join = |tiles: &[Tile]| -> String {
+        tiles
+            .iter()
+            .map(|tile| tile_to_number(tile).to_string())
+            .collect()
+    };
+    let split = |tiles: &[Tile]| -> Result<(Tile, Vec<Tile>), RiichiError> {
+        let called = tiles
+            .iter()
+            .position(|tile| (1..=3).contains(&tile.called_from))
+            .ok_or_else(invalid)?;
+        let mut own = tiles.to_vec();
+        let called = own.remove(called);
+        Ok((called, own))
+    };
+    // the letter and called tile go before the own tiles for kamicha, after the first one for toimen
+    // and after all of them for shimocha
+    let place = |letter: &str, called: &Tile, own: &[Tile]| -> String {
+        let call = format!("{}{}", letter, tile_to_number(called));
+        match called.called_from {
+            3 => format!("{}{}", call, join(own)),
+            2 => format!("{}{}{}", join(&own[..1]), call, join(&own[1..])),
+            _ => format!("{}{}", join(own), call),
+        }
+    };
+
+    match meld {
+        CompleteShape::Open(OpenShape::Chi(tiles)) => {
+            let (called, own) = split(tiles)?;
+            Ok(format!("c{}{}", tile_to_number(&called), join(&own)))
+        }
+        CompleteShape::Open(OpenShape::Pon(tiles)) => {
+            let (called, own) = split(tiles)?;
+            Ok(place("p", &called, &own))
+        }
+        CompleteShape::Open(OpenShape::Kan(OpenKan::Daiminkan(tiles))) => {
+            let (called, own) = split(tiles)?;
+            Ok(place("m", &called, &own))
+        }
+        CompleteShape::Open(OpenShape::Kan(OpenKan::Shouminkan(tiles))) => {
+            let (called, own) = split(&tiles[..3])?;
+            let letter = format!("k{}", tile_to_number(&tiles[3]));
+            Ok(place(&letter, &called, &own))
+        }
+        CompleteShape::Closed(ClosedShape::Kantsu(tiles)) => {
+            // a red 5 is shown as the last tile
+            let mut tiles = *tiles;
+            tiles.sort_by_key(|tile| tile.is_red);
+            Ok(format!("{}a{}", join(&tiles[..3]), join(&tiles[3..])))
+        }
+        _ => Err(invalid()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::riichi::game::{Game, Player};
+    use crate::riichi::table::{Action, Table};
+
+    // dealer riichi with tsumogiri, a pon from toimen and a ron on the riichi player's wait
+    const LOG: &str = r#"{"title":["",""],"name":["A","B","C","D"],
+        "rule":{"disp":"般南喰赤","aka":1},
+        "log":[[[0,0,0],[25000,25000,25000,25000],[12],[38],
+            [11,12,13,21,22,23,31,32,33,41,41,35,36],[47],["r60"],
+            [14,15,16,24,25,26,34,35,36,43,43,47,19],[21,"43p4343"],[60,34],
+            [17,18,19,27,28,29,37,38,39,44,44,42,46],[31],[42],
+            [11,52,15,16,24,26,27,37,38,46,46,45,45],[43],[60],
+            ["和了",[6200,-5200,0,0],[0,1,0,"40符3飜5200点","立直(1飜)","三色同順(2飜)"]]]]}"#;
+
+    /// Seat 1 adds the 4th 7m to their pon and seat 0 robs it
+    const CHANKAN_LOG: &str = r#"{"title":["",""],"name":["A","B","C","D"],
+        "rule":{"disp":"般南喰赤","aka":1},
+        "log":[[[0,0,0],[25000,25000,25000,25000],[12],[],
+            [11,12,13,21,22,23,31,32,33,41,41,15,16],[42,37],[60,60],
+            [17,17,24,25,26,34,35,36,43,43,43,19,28],[29,"17p1717",17],[19,28,"17k171717"],
+            [14,18,27,37,38,39,44,44,44,46,46,47,47],[11,39],[60,60],
+            [17,27,28,29,38,39,45,45,45,47,19,18,13],[31,32],[17,60],
+            ["和了",[2400,-2400,0,0],[0,1,0,"50符1飜2400点","槍槓(1飜)"]]]]}"#;
+
+    /// Seat 3 pons the first discard of seat 0, seat 2 a later one
+    const CALLS_LOG: &str = r#"{"title":["",""],"name":["A","B","C","D"],
+        "rule":{"disp":"般南喰赤","aka":1},
+        "log":[[[0,0,0],[25000,25000,25000,25000],[12],[],
+            [11,12,13,14,15,16,17,18,19,21,22,45,46],[31,32],[45,46],
+            [21,22,23,24,25,26,27,28,29,31,32,33,34],[],[],
+            [11,12,13,14,15,16,17,18,29,35,36,46,46],["46p4646"],[29],
+            [21,22,23,24,25,26,27,28,19,35,36,45,45],["4545p45",33],[19,60],
+            ["流局",[0,0,0,0]]]]}"#;
+
+    /// Wins, declares riichi and calls whenever it can
+    struct CallingPlayer;
+
+    impl Player for CallingPlayer {
+        fn choose(&mut self, table: &Table, actions: &[Action]) -> Action {
+            let preferred = actions.iter().find(|action| {
+                !matches!(
+                    action,
+                    Action::Discard(_) | Action::KyuushuKyuuhai | Action::Pass
+                )
+            });
+            if let Some(action) = preferred {
+                return *action;
+            }
+
+            if let Some(drawn) = table.get_my_hand().get_drawn_tile() {
+                if actions.contains(&Action::Discard(*drawn)) {
+                    return Action::Discard(*drawn);
+                }
+            }
+
+            actions
+                .iter()
+                .find(|action| matches!(action, Action::Discard(_)))
+                .copied()
+                .unwrap_or(Action::Pass)
+        }
+    }
+
+    #[test]
+    fn tenhou_json_events() {
+        let replay = Replay::from_tenhou_json(LOG).unwrap();
+
+        assert_eq!(replay.players[3], "D");
+        assert_eq!(replay.rules.game_length, GameLength::Hanchan);
+        assert_eq!(replay.events.len(), 14);
+        assert_eq!(replay.events[2], ReplayEvent::Riichi { seat: 0 });
+        match &replay.events[3] {
+            ReplayEvent::Discard { seat: 0, tile } => {
+                assert_eq!(tile.to_string(), "7z");
+                assert!(tile.is_riichi);
+                assert!(tile.is_tsumogiri);
+            }
+            event => panic!("{:?} is not the riichi discard", event),
+        }
+        assert_eq!(replay.events[4], ReplayEvent::RiichiAccepted { seat: 0 });
+        match &replay.events[11] {
+            ReplayEvent::Call {
+                seat: 1,
+                meld: CompleteShape::Open(OpenShape::Pon(tiles)),
+            } => {
+                assert_eq!(tiles[0].to_string(), "3z");
+                assert_eq!(tiles[0].called_from, 2);
+            }
+            event => panic!("{:?} is not the pon", event),
+        }
+        match &replay.events[13] {
+            ReplayEvent::Win(win) => {
+                assert_eq!((win.seat, win.from, win.liable), (0, 1, None));
+                assert_eq!(win.tile.to_string(), "4s");
+                assert_eq!((win.han, win.fu, win.points), (3, 40, 5200));
+                assert_eq!(win.ura_dora_indicators[0].to_string(), "8s");
+                assert_eq!(win.deltas, [6200, -5200, 0, 0]);
+            }
+            event => panic!("{:?} is not the win", event),
+        }
+
+        // the red 5 of the last seat
+        match &replay.events[0] {
+            ReplayEvent::Start(start) => assert!(start.hands[3][1].is_red),
+            event => panic!("{:?} is not the start", event),
+        }
+
+        let table = replay.table_at(13, 0).unwrap();
+        assert_eq!(table.get_points(0), Some(24000));
+        assert_eq!(table.get_riichi_sticks(), 1);
+
+        // a robbed kan is won on the added tile
+        let replay = Replay::from_tenhou_json(CHANKAN_LOG).unwrap();
+        match &replay.events[replay.events.len() - 2] {
+            ReplayEvent::Call {
+                seat: 1,
+                meld: CompleteShape::Open(OpenShape::Kan(OpenKan::Shouminkan(_))),
+            } => {}
+            event => panic!("{:?} is not the added kan", event),
+        }
+        match replay.events.last().unwrap() {
+            ReplayEvent::Win(win) => {
+                assert_eq!((win.seat, win.from), (0, 1));
+                assert_eq!(win.tile.to_string(), "7m");
+            }
+            event => panic!("{:?} is not the win", event),
+        }
+    }
+
+    #[test]
+    fn calls_follow_the_discarded_tile() {
+        let replay = Replay::from_tenhou_json(CALLS_LOG).unwrap();
+        let callers: Vec<u8> = replay
+            .events
+            .iter()
+            .filter_map(|event| match event {
+                ReplayEvent::Call { seat, .. } => Some(*seat),
+                _ => None,
+            })
+            .collect();
+
+        assert_eq!(callers, vec![3, 2]);
+        assert_eq!(replay.events.len(), 12);
+        assert!(matches!(
+            replay.events[11],
+            ReplayEvent::Ryuukyoku(HandDraw {
+                reason: DrawReason::Exhaustive,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn nagashi_mangan() {
+        let log = CALLS_LOG.replace(
+            r#"["流局",[0,0,0,0]]"#,
+            r#"["流し満貫",[-4000,8000,-2000,-2000]]"#,
+        );
+        let replay = Replay::from_tenhou_json(&log).unwrap();
+
+        match replay.events.last().unwrap() {
+            ReplayEvent::Ryuukyoku(draw) => {
+                assert_eq!(draw.reason, DrawReason::Exhaustive);
+                assert_eq!(draw.nagashi_mangan, [false, true, false, false]);
+                assert_eq!(draw.tenpai, [false; 4]);
+            }
+            event => panic!("{:?} is not the draw", event),
+        }
+
+        let json: Value = serde_json::from_str(&replay.to_tenhou_json().unwrap()).unwrap();
+        assert_eq!(
+            json["log"][0][16],
+            json!(["流し満貫", [-4000, 8000, -2000, -2000]])
+        );
+    }
+
+    #[test]
+    fn tenhou_json_round_trip() {
+        let replay = Replay::from_tenhou_json(LOG).unwrap();
+        let json: Value = serde_json::from_str(&replay.to_tenhou_json().unwrap()).unwrap();
+        let original: Value = serde_json::from_str(LOG).unwrap();
+
+        // the yaku list is not kept
+        let round = json["log"][0].as_array().unwrap();
+        assert_eq!(round[..16], original["log"][0].as_array().unwrap()[..16]);
+        assert_eq!(
+            round[16],
+            json!(["和了", [6200, -5200, 0, 0], [0, 1, 0, "40符3飜5200点"]])
+        );
+        assert_eq!(json["name"], original["name"]);
+    }
+
+    #[test]
+    fn game_round_trip() {
+        let mut game = Game::new(Rules::tenhou(), 11);
+        let mut players: [Box<dyn Player>; 4] = [
+            Box::new(CallingPlayer),
+            Box::new(CallingPlayer),
+            Box::new(CallingPlayer),
+            Box::new(CallingPlayer),
+        ];
+        for _ in 0..8 {
+            let outcome = game.play_hand(&mut players);
+            if !game.next_hand(&outcome) {
+                break;
+            }
+        }
+
+        let replay = game.get_replay();
+        let json = replay.to_tenhou_json().unwrap();
+        let imported = Replay::from_tenhou_json(&json).unwrap();
+
+        assert_eq!(imported.to_tenhou_json().unwrap(), json);
+        assert_eq!(imported.events.len(), replay.events.len());
+        for (imported, played) in imported.events.iter().zip(replay.events.iter()) {
+            match (imported, played) {
+                (ReplayEvent::Win(imported), ReplayEvent::Win(played)) => {
+                    assert_eq!(imported.points, played.points);
+                    assert_eq!(imported.deltas, played.deltas);
+                }
+                (imported, played) => assert_eq!(imported, played),
+            }
+        }
+        assert!(replay
+            .events
+            .iter()
+            .any(|e| matches!(e, ReplayEvent::Call { .. })));
+    }
+
+    #[test]
+    fn call_strings() {
+        for call in [
+            "c245226",
+            "p161616",
+            "16p1616",
+            "1616p16",
+            "m39393939",
+            "393939m39",
+        ]
+        .iter()
+        {
+            let meld = meld_from_string(call).unwrap();
+            assert_eq!(meld_to_string(&meld).unwrap(), *call);
+        }
+
+        // added kan from toimen, ankan with a red 5
+        for call in ["16k161616", "151515a51"].iter() {
+            let meld = meld_from_string(call).unwrap();
+            assert_eq!(meld_to_string(&meld).unwrap(), *call);
+        }
+
+        assert_eq!(meld_from_string("c282931").unwrap_err().code, 191);
+        assert!(meld_from_string("p161617").is_err());
+        assert!(meld_from_string("x161616").is_err());
+        assert_eq!(tile_from_number(48).unwrap_err().code, 192);
+    }
+}
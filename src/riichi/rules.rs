@@ -1,4 +1,7 @@
+use crate::riichi::riichi_error::RiichiError;
+
 /// Length of the game
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum GameLength {
     /// 1 round
     Tonpuusen,
@@ -6,13 +9,344 @@ pub enum GameLength {
     Hanchan,
 }
 
+/// What can't be discarded right after a chi or pon
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kuikae {
+    /// Any tile can be discarded
+    Allowed,
+    /// The called tile can't be discarded (genbutsu kuikae)
+    Genbutsu,
+    /// Neither the called tile nor the tile on the other side of a chi can be discarded (genbutsu and suji kuikae)
+    Forbidden,
+}
+
+/// Which closed kans are allowed in riichi
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RiichiKan {
+    /// The waits can't change and the kan tiles have to be a triplet in every way the hand can win
+    Strict,
+    /// Only the waits can't change
+    Lenient,
+}
+
+/// What happens when more players can ron the same tile
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MultipleRon {
+    /// Only the first player in turn order from the discarder wins
+    Atamahane,
+    /// Two players can win, three players ron is handled by AbortiveDraws::sanchahou (or atamahane)
+    DoubleRon,
+    /// Up to three players can win
+    TripleRon,
+}
+
+/// How players with the same points are placed at the end
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TieBreak {
+    /// The player who started closer to the first dealer gets the better place
+    SeatOrder,
+    /// Tied players share the place and split its uma (and oka)
+    Shared,
+}
+
+/// Which abortive draws are played
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AbortiveDraws {
+    /// 9 different terminals and honors in the starting hand
+    pub kyuushu_kyuuhai: bool,
+    /// All players discard the same wind on their first turn
+    pub suufon_renda: bool,
+    /// All four players declare riichi
+    pub suucha_riichi: bool,
+    /// Four kans declared by more than one player
+    pub suukaikan: bool,
+    /// Three players ron the same tile
+    pub sanchahou: bool,
+}
+
+impl AbortiveDraws {
+    /// All abortive draws are played
+    pub fn all() -> AbortiveDraws {
+        AbortiveDraws {
+            kyuushu_kyuuhai: true,
+            suufon_renda: true,
+            suucha_riichi: true,
+            suukaikan: true,
+            sanchahou: true,
+        }
+    }
+
+    /// No abortive draws
+    pub fn none() -> AbortiveDraws {
+        AbortiveDraws {
+            kyuushu_kyuuhai: false,
+            suufon_renda: false,
+            suucha_riichi: false,
+            suukaikan: false,
+            sanchahou: false,
+        }
+    }
+}
+
 /// All rules will go here
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Rules {
     /// How long is the game?
     pub game_length: GameLength,
-    /// Do we use red 5s?
-    pub aka_ari: bool,
+    /// Points everyone starts with
+    pub starting_points: i32,
+    /// Points needed to end the game after the last hand
+    pub target_points: i32,
+    /// Points subtracted from everyone's final points (kaeshi)
+    pub return_points: i32,
+    /// Bonus for the first place, usually what everyone paid by returning more points than they started with
+    pub oka: i32,
+    /// Bonus for the 1st - 4th place, in points (10-20 uma is [20000, 10000, -10000, -20000])
+    pub uma: [i32; 4],
+    /// How players with the same points are placed
+    pub tie_break: TieBreak,
+    /// Does the game end when someone goes below zero (tobi)?
+    pub tobi: bool,
+    /// Does the game end when the dealer of the last hand wins or is tenpai in first place (agari-yame)?
+    pub agari_yame: bool,
+    /// Is one more round played when nobody has the target points after the last hand (sudden death)?
+    pub overtime: bool,
+    /// How many red 5s are in each suit? (manzu, pinzu, souzu)
+    pub aka: [u8; 3],
     /// Do we allow open tanyao?
     pub kuitan_ari: bool,
-    // TODO more rules
+    /// Are 4 han 30 fu and 3 han 60 fu rounded up to mangan?
+    pub kiriage_mangan: bool,
+    /// How many fu does a pair of the prevalent wind that is also my seat wind give? (2 or 4)
+    pub double_wind_pair_fu: u8,
+    /// Can pinfu be won by tsumo?
+    pub tsumo_pinfu: bool,
+    /// Do 13+ han from regular yaku and dora count as yakuman? If not, they are capped at sanbaiman.
+    pub kazoe_yakuman: bool,
+    /// Do multiple yakuman in one hand stack (daisangen + tsuuiisou = 2 yakuman)?
+    pub multiple_yakuman: bool,
+    /// Are suuankou tanki, kokushi 13-sided wait, junsei chuuren and daisuushii double yakuman?
+    pub double_yakuman: bool,
+    /// Is nagashi mangan paid at an exhaustive draw?
+    pub nagashi_mangan: bool,
+    /// Swap-calling restrictions
+    pub kuikae: Kuikae,
+    /// Which closed kans are allowed in riichi
+    pub riichi_kan: RiichiKan,
+    /// Can a closed kan in riichi be made without the drawn tile (okurikan)?
+    pub okurikan: bool,
+    /// Atamahane or multiple ron
+    pub multiple_ron: MultipleRon,
+    /// Does every winner of a multiple ron get honba? Otherwise only the first one from the discarder does.
+    pub multiple_ron_honba: bool,
+    /// Is the player who let someone make suukantsu liable for it (pao)? Daisangen and daisuushii always are.
+    pub suukantsu_pao: bool,
+    /// Which abortive draws are played
+    pub abortive_draws: AbortiveDraws,
+}
+
+impl Rules {
+    /// Rules of tenhou.net ranked lobbies
+    pub fn tenhou() -> Rules {
+        Rules {
+            game_length: GameLength::Hanchan,
+            starting_points: 25000,
+            target_points: 30000,
+            return_points: 30000,
+            oka: 20000,
+            uma: [20000, 10000, -10000, -20000],
+            tie_break: TieBreak::SeatOrder,
+            tobi: true,
+            agari_yame: true,
+            overtime: true,
+            aka: [1, 1, 1],
+            kuitan_ari: true,
+            kiriage_mangan: false,
+            double_wind_pair_fu: 4,
+            tsumo_pinfu: true,
+            kazoe_yakuman: true,
+            multiple_yakuman: true,
+            double_yakuman: false,
+            nagashi_mangan: true,
+            kuikae: Kuikae::Forbidden,
+            riichi_kan: RiichiKan::Strict,
+            okurikan: false,
+            multiple_ron: MultipleRon::DoubleRon,
+            multiple_ron_honba: true,
+            suukantsu_pao: false,
+            abortive_draws: AbortiveDraws::all(),
+        }
+    }
+
+    /// Rules of Mahjong Soul ranked lobbies
+    pub fn mahjong_soul() -> Rules {
+        Rules {
+            game_length: GameLength::Hanchan,
+            starting_points: 25000,
+            target_points: 30000,
+            return_points: 25000,
+            oka: 0,
+            uma: [15000, 5000, -5000, -15000],
+            tie_break: TieBreak::SeatOrder,
+            tobi: true,
+            agari_yame: true,
+            overtime: true,
+            aka: [1, 1, 1],
+            kuitan_ari: true,
+            kiriage_mangan: false,
+            double_wind_pair_fu: 4,
+            tsumo_pinfu: true,
+            kazoe_yakuman: true,
+            multiple_yakuman: true,
+            double_yakuman: true,
+            nagashi_mangan: true,
+            kuikae: Kuikae::Forbidden,
+            riichi_kan: RiichiKan::Lenient,
+            okurikan: false,
+            multiple_ron: MultipleRon::DoubleRon,
+            multiple_ron_honba: true,
+            suukantsu_pao: false,
+            abortive_draws: AbortiveDraws::all(),
+        }
+    }
+
+    /// World Riichi Championship rules
+    pub fn wrc() -> Rules {
+        Rules {
+            game_length: GameLength::Hanchan,
+            starting_points: 30000,
+            target_points: 30000,
+            return_points: 30000,
+            oka: 0,
+            uma: [15000, 5000, -5000, -15000],
+            tie_break: TieBreak::Shared,
+            tobi: false,
+            agari_yame: false,
+            overtime: false,
+            aka: [0, 0, 0],
+            kuitan_ari: true,
+            kiriage_mangan: true,
+            double_wind_pair_fu: 2,
+            tsumo_pinfu: true,
+            kazoe_yakuman: false,
+            multiple_yakuman: false,
+            double_yakuman: false,
+            nagashi_mangan: false,
+            kuikae: Kuikae::Forbidden,
+            riichi_kan: RiichiKan::Lenient,
+            okurikan: false,
+            multiple_ron: MultipleRon::Atamahane,
+            multiple_ron_honba: false,
+            suukantsu_pao: false,
+            abortive_draws: AbortiveDraws::none(),
+        }
+    }
+
+    /// European Mahjong Association riichi rules
+    pub fn ema() -> Rules {
+        Rules {
+            game_length: GameLength::Hanchan,
+            starting_points: 30000,
+            target_points: 30000,
+            return_points: 30000,
+            oka: 0,
+            uma: [15000, 5000, -5000, -15000],
+            tie_break: TieBreak::Shared,
+            tobi: false,
+            agari_yame: false,
+            overtime: false,
+            aka: [0, 0, 0],
+            kuitan_ari: true,
+            kiriage_mangan: false,
+            double_wind_pair_fu: 2,
+            tsumo_pinfu: true,
+            kazoe_yakuman: false,
+            multiple_yakuman: false,
+            double_yakuman: false,
+            nagashi_mangan: false,
+            kuikae: Kuikae::Forbidden,
+            riichi_kan: RiichiKan::Lenient,
+            okurikan: false,
+            multiple_ron: MultipleRon::TripleRon,
+            multiple_ron_honba: false,
+            suukantsu_pao: false,
+            abortive_draws: AbortiveDraws::none(),
+        }
+    }
+
+    /// M-League rules
+    pub fn mleague() -> Rules {
+        Rules {
+            game_length: GameLength::Hanchan,
+            starting_points: 25000,
+            target_points: 30000,
+            return_points: 30000,
+            oka: 20000,
+            uma: [30000, 10000, -10000, -30000],
+            tie_break: TieBreak::SeatOrder,
+            tobi: false,
+            agari_yame: false,
+            overtime: false,
+            aka: [1, 1, 1],
+            kuitan_ari: true,
+            kiriage_mangan: true,
+            double_wind_pair_fu: 2,
+            tsumo_pinfu: true,
+            kazoe_yakuman: true,
+            multiple_yakuman: true,
+            double_yakuman: false,
+            nagashi_mangan: true,
+            kuikae: Kuikae::Forbidden,
+            riichi_kan: RiichiKan::Strict,
+            okurikan: false,
+            multiple_ron: MultipleRon::Atamahane,
+            multiple_ron_honba: false,
+            suukantsu_pao: false,
+            abortive_draws: AbortiveDraws::none(),
+        }
+    }
+
+    /// Find a preset by its name: tenhou, mahjong_soul, wrc, ema or mleague
+    pub fn from_preset(name: &str) -> Result<Rules, RiichiError> {
+        match name {
+            "tenhou" => Ok(Rules::tenhou()),
+            "mahjong_soul" | "majsoul" => Ok(Rules::mahjong_soul()),
+            "wrc" => Ok(Rules::wrc()),
+            "ema" => Ok(Rules::ema()),
+            "mleague" => Ok(Rules::mleague()),
+            _ => Err(RiichiError::new(170, "Unknown rules preset")),
+        }
+    }
+
+    /// Do we play with any red 5s?
+    pub fn aka_ari(&self) -> bool {
+        self.aka.iter().any(|count| *count > 0)
+    }
+}
+
+impl Default for Rules {
+    fn default() -> Rules {
+        Rules::tenhou()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presets_by_name() {
+        assert_eq!(Rules::from_preset("wrc").unwrap(), Rules::wrc());
+        assert_eq!(
+            Rules::from_preset("majsoul").unwrap(),
+            Rules::mahjong_soul()
+        );
+        assert!(Rules::from_preset("unknown").is_err());
+    }
+
+    #[test]
+    fn aka_ari() {
+        assert!(Rules::tenhou().aka_ari());
+        assert!(!Rules::ema().aka_ari());
+    }
 }
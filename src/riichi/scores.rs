@@ -1,12 +1,59 @@
+use crate::riichi::rules::Rules;
+use serde::Serialize;
+
+/// The most yakuman a single hand can have
+/// (daisuushii and suuankou tanki as double yakuman, tsuuiisou, suukantsu)
+pub const MAX_YAKUMAN: u8 = 6;
+
+/// Named score limits
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum Limit {
+    /// Not a limit hand, points are counted from han and fu
+    None,
+    /// 5 han, or a lower hand reaching 2000 base points
+    Mangan,
+    /// 6-7 han
+    Haneman,
+    /// 8-10 han
+    Baiman,
+    /// 11-12 han, or 13+ han when kazoe yakuman is not allowed
+    Sanbaiman,
+    /// 13+ han from regular yaku and dora
+    KazoeYakuman,
+    /// A yakuman hand, possibly multiple yakuman
+    Yakuman,
+}
+
+impl Limit {
+    /// Limit name in english
+    pub fn get_name(&self) -> &str {
+        match self {
+            Limit::None => "",
+            Limit::Mangan => "Mangan",
+            Limit::Haneman => "Haneman",
+            Limit::Baiman => "Baiman",
+            Limit::Sanbaiman => "Sanbaiman",
+            Limit::KazoeYakuman => "Kazoe yakuman",
+            Limit::Yakuman => "Yakuman",
+        }
+    }
+}
+
 /// Score representation
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize)]
 pub struct Score {
     /// number of han
     pub han: u8,
     /// number of fu
     pub fu: u8,
+    /// number of yakuman, 0 for regular hands
+    pub yakuman: u8,
     oya: bool,
     tsumo: bool,
+    #[serde(skip)]
+    kazoe_yakuman: bool,
+    #[serde(skip)]
+    kiriage_mangan: bool,
 }
 
 impl Score {
@@ -20,11 +67,33 @@ impl Score {
         Score {
             han,
             fu: new_fu,
+            yakuman: 0,
             oya,
             tsumo,
+            kazoe_yakuman: true,
+            kiriage_mangan: false,
         }
     }
 
+    /// Create a new yakuman Score
+    pub fn new_yakuman(yakuman: u8, oya: bool, tsumo: bool) -> Score {
+        Score {
+            han: 13,
+            fu: 0,
+            yakuman,
+            oya,
+            tsumo,
+            kazoe_yakuman: true,
+            kiriage_mangan: false,
+        }
+    }
+
+    /// Apply the scoring rules to this Score
+    pub fn set_rules(&mut self, rules: &Rules) {
+        self.kazoe_yakuman = rules.kazoe_yakuman;
+        self.kiriage_mangan = rules.kiriage_mangan;
+    }
+
     /// Finds the first han + fu combination that reaches at least the value of points given.
     pub fn from_points(points: u32, oya: bool, tsumo: bool, fu_limit: u8) -> Option<Vec<Score>> {
         let base_points: f32 = if oya {
@@ -35,16 +104,17 @@ impl Score {
 
         //        println!("base points {}", base_points);
 
-        // 8000 is base for yakuman - if it's more, this score can't be reached by a hand
-        // TODO double yakumans?
-        if base_points > 8000f32 {
+        // 8000 is base for a single yakuman - if it's more than the most yakuman a hand can have,
+        // this score can't be reached by a hand
+        if base_points > 8000f32 * MAX_YAKUMAN as f32 {
             return None;
         }
 
         let mut scores = vec![];
 
         if base_points > 6000f32 {
-            scores.push(Score::new(13, 0, oya, tsumo));
+            let yakuman = (base_points / 8000f32).ceil() as u8;
+            scores.push(Score::new_yakuman(yakuman, oya, tsumo));
             return Some(scores);
         }
 
@@ -129,80 +199,145 @@ impl Score {
         None
     }
 
+    /// Which limit does this Score reach?
+    pub fn limit(&self) -> Limit {
+        if self.yakuman > 0 {
+            Limit::Yakuman
+        } else if self.han >= 13 && self.kazoe_yakuman {
+            Limit::KazoeYakuman
+        } else if self.han >= 11 {
+            Limit::Sanbaiman
+        } else if self.han >= 8 {
+            Limit::Baiman
+        } else if self.han >= 6 {
+            Limit::Haneman
+        } else if self.han == 5 {
+            Limit::Mangan
+        } else {
+            let base_points = self.fu as u32 * (2u32.pow(2u32 + self.han as u32));
+            // kiriage: 4 han 30 fu and 3 han 60 fu (1920 base points) are rounded up
+            if base_points >= 2000 || (self.kiriage_mangan && base_points == 1920) {
+                Limit::Mangan
+            } else {
+                Limit::None
+            }
+        }
+    }
+
     /// Returns the base points of this Score. See http://arcturus.su/wiki/Japanese_mahjong_scoring_rules#Scoring_procedure
     fn base_points(&self) -> u32 {
-        if self.han == 5 || (self.han == 3 && self.fu >= 70) || (self.han == 4 && self.fu >= 40) {
-            // mangan
-            return 2000;
-        } else if self.han >= 6 && self.han <= 7 {
-            // haneman
-            return 3000;
-        } else if self.han >= 8 && self.han <= 10 {
-            // baiman
-            return 4000;
-        } else if self.han >= 11 && self.han <= 12 {
-            // sanbaiman
-            return 6000;
-        } else if self.han >= 13 {
-            // yakuman
-            // TODO double yakuman?
-            return 8000;
+        match self.limit() {
+            Limit::None => self.fu as u32 * (2u32.pow(2u32 + self.han as u32)),
+            Limit::Mangan => 2000,
+            Limit::Haneman => 3000,
+            Limit::Baiman => 4000,
+            Limit::Sanbaiman => 6000,
+            Limit::KazoeYakuman => 8000,
+            Limit::Yakuman => 8000 * self.yakuman as u32,
         }
-
-        self.fu as u32 * (2u32.pow(2u32 + self.han as u32))
     }
 
     /// Returns total points that will be distributed from this Score
     pub fn total_points(&self) -> u32 {
-        let base_points = self.base_points();
-
-        if self.han == 5 || (self.han == 3 && self.fu >= 70) || (self.han == 4 && self.fu >= 40) {
-            // mangan
-            if self.oya {
-                12000
-            } else {
-                8000
-            }
-        } else if self.han >= 6 && self.han <= 7 {
-            // haneman
-            if self.oya {
-                18000
-            } else {
-                12000
-            }
-        } else if self.han >= 8 && self.han <= 10 {
-            // baiman
-            if self.oya {
-                24000
-            } else {
-                16000
-            }
-        } else if self.han >= 11 && self.han <= 12 {
-            // sanbaiman
-            if self.oya {
-                36000
-            } else {
-                24000
-            }
-        } else if self.han >= 13 {
-            // yakuman
-            if self.oya {
-                48000
-            } else {
-                32000
-            }
-        } else if self.oya {
+        if self.oya {
             if self.tsumo {
-                ((((base_points * 2) as f32 / 100f32).ceil() * 100f32) * 3f32) as u32
+                self.points_from_ko() * 3
             } else {
-                (((base_points * 6) as f32 / 100f32).ceil() * 100f32) as u32
+                self.points_from_ko()
             }
         } else if self.tsumo {
-            ((((base_points as f32 / 100f32).ceil() * 100f32) * 2f32)
-                + (((base_points * 2) as f32 / 100f32).ceil() * 100f32)) as u32
+            self.points_from_oya() + self.points_from_ko() * 2
+        } else {
+            self.points_from_ko()
+        }
+    }
+
+    /// How the payment is usually written: "7700" for ron, "2000-3900" (ko-oya) for tsumo, "3900 all" for oya tsumo
+    pub fn payment_string(&self) -> String {
+        if !self.tsumo {
+            self.total_points().to_string()
+        } else if self.oya {
+            format!("{} all", self.points_from_ko())
         } else {
-            (((base_points * 4) as f32 / 100f32).ceil() * 100f32) as u32
+            format!("{}-{}", self.points_from_ko(), self.points_from_oya())
+        }
+    }
+
+    /// What every player pays to the winner (player 0), indexed 1 = shimocha, 2 = toimen, 3 = kamicha.
+    /// Players that can't be determined (unknown dealer or ron_from) pay 0.
+    pub fn payments(&self, dealer: Option<u8>, ron_from: Option<u8>) -> [u32; 4] {
+        let mut payments = [0; 4];
+
+        if self.tsumo {
+            for (player, payment) in payments.iter_mut().enumerate().skip(1) {
+                if self.oya {
+                    *payment = self.points_from_ko();
+                } else if let Some(dealer) = dealer {
+                    *payment = if dealer as usize == player {
+                        self.points_from_oya()
+                    } else {
+                        self.points_from_ko()
+                    };
+                }
+            }
+        } else if let Some(player) = ron_from {
+            if (1..=3).contains(&player) {
+                payments[player as usize] = self.total_points();
+            }
         }
+
+        payments
+    }
+
+    /// Point changes of all players (0 = me, 1 = shimocha, 2 = toimen, 3 = kamicha) when this Score wins.
+    /// Every honba adds 300 points (100 from each player on tsumo) and the winner takes the riichi sticks from the pot.
+    /// The loser is only used for ron.
+    pub fn point_deltas(
+        &self,
+        winner: u8,
+        dealer: u8,
+        loser: Option<u8>,
+        honba: u8,
+        riichi_sticks: u8,
+    ) -> [i32; 4] {
+        let mut deltas = [0; 4];
+
+        if self.tsumo {
+            for player in 0..4u8 {
+                if player == winner {
+                    continue;
+                }
+
+                let payment = if !self.oya && player == dealer {
+                    self.points_from_oya()
+                } else {
+                    self.points_from_ko()
+                } as i32
+                    + honba as i32 * 100;
+
+                deltas[player as usize] -= payment;
+                deltas[winner as usize] += payment;
+            }
+        } else if let Some(loser) = loser {
+            let payment = self.total_points() as i32 + honba as i32 * 300;
+
+            deltas[loser as usize] -= payment;
+            deltas[winner as usize] += payment;
+        }
+
+        deltas[winner as usize] += riichi_sticks as i32 * 1000;
+
+        deltas
+    }
+
+    /// Is this a score of the dealer?
+    pub fn is_oya(&self) -> bool {
+        self.oya
+    }
+
+    /// Is this a score of a tsumo win?
+    pub fn is_tsumo(&self) -> bool {
+        self.tsumo
     }
 
     /// How many points will oya pay from this Score?
@@ -424,6 +559,112 @@ mod tests {
         assert_eq!(scores[1].fu, 0);
     }
 
+    #[test]
+    fn score_2_130_is_mangan() {
+        let score = Score::new(2, 130, false, false);
+        assert_eq!(score.limit(), Limit::Mangan);
+        assert_eq!(score.total_points(), 8000);
+    }
+
+    #[test]
+    fn payment_strings() {
+        assert_eq!(Score::new(4, 30, false, false).payment_string(), "7700");
+        assert_eq!(Score::new(4, 30, false, true).payment_string(), "2000-3900");
+        assert_eq!(Score::new(4, 30, true, true).payment_string(), "3900 all");
+        assert_eq!(Score::new(6, 0, true, true).payment_string(), "6000 all");
+    }
+
+    #[test]
+    fn kazoe_yakuman() {
+        let score = Score::new(13, 40, false, false);
+        assert_eq!(score.limit(), Limit::KazoeYakuman);
+        assert_eq!(score.total_points(), 32000);
+    }
+
+    #[test]
+    fn kazoe_capped_at_sanbaiman() {
+        let mut score = Score::new(14, 40, false, false);
+        score.set_rules(&Rules {
+            kazoe_yakuman: false,
+            ..Rules::default()
+        });
+        assert_eq!(score.limit(), Limit::Sanbaiman);
+        assert_eq!(score.total_points(), 24000);
+    }
+
+    #[test]
+    fn kiriage_mangan() {
+        let mut score = Score::new(4, 30, false, false);
+        assert_eq!(score.total_points(), 7700);
+
+        score.set_rules(&Rules::wrc());
+        assert_eq!(score.limit(), Limit::Mangan);
+        assert_eq!(score.total_points(), 8000);
+
+        let mut score = Score::new(3, 60, true, true);
+        score.set_rules(&Rules::mleague());
+        assert_eq!(score.payment_string(), "4000 all");
+    }
+
+    #[test]
+    fn double_yakuman() {
+        assert_eq!(Score::new_yakuman(2, false, false).total_points(), 64000);
+        assert_eq!(Score::new_yakuman(2, true, false).total_points(), 96000);
+        assert_eq!(
+            Score::new_yakuman(3, false, true).payment_string(),
+            "24000-48000"
+        );
+    }
+
+    #[test]
+    fn from_points_double_yakuman() {
+        let scores = Score::from_points(64000, false, false, 110).unwrap();
+        assert_eq!(scores.len(), 1);
+        assert_eq!(scores[0].yakuman, 2);
+
+        let scores = Score::from_points(144000, true, true, 110).unwrap();
+        assert_eq!(scores[0].yakuman, 3);
+
+        assert!(Score::from_points(200000, false, false, 110).is_none());
+    }
+
+    #[test]
+    fn point_deltas_tsumo_with_honba() {
+        let score = Score::new(4, 30, false, true);
+        assert_eq!(
+            score.point_deltas(0, 2, None, 2, 1),
+            [9500, -2200, -4100, -2200]
+        );
+
+        let score = Score::new(4, 30, true, true);
+        assert_eq!(
+            score.point_deltas(3, 3, None, 1, 0),
+            [-4000, -4000, -4000, 12000]
+        );
+    }
+
+    #[test]
+    fn point_deltas_ron_with_honba() {
+        let score = Score::new(4, 30, false, false);
+        assert_eq!(
+            score.point_deltas(1, 0, Some(2), 1, 2),
+            [0, 10000, -8000, 0]
+        );
+    }
+
+    #[test]
+    fn payments_ko_tsumo() {
+        let score = Score::new(4, 30, false, true);
+        assert_eq!(score.payments(Some(2), None), [0, 2000, 3900, 2000]);
+        assert_eq!(score.payments(None, None), [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn payments_ron() {
+        let score = Score::new(4, 30, false, false);
+        assert_eq!(score.payments(Some(2), Some(3)), [0, 0, 0, 7700]);
+    }
+
     #[test]
     fn create_score_1_22() {
         let score = Score::new(1, 22, false, true);
@@ -0,0 +1,450 @@
+use crate::riichi::rules::{MultipleRon, Rules};
+use crate::riichi::scores::Score;
+use crate::riichi::tile::Tile;
+use crate::riichi::yaku::Yaku;
+
+/// Why a hand ended in a draw
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DrawReason {
+    /// The wall ran out (ryuukyoku)
+    Exhaustive,
+    /// Nine different terminals and honors in the first uninterrupted draw
+    KyuushuKyuuhai,
+    /// The same wind discarded by all four players on the first turn
+    SuufonRenda,
+    /// All four players in riichi
+    SuuchaRiichi,
+    /// Four kans by more than one player
+    Suukaikan,
+    /// Three players ron the same tile
+    Sanchahou,
+}
+
+/// A player winning a hand.
+/// Players are numbered in turn order, so player + 1 is the shimocha of player.
+#[derive(Debug, Clone)]
+pub struct Win {
+    /// Who won
+    pub player: u8,
+    /// Score of the winning hand
+    pub score: Score,
+    /// The player liable for a yakuman (sekinin barai / pao), if any
+    pub liable: Option<Liability>,
+}
+
+/// A player liable (pao) for one yakuman of a winning hand
+#[derive(Debug, Clone)]
+pub struct Liability {
+    /// Who is liable
+    pub player: u8,
+    /// The yakuman they are liable for
+    pub yaku: Yaku,
+    /// How many yakuman the yaku is worth, 2 for a double yakuman
+    pub yakuman: u8,
+}
+
+impl Win {
+    /// A win without a liable player
+    pub fn new(player: u8, score: Score) -> Win {
+        Win {
+            player,
+            score,
+            liable: None,
+        }
+    }
+
+    /// The score of the yakuman the liable player pays for, and the score of the rest of the hand (if any)
+    fn liable_scores(&self, liability: &Liability) -> (Score, Option<Score>) {
+        let mut liable = self.score.clone();
+        liable.yakuman = liability.yakuman.min(self.score.yakuman);
+
+        let rest = if self.score.yakuman > liable.yakuman {
+            let mut rest = self.score.clone();
+            rest.yakuman = self.score.yakuman - liable.yakuman;
+            Some(rest)
+        } else {
+            None
+        };
+
+        (liable, rest)
+    }
+}
+
+/// Point changes after a win
+#[derive(Debug)]
+pub struct Settlement {
+    /// Players that were paid, in turn order from the discarder
+    pub winners: Vec<u8>,
+    /// Point changes of all players
+    pub deltas: [i32; 4],
+    /// The hand ended in an abortive draw instead (sanchahou). Nobody is paid.
+    pub abortive_draw: Option<DrawReason>,
+}
+
+impl Settlement {
+    /// Settles a tsumo win. Honba and riichi sticks go to the winner.
+    /// A liable player pays the whole yakuman they are liable for, honba included,
+    /// the rest of the hand is paid as a normal tsumo.
+    pub fn tsumo(win: &Win, dealer: u8, honba: u8, riichi_sticks: u8) -> Settlement {
+        let deltas = match &win.liable {
+            None => win
+                .score
+                .point_deltas(win.player, dealer, None, honba, riichi_sticks),
+            Some(liability) => {
+                let (liable, rest) = win.liable_scores(liability);
+                let mut deltas = match rest {
+                    Some(rest) => rest.point_deltas(win.player, dealer, None, 0, 0),
+                    None => [0; 4],
+                };
+                let payment = liable.total_points() as i32 + honba as i32 * 300;
+
+                deltas[liability.player as usize] -= payment;
+                deltas[win.player as usize] += payment + riichi_sticks as i32 * 1000;
+
+                deltas
+            }
+        };
+
+        Settlement {
+            winners: vec![win.player],
+            deltas,
+            abortive_draw: None,
+        }
+    }
+
+    /// Settles one or more rons on the same discard, according to the multiple ron rules.
+    /// Riichi sticks go only to the first winner in turn order from the discarder,
+    /// honba too unless the rules pay them to every winner.
+    /// A liable player who is not the discarder pays half of the yakuman they are liable for,
+    /// the discarder pays the rest of the hand.
+    pub fn ron(
+        discarder: u8,
+        wins: &[Win],
+        dealer: u8,
+        honba: u8,
+        riichi_sticks: u8,
+        rules: &Rules,
+    ) -> Settlement {
+        let mut wins: Vec<&Win> = wins.iter().filter(|w| w.player != discarder).collect();
+        wins.sort_by_key(|w| (w.player + 4 - discarder) % 4);
+
+        let paid = match rules.multiple_ron {
+            MultipleRon::Atamahane => 1,
+            MultipleRon::DoubleRon => {
+                if wins.len() >= 3 {
+                    if rules.abortive_draws.sanchahou {
+                        return Settlement {
+                            winners: vec![],
+                            deltas: [0; 4],
+                            abortive_draw: Some(DrawReason::Sanchahou),
+                        };
+                    }
+
+                    1
+                } else {
+                    2
+                }
+            }
+            MultipleRon::TripleRon => 3,
+        };
+        wins.truncate(paid);
+
+        let mut deltas = [0; 4];
+        for (i, win) in wins.iter().enumerate() {
+            // only the first winner gets riichi sticks
+            let win_honba = if i == 0 || rules.multiple_ron_honba {
+                honba
+            } else {
+                0
+            };
+            let win_sticks = if i == 0 { riichi_sticks } else { 0 };
+
+            match &win.liable {
+                Some(liability) if liability.player != discarder => {
+                    let (liable, rest) = win.liable_scores(liability);
+                    let half = liable.total_points() as i32 / 2;
+                    let rest = match rest {
+                        Some(rest) => rest.total_points() as i32,
+                        None => 0,
+                    };
+
+                    deltas[liability.player as usize] -= half;
+                    deltas[discarder as usize] -= half + rest + win_honba as i32 * 300;
+                    deltas[win.player as usize] +=
+                        half * 2 + rest + win_honba as i32 * 300 + win_sticks as i32 * 1000;
+                }
+                _ => {
+                    let win_deltas = win.score.point_deltas(
+                        win.player,
+                        dealer,
+                        Some(discarder),
+                        win_honba,
+                        win_sticks,
+                    );
+                    for (delta, win_delta) in deltas.iter_mut().zip(win_deltas.iter()) {
+                        *delta += win_delta;
+                    }
+                }
+            }
+        }
+
+        Settlement {
+            winners: wins.iter().map(|w| w.player).collect(),
+            deltas,
+            abortive_draw: None,
+        }
+    }
+}
+
+/// Point changes after an exhaustive draw (ryuukyoku)
+#[derive(Debug)]
+pub struct DrawSettlement {
+    /// Point changes of all players
+    pub deltas: [i32; 4],
+    /// Players paid for nagashi mangan
+    pub nagashi_mangan: Vec<u8>,
+    /// Does the dealer stay on? (the dealer was tenpai)
+    pub dealer_stays: bool,
+    /// Honba count for the next hand
+    pub honba: u8,
+}
+
+impl DrawSettlement {
+    /// Settles an exhaustive draw. Noten players split 3000 points to the tenpai players.
+    /// If nagashi mangan is allowed and someone has it, it is paid as a mangan tsumo instead of the noten payments.
+    /// Riichi sticks stay in the pot.
+    pub fn exhaustive_draw(
+        tenpai: [bool; 4],
+        nagashi: [bool; 4],
+        dealer: u8,
+        honba: u8,
+        rules: &Rules,
+    ) -> DrawSettlement {
+        let mut deltas = [0; 4];
+        let mut nagashi_mangan = vec![];
+
+        if rules.nagashi_mangan && nagashi.iter().any(|n| *n) {
+            for (player, _) in nagashi.iter().enumerate().filter(|(_, n)| **n) {
+                let player = player as u8;
+                let score = Score::new(5, 0, player == dealer, true);
+                let nagashi_deltas = score.point_deltas(player, dealer, None, 0, 0);
+                for (delta, nagashi_delta) in deltas.iter_mut().zip(nagashi_deltas.iter()) {
+                    *delta += nagashi_delta;
+                }
+
+                nagashi_mangan.push(player);
+            }
+        } else {
+            let tenpai_count = tenpai.iter().filter(|t| **t).count() as i32;
+            if tenpai_count > 0 && tenpai_count < 4 {
+                for (delta, is_tenpai) in deltas.iter_mut().zip(tenpai.iter()) {
+                    *delta = if *is_tenpai {
+                        3000 / tenpai_count
+                    } else {
+                        -3000 / (4 - tenpai_count)
+                    };
+                }
+            }
+        }
+
+        DrawSettlement {
+            deltas,
+            nagashi_mangan,
+            dealer_stays: tenpai[dealer as usize],
+            honba: honba + 1,
+        }
+    }
+}
+
+/// Does this player have nagashi mangan? All discards have to be terminals or honors and none of them were called.
+pub fn is_nagashi_mangan(discards: &[Tile], discards_called: bool) -> bool {
+    !discards_called && !discards.is_empty() && discards.iter().all(|t| t.is_terminal_or_honor())
+}
+
+/// Can a player be liable (pao) for this yaku?
+/// Daisangen and daisuushii always, suukantsu only if the rules say so.
+pub fn is_pao_yaku(yaku: &Yaku, rules: &Rules) -> bool {
+    match yaku {
+        Yaku::Daisangen | Yaku::Daisuushii => true,
+        Yaku::Suukantsu => rules.suukantsu_pao,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn double_ron() {
+        let wins = vec![
+            Win::new(3, Score::new(3, 30, false, false)),
+            Win::new(2, Score::new(1, 30, false, false)),
+        ];
+        let settlement = Settlement::ron(1, &wins, 0, 1, 1, &Rules::tenhou());
+
+        // 2 is first from 1 in turn order, so 2 gets the riichi stick, both get honba
+        assert_eq!(settlement.winners, vec![2, 3]);
+        assert_eq!(settlement.deltas, [0, -5500, 2300, 4200]);
+
+        // only 2 gets honba
+        let rules = Rules {
+            multiple_ron_honba: false,
+            ..Rules::tenhou()
+        };
+        let settlement = Settlement::ron(1, &wins, 0, 1, 1, &rules);
+        assert_eq!(settlement.deltas, [0, -5200, 2300, 3900]);
+    }
+
+    #[test]
+    fn atamahane() {
+        let wins = vec![
+            Win::new(3, Score::new(3, 30, false, false)),
+            Win::new(2, Score::new(1, 30, false, false)),
+        ];
+        let settlement = Settlement::ron(1, &wins, 0, 0, 0, &Rules::wrc());
+
+        assert_eq!(settlement.winners, vec![2]);
+        assert_eq!(settlement.deltas, [0, -1000, 1000, 0]);
+    }
+
+    #[test]
+    fn triple_ron() {
+        let wins = vec![
+            Win::new(1, Score::new(1, 30, false, false)),
+            Win::new(2, Score::new(1, 30, false, false)),
+            Win::new(3, Score::new(1, 30, false, false)),
+        ];
+
+        let settlement = Settlement::ron(0, &wins, 0, 0, 0, &Rules::tenhou());
+        assert_eq!(settlement.abortive_draw, Some(DrawReason::Sanchahou));
+        assert_eq!(settlement.deltas, [0; 4]);
+
+        let settlement = Settlement::ron(0, &wins, 0, 0, 0, &Rules::ema());
+        assert_eq!(settlement.winners, vec![1, 2, 3]);
+        assert_eq!(settlement.deltas, [-3000, 1000, 1000, 1000]);
+    }
+
+    fn daisangen_pao(player: u8) -> Option<Liability> {
+        Some(Liability {
+            player,
+            yaku: Yaku::Daisangen,
+            yakuman: 1,
+        })
+    }
+
+    #[test]
+    fn pao_tsumo() {
+        let win = Win {
+            player: 0,
+            score: Score::new_yakuman(1, false, true),
+            liable: daisangen_pao(2),
+        };
+        let settlement = Settlement::tsumo(&win, 1, 1, 0);
+
+        assert_eq!(settlement.deltas, [32300, 0, -32300, 0]);
+
+        // daisangen + tsuuiisou, tsuuiisou is paid by everyone
+        let win = Win {
+            player: 0,
+            score: Score::new_yakuman(2, false, true),
+            liable: daisangen_pao(2),
+        };
+        let settlement = Settlement::tsumo(&win, 1, 0, 0);
+
+        assert_eq!(settlement.deltas, [64000, -16000, -40000, -8000]);
+    }
+
+    #[test]
+    fn pao_ron() {
+        let win = Win {
+            player: 0,
+            score: Score::new_yakuman(1, false, false),
+            liable: daisangen_pao(2),
+        };
+        let settlement = Settlement::ron(3, &[win], 1, 1, 0, &Rules::tenhou());
+
+        assert_eq!(settlement.deltas, [32300, 0, -16000, -16300]);
+
+        // daisangen + tsuuiisou, the discarder pays all of tsuuiisou
+        let win = Win {
+            player: 0,
+            score: Score::new_yakuman(2, false, false),
+            liable: daisangen_pao(2),
+        };
+        let settlement = Settlement::ron(3, &[win], 1, 0, 0, &Rules::tenhou());
+
+        assert_eq!(settlement.deltas, [64000, 0, -16000, -48000]);
+    }
+
+    #[test]
+    fn noten_payments() {
+        let rules = Rules::tenhou();
+
+        let draw =
+            DrawSettlement::exhaustive_draw([true, false, false, false], [false; 4], 0, 0, &rules);
+        assert_eq!(draw.deltas, [3000, -1000, -1000, -1000]);
+        assert!(draw.dealer_stays);
+        assert_eq!(draw.honba, 1);
+
+        let draw =
+            DrawSettlement::exhaustive_draw([false, true, true, false], [false; 4], 0, 2, &rules);
+        assert_eq!(draw.deltas, [-1500, 1500, 1500, -1500]);
+        assert!(!draw.dealer_stays);
+        assert_eq!(draw.honba, 3);
+
+        let draw =
+            DrawSettlement::exhaustive_draw([true, true, false, true], [false; 4], 0, 0, &rules);
+        assert_eq!(draw.deltas, [1000, 1000, -3000, 1000]);
+
+        let draw = DrawSettlement::exhaustive_draw([true; 4], [false; 4], 0, 0, &rules);
+        assert_eq!(draw.deltas, [0; 4]);
+    }
+
+    #[test]
+    fn nagashi_mangan() {
+        let nagashi = [false, false, true, false];
+        let draw = DrawSettlement::exhaustive_draw(
+            [true, false, false, false],
+            nagashi,
+            0,
+            0,
+            &Rules::tenhou(),
+        );
+        assert_eq!(draw.deltas, [-4000, -2000, 8000, -2000]);
+        assert_eq!(draw.nagashi_mangan, vec![2]);
+        assert!(draw.dealer_stays);
+
+        let draw = DrawSettlement::exhaustive_draw(
+            [true, false, false, false],
+            nagashi,
+            0,
+            0,
+            &Rules::wrc(),
+        );
+        assert_eq!(draw.deltas, [3000, -1000, -1000, -1000]);
+        assert!(draw.nagashi_mangan.is_empty());
+    }
+
+    #[test]
+    fn detect_nagashi_mangan() {
+        let discards: Vec<Tile> = ["1m", "9p", "1z", "7z"]
+            .iter()
+            .map(|t| Tile::from_text(t).unwrap())
+            .collect();
+        assert!(is_nagashi_mangan(&discards, false));
+        assert!(!is_nagashi_mangan(&discards, true));
+
+        let discards = vec![
+            Tile::from_text("1m").unwrap(),
+            Tile::from_text("2m").unwrap(),
+        ];
+        assert!(!is_nagashi_mangan(&discards, false));
+    }
+
+    #[test]
+    fn pao_yaku() {
+        assert!(is_pao_yaku(&Yaku::Daisangen, &Rules::tenhou()));
+        assert!(!is_pao_yaku(&Yaku::Suukantsu, &Rules::tenhou()));
+    }
+}
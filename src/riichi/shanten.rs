@@ -47,26 +47,10 @@ impl ShantenFinder {
 
     /// Gets the hand's shanten to kokushi musou.
     fn kokushi_shanten(&self, array_34: &[u8; 34]) -> i8 {
-        let mut shanten: i8 = 13;
-        let mut pair_found = false;
-
-        for (i, count) in array_34.iter().enumerate() {
-            if [1, 9, 10, 18, 19, 27].contains(&(i + 1)) || (i + 1) >= 28 {
-                // we only need 1 of each here + pair
-                if *count > 1 {
-                    if !pair_found {
-                        shanten -= 2; // I'm keeping two of these as a pair
-                        pair_found = true;
-                    } else {
-                        shanten -= 1;
-                    }
-                } else if *count > 0 {
-                    shanten -= 1;
-                }
-            }
-        }
+        let (kinds, pair_found) = Hand::kokushi_tiles(array_34);
 
-        shanten
+        // one of each kind, one more if I'm keeping two of them as a pair
+        13 - kinds as i8 - pair_found as i8
     }
 
     /// Gets the hand's shanten to chiitoitsu
@@ -27,7 +27,7 @@ pub enum ShapeType {
 }
 
 /// A complete shape
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum CompleteShape {
     /// A closed shape
     Closed(ClosedShape),
@@ -36,7 +36,7 @@ pub enum CompleteShape {
 }
 
 /// A closed shape
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum ClosedShape {
     /// Meld
     Shuntsu([Tile; 3]),
@@ -51,7 +51,7 @@ pub enum ClosedShape {
 }
 
 /// An open shape
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum OpenShape {
     /// Chi (sequence of 3 tiles)
     Chi([Tile; 3]),
@@ -62,7 +62,7 @@ pub enum OpenShape {
 }
 
 /// A kan that is open
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum OpenKan {
     /// kan opened by a call
     Daiminkan([Tile; 4]),
@@ -1,18 +1,159 @@
 use crate::riichi::hand::Hand;
+use crate::riichi::placement::final_results;
 use crate::riichi::riichi_error::RiichiError;
-use crate::riichi::rules::Rules;
+use crate::riichi::rules::{Kuikae, RiichiKan, Rules};
 use crate::riichi::scores::Score;
-use crate::riichi::shapes::{ClosedShape, Shape};
-use crate::riichi::tile::Tile;
-use crate::riichi::yaku::{Yaku, YakuFinder};
+use crate::riichi::settlement::DrawReason;
+use crate::riichi::shape_finder::ShapeFinder;
+use crate::riichi::shapes::{ClosedShape, CompleteShape, OpenKan, OpenShape, Shape, ShapeType};
+use crate::riichi::tile::{Tile, TileType};
+use crate::riichi::yaku::{HandResult, YakuFinder};
 use serde_json::{Map, Value};
 
+/// Which kan was robbed by a chankan win
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RobbedKan {
+    /// A tile added to a pon (any hand can rob it)
+    Shouminkan,
+    /// A closed kan (only kokushi can rob it)
+    Ankan,
+}
+
+/// Something a player can do when asked
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    /// Discard this tile
+    Discard(Tile),
+    /// Declare riichi and discard this tile
+    Riichi(Tile),
+    /// Win with the drawn tile
+    Tsumo,
+    /// Win with another player's discard (or a robbed kan)
+    Ron,
+    /// Call chi with these two tiles from my hand
+    Chi(Tile, Tile),
+    /// Call pon with these two tiles from my hand
+    Pon(Tile, Tile),
+    /// Call an open kan on a discard
+    Daiminkan,
+    /// Declare a closed kan of this tile
+    Ankan(Tile),
+    /// Add this tile to my pon
+    Shouminkan(Tile),
+    /// Abort the hand with 9 different terminals and honors
+    KyuushuKyuuhai,
+    /// Don't call
+    Pass,
+}
+
+/// Why I can't ron
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Furiten {
+    /// One of my waits is in my discards
+    Permanent,
+    /// I passed on a winning tile since my last discard
+    Temporary,
+    /// I passed on a winning tile after declaring riichi, this lasts until the end of the hand
+    Riichi,
+}
+
+/// What a wait of my tenpai hand would win
+#[derive(Debug)]
+pub struct WaitResult {
+    /// The winning tile
+    pub tile: Tile,
+    /// Yaku and score by ron (from kamicha), if I can ron it
+    pub ron: Option<HandResult>,
+    /// Yaku and score by tsumo, if the hand has a yaku
+    pub tsumo: Option<HandResult>,
+}
+
+/// A hand in progress with absolute seats (0 is the first dealer), as a game or a replay knows it
+pub struct HandSnapshot<'a> {
+    /// Rules of the game
+    pub rules: Rules,
+    /// 1 = east, 2 = south, 3 = west, 4 = north
+    pub prevalent_wind: u8,
+    /// Seat of the dealer
+    pub dealer: u8,
+    /// Honba (repeat) sticks
+    pub honba: u8,
+    /// Riichi sticks on the table
+    pub riichi_sticks: u8,
+    /// Tiles left in the live wall
+    pub tiles_remaining: u8,
+    /// Points of all seats
+    pub points: [i32; 4],
+    /// Hands of all seats
+    pub hands: &'a [Hand; 4],
+    /// Discards of all seats
+    pub discards: &'a [Vec<Tile>; 4],
+    /// Who declared riichi
+    pub riichi: [bool; 4],
+    /// Who declared double riichi
+    pub double_riichi: [bool; 4],
+    /// Revealed dora indicators
+    pub dora_indicators: &'a [Tile],
+}
+
+impl HandSnapshot<'_> {
+    /// The table from a seat's point of view. Other seats become players 1-3 in turn order.
+    pub fn table_for(&self, seat: u8) -> Table {
+        let mut table = Table::from_map(&Map::new()).unwrap();
+        table.set_rules(self.rules);
+        table.set_my_hand(self.hands[seat as usize].clone());
+        table.set_prevalent_wind(self.prevalent_wind);
+        table.set_my_seat_wind((seat + 4 - self.dealer) % 4 + 1);
+        table.set_tiles_remaining(self.tiles_remaining);
+        table.set_tsumibo(self.honba);
+        table.set_riichi_sticks(self.riichi_sticks);
+
+        if self.double_riichi[seat as usize] {
+            table.set_my_double_riichi(true);
+        } else {
+            table.set_my_riichi(self.riichi[seat as usize]);
+        }
+
+        for indicator in self.dora_indicators.iter() {
+            table.add_dora_indicator(*indicator);
+        }
+
+        for player in 0..4 {
+            let other = ((seat + player) % 4) as usize;
+
+            table.set_points(player, self.points[other]);
+            for tile in self.discards[other].iter() {
+                table.add_tile_to_discards(player, *tile);
+                table.add_tile_to_visible_tiles(*tile);
+            }
+
+            match player {
+                0 => continue,
+                1 => table.set_p1_riichi(self.riichi[other]),
+                2 => table.set_p2_riichi(self.riichi[other]),
+                _ => table.set_p3_riichi(self.riichi[other]),
+            }
+
+            table.add_shapes_from_hand(player, &self.hands[other]);
+        }
+
+        table
+    }
+}
+
 /// Representation of the game state
+#[derive(Clone)]
 pub struct Table {
     my_hand: Option<Hand>,
     my_discards: Vec<Tile>,
     my_riichi: Option<bool>,
+    my_double_riichi: Option<bool>,
     my_tsumo: Option<bool>,
+    my_rinshan: Option<bool>,
+    my_chankan: Option<RobbedKan>,
+    my_temporary_furiten: Option<bool>,
+    my_riichi_furiten: Option<bool>,
+    my_forbidden_discards: Vec<Tile>,
     my_points: Option<i32>,
     // player to the right (shimocha)
     p1_discards: Vec<Tile>,
@@ -37,6 +178,8 @@ pub struct Table {
     p3_points: Option<i32>,
 
     riichi_declaring_player: Option<u8>,
+    /// Which player dealt into my ron (1 = shimocha, 2 = toimen, 3 = kamicha)
+    ron_from: Option<u8>,
 
     // 1 = east, 2 = south, 3 = west, 4 = north
     prevalent_wind: Option<u8>,
@@ -51,6 +194,7 @@ pub struct Table {
     tsumibo: Option<u8>,
 
     dora_indicators: Vec<Tile>,
+    ura_dora_indicators: Vec<Tile>,
     visible_tiles: [u8; 34], // in array_34 format
 
     rules: Option<Rules>,
@@ -63,7 +207,13 @@ impl Table {
             my_hand: None,
             my_discards: vec![],
             my_riichi: None,
+            my_double_riichi: None,
             my_tsumo: None,
+            my_rinshan: None,
+            my_chankan: None,
+            my_temporary_furiten: None,
+            my_riichi_furiten: None,
+            my_forbidden_discards: vec![],
             my_points: None,
             p1_discards: vec![],
             p1_safe_tiles: vec![],
@@ -84,6 +234,7 @@ impl Table {
             p3_tsumo: None,
             p3_points: None,
             riichi_declaring_player: None,
+            ron_from: None,
             prevalent_wind: None,
             my_seat_wind: None,
             my_initial_seat_wind: None,
@@ -93,6 +244,7 @@ impl Table {
             riichi_sticks_in_pot: None,
             tsumibo: None,
             dora_indicators: vec![],
+            ura_dora_indicators: vec![],
             visible_tiles: [0; 34],
             rules: None,
         };
@@ -109,10 +261,26 @@ impl Table {
                 if let Value::Bool(b) = value {
                     t.my_riichi = Some(*b);
                 }
+            } else if index.eq(&String::from("my_double_riichi")) {
+                if let Value::Bool(b) = value {
+                    t.set_my_double_riichi(*b);
+                }
             } else if index.eq(&String::from("my_tsumo")) {
                 if let Value::Bool(b) = value {
                     t.my_tsumo = Some(*b);
                 }
+            } else if index.eq(&String::from("my_rinshan")) {
+                if let Value::Bool(b) = value {
+                    t.my_rinshan = Some(*b);
+                }
+            } else if index.eq(&String::from("my_chankan")) {
+                if let Value::String(kan) = value {
+                    match kan.as_str() {
+                        "shouminkan" => t.my_chankan = Some(RobbedKan::Shouminkan),
+                        "ankan" => t.my_chankan = Some(RobbedKan::Ankan),
+                        _ => return Err(RiichiError::new(160, "Unknown robbed kan")),
+                    }
+                }
             } else if index.eq(&String::from("prevalent_wind")) {
                 match value {
                     Value::Number(v) => {
@@ -124,6 +292,10 @@ impl Table {
                     }
                     _ => (),
                 }
+            } else if index.eq(&String::from("rules")) {
+                if let Value::String(preset) = value {
+                    t.rules = Some(Rules::from_preset(preset)?);
+                }
             } else if index.eq(&String::from("my_seat_wind")) {
                 match value {
                     Value::Number(v) => {
@@ -231,6 +403,24 @@ impl Table {
         self.riichi_declaring_player
     }
 
+    /// Set the player I won from by ron
+    pub fn set_ron_from(&mut self, player: u8) {
+        self.ron_from = Some(player);
+    }
+
+    /// Which player did I win from by ron?
+    pub fn get_ron_from(&self) -> Option<u8> {
+        self.ron_from
+    }
+
+    /// Which player is the dealer? (0 = me, 1 = shimocha, 2 = toimen, 3 = kamicha)
+    pub fn get_dealer_player(&self) -> Option<u8> {
+        match self.my_seat_wind {
+            Some(seat) if (1..=4).contains(&seat) => Some((5 - seat) % 4),
+            _ => None,
+        }
+    }
+
     /// Did I riichi?
     pub fn did_i_riichi(&self) -> bool {
         match &self.my_riichi {
@@ -239,6 +429,40 @@ impl Table {
         }
     }
 
+    /// Set player 0 (me) double riichi - riichi declared on the first uninterrupted discard.
+    /// Double riichi is also a riichi.
+    pub fn set_my_double_riichi(&mut self, value: bool) {
+        self.my_double_riichi = Some(value);
+        if value {
+            self.my_riichi = Some(true);
+        }
+    }
+
+    /// Did I declare double riichi?
+    pub fn did_i_double_riichi(&self) -> bool {
+        self.my_double_riichi.unwrap_or(false)
+    }
+
+    /// Set whether I won on the replacement draw after a kan
+    pub fn set_my_rinshan(&mut self, value: bool) {
+        self.my_rinshan = Some(value);
+    }
+
+    /// Did I win on the replacement draw after a kan?
+    pub fn did_i_rinshan(&self) -> bool {
+        self.my_rinshan.unwrap_or(false)
+    }
+
+    /// Set the kan I robbed to win, or None
+    pub fn set_my_chankan(&mut self, value: Option<RobbedKan>) {
+        self.my_chankan = value;
+    }
+
+    /// Which kan did I rob to win?
+    pub fn get_my_chankan(&self) -> Option<RobbedKan> {
+        self.my_chankan
+    }
+
     /// Set my hand
     pub fn set_my_hand(&mut self, hand: Hand) {
         self.my_hand = Some(hand);
@@ -338,35 +562,57 @@ impl Table {
         }
     }
 
+    /// Return points of a player
+    pub fn get_points(&self, player: u8) -> Option<i32> {
+        match player {
+            0 => self.my_points,
+            1 => self.p1_points,
+            2 => self.p2_points,
+            3 => self.p3_points,
+            _ => panic!("Wrong player!"),
+        }
+    }
+
+    /// Add point changes to every player's points (0 = me, 1 = shimocha, 2 = toimen, 3 = kamicha)
+    pub fn apply_point_deltas(&mut self, deltas: &[i32; 4]) {
+        for (player, delta) in deltas.iter().enumerate() {
+            let points = self.get_points(player as u8).unwrap_or(25000);
+            self.set_points(player as u8, points + delta);
+        }
+    }
+
+    /// Point changes of all players if I win with this score, with honba and riichi sticks in the pot.
+    /// None if we don't know who the dealer is.
+    pub fn point_deltas(&self, score: &Score) -> Option<[i32; 4]> {
+        let dealer = self.get_dealer_player()?;
+
+        Some(score.point_deltas(
+            0,
+            dealer,
+            self.get_ron_from(),
+            self.get_tsumibo(),
+            self.get_riichi_sticks(),
+        ))
+    }
+
     /// Return my points
     pub fn get_my_points(&self) -> Option<i32> {
         self.my_points
     }
 
-    /// Return my placing based on score
+    /// Return my placing based on score. Ties are broken according to the rules.
     pub fn get_placing(&self) -> u8 {
-        // TODO: account for different tie-breaking rules
-        let initial_seat_winds = match self.my_initial_seat_wind {
-            Some(1) => vec![1, 2, 3, 4],
-            Some(2) => vec![2, 3, 4, 1],
-            Some(3) => vec![3, 4, 1, 2],
-            _ => vec![4, 1, 2, 3],
-        };
-        let points = vec![
-            self.my_points.unwrap_or(25000),
-            self.p1_points.unwrap_or(25000),
-            self.p2_points.unwrap_or(25000),
-            self.p3_points.unwrap_or(25000),
-        ];
-        let mut all_scores: Vec<_> = (points.iter()).zip(initial_seat_winds.iter()).collect();
-        // sort from lowest to hightest
-        all_scores.sort_by(|a, b| b.0.cmp(a.0).then(a.1.cmp(b.1)));
+        // points in seat order from the first dealer
+        let my_initial_seat_wind = self.my_initial_seat_wind.unwrap_or(4);
+        let mut points = [0; 4];
+        for player in 0..4 {
+            let initial_seat_wind = (my_initial_seat_wind + player - 1) % 4;
+            points[initial_seat_wind as usize] = self.get_points(player).unwrap_or(25000);
+        }
 
-        all_scores
-            .iter()
-            .position(|score| *score.1 == self.my_initial_seat_wind.unwrap_or(4))
-            .unwrap_or(4) as u8
-            + 1
+        final_results(&points, &self.get_rules().unwrap_or_default())
+            [(my_initial_seat_wind - 1) as usize]
+            .place
     }
 
     /// Set the dora indicators list
@@ -385,6 +631,21 @@ impl Table {
         &self.dora_indicators
     }
 
+    /// Set the ura dora indicators list
+    pub fn set_ura_dora_indicators(&mut self, indicators: Vec<Tile>) {
+        self.ura_dora_indicators = indicators;
+    }
+
+    /// Add an ura dora indicator tile
+    pub fn add_ura_dora_indicator(&mut self, indicator: Tile) {
+        self.ura_dora_indicators.push(indicator);
+    }
+
+    /// Return a vector of ura dora indicators (revealed after a riichi win)
+    pub fn get_ura_dora_indicators(&self) -> &Vec<Tile> {
+        &self.ura_dora_indicators
+    }
+
     /// Add a Tile to the visible list
     pub fn add_tile_to_visible_tiles(&mut self, tile: Tile) {
         self.visible_tiles[(tile.get_id() - 1) as usize] += 1;
@@ -455,7 +716,12 @@ impl Table {
     /// Add a Tile to a player's discard list (not pile, since also called tiles are in here)
     pub fn add_tile_to_discards(&mut self, player: u8, tile: Tile) {
         match player {
-            0 => self.my_discards.push(tile),
+            0 => {
+                // my discard ends temporary furiten and kuikae
+                self.my_temporary_furiten = Some(false);
+                self.my_forbidden_discards = vec![];
+                self.my_discards.push(tile);
+            }
             1 => self.p1_discards.push(tile),
             2 => self.p2_discards.push(tile),
             3 => self.p3_discards.push(tile),
@@ -463,6 +729,204 @@ impl Table {
         }
     }
 
+    /// Get a player's discards
+    pub fn get_discards(&self, player: u8) -> &Vec<Tile> {
+        match player {
+            0 => &self.my_discards,
+            1 => &self.p1_discards,
+            2 => &self.p2_discards,
+            3 => &self.p3_discards,
+            _ => panic!("Invalid player"),
+        }
+    }
+
+    /// Add a called shape (or a closed kan) to another player's open tiles. My calls are in my hand.
+    pub fn add_open_shape(&mut self, player: u8, shape: Shape) {
+        match player {
+            1 => self.p1_open_tiles.push(shape),
+            2 => self.p2_open_tiles.push(shape),
+            3 => self.p3_open_tiles.push(shape),
+            _ => panic!("Invalid player"),
+        }
+    }
+
+    /// Add a tile to a player's pon, making it a shouminkan. My pon is in my hand.
+    pub fn add_shouminkan(&mut self, player: u8, tile: &Tile) -> Result<(), RiichiError> {
+        let no_pon = || RiichiError::new(194, "There is no pon to add the tile to");
+        let open_tiles = match player {
+            0 => {
+                let hand = match self.my_hand.as_mut() {
+                    None => return Err(no_pon()),
+                    Some(hand) => hand,
+                };
+                let has_pon = hand.get_shapes().iter().any(|shape| match shape {
+                    CompleteShape::Open(OpenShape::Pon(tiles)) => tiles[0] == *tile,
+                    _ => false,
+                });
+                if !has_pon || hand.get_34_array(true)[tile.get_id_minus_1() as usize] == 0 {
+                    return Err(no_pon());
+                }
+
+                hand.add_shouminkan(tile);
+                return Ok(());
+            }
+            1 => &mut self.p1_open_tiles,
+            2 => &mut self.p2_open_tiles,
+            3 => &mut self.p3_open_tiles,
+            _ => panic!("Invalid player"),
+        };
+
+        for shape in open_tiles.iter_mut() {
+            if let ShapeType::Complete(CompleteShape::Open(OpenShape::Pon(tiles))) =
+                shape.get_shape_type()
+            {
+                if tiles[0] == *tile {
+                    let kan = OpenKan::Shouminkan([tiles[0], tiles[1], tiles[2], *tile]);
+                    *shape = Shape::new(
+                        ShapeType::Complete(CompleteShape::Open(OpenShape::Kan(kan))),
+                        4,
+                        true,
+                    );
+                    return Ok(());
+                }
+            }
+        }
+
+        Err(no_pon())
+    }
+
+    /// Add all calls and closed kans of another player's hand as their open shapes. Their tiles become visible.
+    pub fn add_shapes_from_hand(&mut self, player: u8, hand: &Hand) {
+        for shape in hand.get_shapes().iter() {
+            let (tiles, is_open): (Vec<Tile>, bool) = match shape {
+                CompleteShape::Open(OpenShape::Chi(tiles))
+                | CompleteShape::Open(OpenShape::Pon(tiles)) => (tiles.to_vec(), true),
+                CompleteShape::Open(OpenShape::Kan(OpenKan::Daiminkan(tiles)))
+                | CompleteShape::Open(OpenShape::Kan(OpenKan::Shouminkan(tiles))) => {
+                    (tiles.to_vec(), true)
+                }
+                CompleteShape::Closed(ClosedShape::Kantsu(tiles)) => (tiles.to_vec(), false),
+                _ => continue,
+            };
+
+            for tile in tiles.iter() {
+                self.add_tile_to_visible_tiles(*tile);
+            }
+            self.add_open_shape(
+                player,
+                Shape::new(ShapeType::Complete(*shape), tiles.len() as u8, is_open),
+            );
+        }
+    }
+
+    /// How many kans did this player declare?
+    pub fn count_kans(&self, player: u8) -> u8 {
+        let open_tiles = match player {
+            0 => {
+                let hand = match &self.my_hand {
+                    None => return 0,
+                    Some(hand) => hand,
+                };
+                let open_kans = hand
+                    .get_open_shapes()
+                    .iter()
+                    .filter(|s| matches!(s, OpenShape::Kan(_)))
+                    .count() as u8;
+
+                return hand.get_closed_kans() + open_kans;
+            }
+            1 => &self.p1_open_tiles,
+            2 => &self.p2_open_tiles,
+            3 => &self.p3_open_tiles,
+            _ => panic!("Invalid player"),
+        };
+
+        open_tiles
+            .iter()
+            .filter(|s| {
+                matches!(
+                    s.get_shape_type(),
+                    ShapeType::Complete(CompleteShape::Open(OpenShape::Kan(_)))
+                        | ShapeType::Complete(CompleteShape::Closed(ClosedShape::Kantsu(_)))
+                )
+            })
+            .count() as u8
+    }
+
+    /// Was any tile called yet (kans included)?
+    pub fn any_calls(&self) -> bool {
+        let my_calls = match &self.my_hand {
+            None => false,
+            Some(hand) => !hand.is_closed() || hand.get_closed_kans() > 0,
+        };
+
+        my_calls
+            || !self.p1_open_tiles.is_empty()
+            || !self.p2_open_tiles.is_empty()
+            || !self.p3_open_tiles.is_empty()
+    }
+
+    /// Can I declare kyuushu kyuuhai? I need 9 different terminals and honors on my first uninterrupted draw.
+    pub fn can_declare_kyuushu_kyuuhai(&self) -> bool {
+        if !self
+            .get_rules()
+            .unwrap_or_default()
+            .abortive_draws
+            .kyuushu_kyuuhai
+        {
+            return false;
+        }
+
+        if !self.my_discards.is_empty() || self.any_calls() {
+            return false;
+        }
+
+        self.get_my_hand().count_terminal_and_honor_kinds() >= 9
+    }
+
+    /// Did the table state end the hand in an abortive draw? Only draws the rules allow are reported.
+    /// Kyuushu kyuuhai has to be declared (see can_declare_kyuushu_kyuuhai) and sanchahou is found when settling rons.
+    pub fn abortive_draw(&self) -> Option<DrawReason> {
+        let abortive_draws = self.get_rules().unwrap_or_default().abortive_draws;
+
+        if abortive_draws.suufon_renda && !self.any_calls() {
+            let discards = [
+                &self.my_discards,
+                &self.p1_discards,
+                &self.p2_discards,
+                &self.p3_discards,
+            ];
+
+            if discards.iter().all(|d| d.len() == 1) {
+                let first = discards[0][0];
+                if matches!(first.tile_type, TileType::Wind(_))
+                    && discards.iter().all(|d| d[0].eq(&first))
+                {
+                    return Some(DrawReason::SuufonRenda);
+                }
+            }
+        }
+
+        if abortive_draws.suucha_riichi
+            && self.did_i_riichi()
+            && self.get_p1_riichi()
+            && self.get_p2_riichi()
+            && self.get_p3_riichi()
+        {
+            return Some(DrawReason::SuuchaRiichi);
+        }
+
+        if abortive_draws.suukaikan {
+            let kans: Vec<u8> = (0..4).map(|player| self.count_kans(player)).collect();
+            let total: u8 = kans.iter().sum();
+            if total >= 4 && kans.iter().all(|k| *k < total) {
+                return Some(DrawReason::Suukaikan);
+            }
+        }
+
+        None
+    }
+
     /// Add a Tile to a player's safe tiles list
     pub fn add_tile_to_safe_tiles(&mut self, player: u8, tile: Tile) {
         match player {
@@ -473,8 +937,373 @@ impl Table {
         }
     }
 
+    /// Everything I can do right now. Without a tile to react to, it's my turn after a draw or a call.
+    /// With a tile discarded by a player, I can call it or ron it. If my_chankan is set, the tile is from a kan
+    /// and I can only ron it. Discarding (or passing) always comes first.
+    pub fn legal_actions(&self, discard: Option<(u8, &Tile)>) -> Vec<Action> {
+        match discard {
+            None => self.turn_actions(),
+            Some((player, tile)) => self.reaction_actions(player, tile),
+        }
+    }
+
+    fn turn_actions(&self) -> Vec<Action> {
+        let hand = self.get_my_hand();
+        let closed_tiles = distinct_tiles(&self.my_closed_tiles());
+        let mut actions = vec![];
+
+        if self.did_i_riichi() {
+            if let Some(drawn) = hand.get_drawn_tile() {
+                actions.push(Action::Discard(*drawn));
+            }
+        } else {
+            actions.extend(
+                closed_tiles
+                    .iter()
+                    .filter(|t| !self.my_forbidden_discards.iter().any(|f| f.eq(t)))
+                    .map(|t| Action::Discard(*t)),
+            );
+        }
+
+        // right after a call, I can only discard
+        if hand.get_drawn_tile().is_none() {
+            return actions;
+        }
+
+        if !self.did_i_riichi()
+            && hand.is_closed()
+            && self.my_points.unwrap_or(25000) >= 1000
+            && self.tiles_remaining.unwrap_or(70) >= 4
+        {
+            for tile in closed_tiles.iter() {
+                let mut after_discard = hand.clone();
+                after_discard.remove_tile(tile);
+                if after_discard.get_shanten() == 0 {
+                    actions.push(Action::Riichi(*tile));
+                }
+            }
+        }
+
+        if self.can_kan() {
+            if let Some(tiles) = self.can_ankan() {
+                actions.extend(tiles.into_iter().map(Action::Ankan));
+            }
+
+            if !self.did_i_riichi() {
+                for shape in hand.get_open_shapes().iter() {
+                    if let OpenShape::Pon(tiles) = shape {
+                        if let Some(tile) = closed_tiles
+                            .iter()
+                            .find(|t| t.get_id() == tiles[0].get_id())
+                        {
+                            actions.push(Action::Shouminkan(*tile));
+                        }
+                    }
+                }
+            }
+        }
+
+        if self.can_declare_kyuushu_kyuuhai() {
+            actions.push(Action::KyuushuKyuuhai);
+        }
+
+        if self.can_tsumo() {
+            actions.push(Action::Tsumo);
+        }
+
+        actions
+    }
+
+    fn reaction_actions(&self, player: u8, tile: &Tile) -> Vec<Action> {
+        let mut actions = vec![Action::Pass];
+
+        if self.can_ron(player, tile) {
+            actions.push(Action::Ron);
+        }
+
+        // no calls on a kan, in riichi or on the last discard
+        if self.my_chankan.is_some()
+            || self.did_i_riichi()
+            || self.tiles_remaining == Some(0)
+            || self.get_my_hand().count_tiles() != 13
+        {
+            return actions;
+        }
+
+        let closed_tiles = self.my_closed_tiles();
+        let with_id = |tile_id: u8| -> Vec<Tile> {
+            closed_tiles
+                .iter()
+                .filter(|t| t.get_id() == tile_id)
+                .copied()
+                .collect()
+        };
+
+        // one pon for each number of red 5s used
+        let copies = with_id(tile.get_id());
+        let mut red_counts = vec![];
+        for (i, first) in copies.iter().enumerate() {
+            for second in copies.iter().skip(i + 1) {
+                let red_count = first.is_red as u8 + second.is_red as u8;
+                let pon = Action::Pon(*first, *second);
+                if !red_counts.contains(&red_count) && self.can_discard_after_call(tile, &pon) {
+                    red_counts.push(red_count);
+                    actions.push(pon);
+                }
+            }
+        }
+
+        if copies.len() >= 3 && self.can_kan() {
+            actions.push(Action::Daiminkan);
+        }
+
+        // chi only from kamicha
+        if player == 3 {
+            if let TileType::Number(number, _) = tile.tile_type {
+                let id = tile.get_id() as i8;
+                let number = number as i8;
+                for (a, b) in [(-2, -1), (-1, 1), (1, 2)].iter() {
+                    if number + a < 1 || number + b > 9 {
+                        continue;
+                    }
+
+                    for first in distinct_tiles(&with_id((id + a) as u8)).iter() {
+                        for second in distinct_tiles(&with_id((id + b) as u8)).iter() {
+                            let chi = Action::Chi(*first, *second);
+                            if self.can_discard_after_call(tile, &chi) {
+                                actions.push(chi);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        actions
+    }
+
+    /// Tiles I can't discard right after calling this tile with a chi or pon (kuikae), according to the rules.
+    /// Genbutsu kuikae forbids the called tile, suji kuikae also the tile on the other end of a chi.
+    pub fn forbidden_discards(&self, called: &Tile, call: &Action) -> Vec<Tile> {
+        let kuikae = self.get_rules().unwrap_or_default().kuikae;
+        if kuikae == Kuikae::Allowed {
+            return vec![];
+        }
+
+        let mut forbidden = vec![Tile::from_id(called.get_id()).unwrap()];
+        if kuikae == Kuikae::Forbidden {
+            if let (Action::Chi(first, second), TileType::Number(number, _)) =
+                (call, called.tile_type)
+            {
+                let lowest = first.get_id().min(second.get_id()).min(called.get_id());
+                let id = called.get_id();
+
+                // called 3m with 45m -> 6m, called 6m with 45m -> 3m
+                if id == lowest && number <= 6 {
+                    forbidden.push(Tile::from_id(id + 3).unwrap());
+                } else if id == lowest + 2 && number >= 4 {
+                    forbidden.push(Tile::from_id(id - 3).unwrap());
+                }
+            }
+        }
+
+        forbidden
+    }
+
+    /// Tiles I can't discard now because of kuikae
+    pub fn get_my_forbidden_discards(&self) -> &Vec<Tile> {
+        &self.my_forbidden_discards
+    }
+
+    /// Set the tiles I can't discard after my call (see forbidden_discards)
+    pub fn set_my_forbidden_discards(&mut self, tiles: Vec<Tile>) {
+        self.my_forbidden_discards = tiles;
+    }
+
+    /// Would I have anything to discard after this call?
+    fn can_discard_after_call(&self, called: &Tile, call: &Action) -> bool {
+        let mut remaining = self.my_closed_tiles();
+        if let Action::Chi(first, second) | Action::Pon(first, second) = call {
+            for used in [first, second].iter() {
+                if let Some(i) = remaining.iter().position(|t| t.eq(used)) {
+                    remaining.remove(i);
+                }
+            }
+        }
+
+        let forbidden = self.forbidden_discards(called, call);
+        remaining.iter().any(|t| !forbidden.iter().any(|f| f.eq(t)))
+    }
+
+    /// My closed tiles (not in calls or kans)
+    fn my_closed_tiles(&self) -> Vec<Tile> {
+        self.get_my_hand()
+            .get_tiles()
+            .iter()
+            .flatten()
+            .filter(|t| !t.is_open && !t.is_kan)
+            .copied()
+            .collect()
+    }
+
+    /// Kans need a fifth kan to be impossible and a tile left in the wall
+    fn can_kan(&self) -> bool {
+        let kans: u8 = (0..4).map(|player| self.count_kans(player)).sum();
+
+        kans < 4 && self.tiles_remaining.unwrap_or(70) > 0
+    }
+
+    /// My waits, if my 13 tile hand is tenpai
+    pub fn get_my_waits(&self) -> Vec<Tile> {
+        let mut hand = self.get_my_hand().clone();
+        if hand.count_tiles() != 13 || hand.shanten() != 0 {
+            return vec![];
+        }
+
+        match hand.find_shanten_improving_tiles(None).first() {
+            None => vec![],
+            Some((_, tiles, _)) => tiles.iter().map(|(tile, _count)| *tile).collect(),
+        }
+    }
+
+    /// I let this tile go without a ron (another player's discard or kan). If it was one of my waits,
+    /// I'm furiten until my next discard, or until the end of the hand in riichi.
+    pub fn pass_on_tile(&mut self, tile: &Tile) {
+        if !self.get_my_waits().iter().any(|wait| wait.eq(tile)) {
+            return;
+        }
+
+        self.my_temporary_furiten = Some(true);
+        if self.did_i_riichi() {
+            self.my_riichi_furiten = Some(true);
+        }
+    }
+
+    /// Set temporary furiten
+    pub fn set_my_temporary_furiten(&mut self, value: bool) {
+        self.my_temporary_furiten = Some(value);
+    }
+
+    /// Am I furiten since passing on a winning tile this go-around?
+    pub fn get_my_temporary_furiten(&self) -> bool {
+        self.my_temporary_furiten.unwrap_or(false)
+    }
+
+    /// Set riichi furiten
+    pub fn set_my_riichi_furiten(&mut self, value: bool) {
+        self.my_riichi_furiten = Some(value);
+    }
+
+    /// Am I furiten since passing on a winning tile in riichi?
+    pub fn get_my_riichi_furiten(&self) -> bool {
+        self.my_riichi_furiten.unwrap_or(false)
+    }
+
+    /// Why am I furiten, if I am?
+    pub fn get_furiten(&self) -> Option<Furiten> {
+        if self
+            .get_my_waits()
+            .iter()
+            .any(|wait| self.my_discards.iter().any(|d| d.eq(wait)))
+        {
+            return Some(Furiten::Permanent);
+        }
+
+        if self.my_riichi_furiten == Some(true) {
+            return Some(Furiten::Riichi);
+        }
+
+        if self.my_temporary_furiten == Some(true) {
+            return Some(Furiten::Temporary);
+        }
+
+        None
+    }
+
+    /// Am I furiten?
+    pub fn is_furiten(&self) -> bool {
+        self.get_furiten().is_some()
+    }
+
+    /// What would each of my waits win? Ron results respect furiten.
+    pub fn get_my_wait_results(&self) -> Vec<WaitResult> {
+        let furiten = self.is_furiten();
+
+        self.get_my_waits()
+            .into_iter()
+            .map(|tile| {
+                let mut hand = self.get_my_hand().clone();
+                let mut drawn = tile;
+                drawn.is_draw = true;
+                hand.reset_drawn_tiles();
+                hand.add_tile(drawn);
+
+                let mut table = self.clone();
+                table.set_my_hand(hand);
+                table.set_my_tsumo(true);
+                let tsumo = match table.yaku() {
+                    Some(result) if !result.yaku.is_empty() => Some(result),
+                    _ => None,
+                };
+
+                WaitResult {
+                    tile,
+                    ron: if furiten {
+                        None
+                    } else {
+                        self.ron_yaku(3, &tile)
+                    },
+                    tsumo,
+                }
+            })
+            .collect()
+    }
+
+    /// Can I win with the tile I drew? The hand has to have a yaku.
+    pub fn can_tsumo(&self) -> bool {
+        let hand = self.get_my_hand();
+        if hand.count_tiles() != 14 || hand.get_drawn_tile().is_none() {
+            return false;
+        }
+
+        let mut table = self.clone();
+        table.set_my_tsumo(true);
+
+        matches!(table.yaku(), Some(result) if !result.yaku.is_empty())
+    }
+
+    /// Can I ron this tile discarded by a player? The hand has to have a yaku and can't be furiten.
+    pub fn can_ron(&self, player: u8, tile: &Tile) -> bool {
+        !self.is_furiten() && self.ron_yaku(player, tile).is_some()
+    }
+
+    /// Yaku and score if I won by ron on this tile from a player. Furiten is not checked.
+    pub fn ron_yaku(&self, player: u8, tile: &Tile) -> Option<HandResult> {
+        if self.get_my_hand().count_tiles() != 13 {
+            return None;
+        }
+
+        let mut hand = self.get_my_hand().clone();
+        let mut tile = *tile;
+        tile.is_draw = true;
+        tile.is_riichi = false;
+        tile.is_tsumogiri = false;
+        hand.reset_drawn_tiles();
+        hand.add_tile(tile);
+
+        let mut table = self.clone();
+        table.set_my_hand(hand);
+        table.set_my_tsumo(false);
+        table.set_ron_from(player);
+
+        match table.yaku() {
+            Some(result) if !result.yaku.is_empty() => Some(result),
+            _ => None,
+        }
+    }
+
     /// Finds yaku based on the table state. Some yaku depend on winds, tsumo / ron, tiles remaining etc.
-    pub fn yaku(&mut self) -> Option<(Vec<Yaku>, Score)> {
+    pub fn yaku(&mut self) -> Option<HandResult> {
         let yf = YakuFinder::new();
         yf.find(self)
     }
@@ -520,7 +1349,9 @@ impl Table {
             }
         }
 
-        if ret_tiles.len() == 2 {
+        if ret_tiles.len() == 2
+            && self.can_discard_after_call(tile, &Action::Pon(ret_tiles[0], ret_tiles[1]))
+        {
             return Some(ret_tiles);
         }
 
@@ -529,7 +1360,7 @@ impl Table {
 
     /// Can my hand do an ankan (closed kan)? And if so, with what tile?
     pub fn can_ankan(&self) -> Option<Vec<Tile>> {
-        let mut hand = self.get_my_hand().clone();
+        let hand = self.get_my_hand();
         if !hand.is_closed() {
             return None;
         }
@@ -550,40 +1381,23 @@ impl Table {
             return None;
         }
 
-        return if self.did_i_riichi() {
-            // I can only kan with tiles that won't change my hand structure
-            // I can also only kan with the drawn tile
-            let mut drawn_tile = *hand.get_drawn_tile().unwrap();
-            let drawn_tile_id = drawn_tile.get_id();
-
-            if !kannable_tiles.contains(&drawn_tile_id) {
-                return None;
-            }
-
-            hand.remove_tile_by_id(drawn_tile_id);
-            let no_kan_improving_tiles = hand.find_shanten_improving_tiles(None);
-
-            drawn_tile.is_draw = false;
-            hand.add_tile(drawn_tile);
-            hand.add_closed_kan(ClosedShape::Kantsu([
-                drawn_tile, drawn_tile, drawn_tile, drawn_tile,
-            ]));
-
-            hand.reset_shanten();
-            let kan_improving_tiles = hand.find_shanten_improving_tiles(None);
-
-            if no_kan_improving_tiles.is_empty() || kan_improving_tiles.is_empty() {
-                return None;
+        if self.did_i_riichi() {
+            let drawn_tile = *hand.get_drawn_tile()?;
+            let rules = self.get_rules().unwrap_or_default();
+
+            // without okurikan, I can only kan with the drawn tile
+            let riichi_kans: Vec<Tile> = kannable_tiles
+                .iter()
+                .filter(|tile_id| rules.okurikan || **tile_id == drawn_tile.get_id())
+                .map(|tile_id| Tile::from_id(*tile_id).unwrap())
+                .filter(|tile| riichi_kan_keeps_hand(hand, &drawn_tile, tile, &rules))
+                .collect();
+
+            if riichi_kans.is_empty() {
+                None
             } else {
-                let (_to, no_kan_ukeire, _total_count) = no_kan_improving_tiles.get(0).unwrap();
-                let (_to, kan_ukeire, _total_count) = kan_improving_tiles.get(0).unwrap();
-
-                if no_kan_ukeire.eq(kan_ukeire) {
-                    return Some(vec![drawn_tile]);
-                }
+                Some(riichi_kans)
             }
-
-            None
         } else {
             let mut kannable_vec = vec![];
             for tile_id in kannable_tiles.iter() {
@@ -591,7 +1405,7 @@ impl Table {
             }
 
             Some(kannable_vec)
-        };
+        }
     }
 
     /// How safe is this tile to discard based on this table state?
@@ -719,6 +1533,92 @@ impl Table {
     }
 }
 
+/// One tile of each kind, red 5s are a kind of their own
+fn distinct_tiles(tiles: &[Tile]) -> Vec<Tile> {
+    let mut kinds: Vec<Tile> = vec![];
+    for tile in tiles.iter() {
+        if !kinds
+            .iter()
+            .any(|k| k.get_id() == tile.get_id() && k.is_red == tile.is_red)
+        {
+            kinds.push(*tile);
+        }
+    }
+
+    kinds
+}
+
+/// Does a closed kan in riichi leave my hand as it was? My waits can't change and with strict rules,
+/// the kan tiles have to be a triplet in every way the hand can win.
+fn riichi_kan_keeps_hand(hand: &Hand, drawn_tile: &Tile, kan_tile: &Tile, rules: &Rules) -> bool {
+    let mut before = hand.clone();
+    before.remove_tile(drawn_tile);
+
+    let mut after = hand.clone();
+    after.reset_drawn_tiles();
+    after.add_closed_kan(ClosedShape::Kantsu([*kan_tile; 4]));
+
+    let waits = regular_waits(&before);
+    if waits.is_empty() || waits != regular_waits(&after) {
+        return false;
+    }
+
+    if rules.riichi_kan == RiichiKan::Lenient {
+        return true;
+    }
+
+    waits.iter().all(|wait| {
+        let mut complete = before.clone();
+        complete.add_tile(*wait);
+
+        ShapeFinder::new()
+            .find(&mut complete)
+            .iter()
+            .filter(|variant| is_regular_variant(variant))
+            .all(|variant| {
+                variant.iter().any(|shape| match shape.get_shape_type() {
+                    ShapeType::Complete(CompleteShape::Closed(ClosedShape::Koutsu(tiles))) => {
+                        tiles[0].get_id() == kan_tile.get_id()
+                    }
+                    _ => false,
+                })
+            })
+    })
+}
+
+/// Tiles that complete a 13 tile hand into 4 groups and a pair
+fn regular_waits(hand: &Hand) -> Vec<Tile> {
+    let array_34 = hand.get_34_array(false);
+
+    (1..=34)
+        .filter(|tile_id| array_34[(*tile_id - 1) as usize] < 4)
+        .map(|tile_id| Tile::from_id(tile_id).unwrap())
+        .filter(|tile| {
+            let mut complete = hand.clone();
+            complete.add_tile(*tile);
+
+            ShapeFinder::new()
+                .find(&mut complete)
+                .iter()
+                .any(|variant| is_regular_variant(variant))
+        })
+        .collect()
+}
+
+/// 4 groups and a pair, not chiitoitsu or kokushi
+fn is_regular_variant(variant: &[Shape]) -> bool {
+    let mut pairs = 0;
+    for shape in variant.iter() {
+        match shape.get_shape_type() {
+            ShapeType::Complete(CompleteShape::Closed(ClosedShape::Toitsu(_))) => pairs += 1,
+            ShapeType::Complete(CompleteShape::Closed(ClosedShape::Single(_))) => return false,
+            _ => {}
+        }
+    }
+
+    pairs == 1
+}
+
 mod tests {
 
     #[test]
@@ -774,6 +1674,46 @@ mod tests {
         assert!(table.can_ankan() != None);
     }
 
+    #[test]
+    fn riichi_ankan_strict_and_lenient() {
+        use super::*;
+        let mut table = Table::from_map(&Map::new()).unwrap();
+        // 111 222 333m can also be 123 123 123m, the waits stay 3p6p after the kan
+        table.set_my_hand(Hand::from_text("111222333m45p66s1m", false).unwrap());
+        table.set_my_riichi(true);
+
+        assert!(table.can_ankan() == None);
+
+        table.set_rules(Rules {
+            riichi_kan: RiichiKan::Lenient,
+            ..Rules::tenhou()
+        });
+        assert_eq!(
+            table.can_ankan(),
+            Some(vec![Tile::from_text("1m").unwrap()])
+        );
+    }
+
+    #[test]
+    fn riichi_okurikan() {
+        use super::*;
+        let mut table = Table::from_map(&Map::new()).unwrap();
+        // 5555m without the drawn 8m, 678m replaces 567m and the wait stays 9s
+        table.set_my_hand(Hand::from_text("555567m234p678s9s8m", false).unwrap());
+        table.set_my_riichi(true);
+
+        assert!(table.can_ankan() == None);
+
+        table.set_rules(Rules {
+            okurikan: true,
+            ..Rules::tenhou()
+        });
+        assert_eq!(
+            table.can_ankan(),
+            Some(vec![Tile::from_text("5m").unwrap()])
+        );
+    }
+
     #[test]
     fn safety_riichi() {
         use super::*;
@@ -793,4 +1733,264 @@ mod tests {
 
         println!("{}", safety);
     }
+
+    #[test]
+    fn kyuushu_kyuuhai() {
+        use super::*;
+        let mut table = Table::from_map(&Map::new()).unwrap();
+        table.set_my_hand(Hand::from_text("19m19p1s123456z258s", false).unwrap());
+        assert!(table.can_declare_kyuushu_kyuuhai());
+
+        table.set_my_hand(Hand::from_text("19m19p1234z255588s", false).unwrap());
+        assert!(!table.can_declare_kyuushu_kyuuhai());
+
+        table.set_my_hand(Hand::from_text("19m19p1s123456z258s", false).unwrap());
+        table.set_rules(Rules::wrc());
+        assert!(!table.can_declare_kyuushu_kyuuhai());
+    }
+
+    #[test]
+    fn suufon_renda() {
+        use super::*;
+        let mut table = Table::from_map(&Map::new()).unwrap();
+        for player in 0..4 {
+            table.add_tile_to_discards(player, Tile::from_text("2z").unwrap());
+        }
+        assert_eq!(table.abortive_draw(), Some(DrawReason::SuufonRenda));
+
+        table.add_tile_to_discards(0, Tile::from_text("3z").unwrap());
+        assert_eq!(table.abortive_draw(), None);
+    }
+
+    #[test]
+    fn suucha_riichi() {
+        use super::*;
+        let mut table = Table::from_map(&Map::new()).unwrap();
+        table.set_my_riichi(true);
+        table.set_p1_riichi(true);
+        table.set_p2_riichi(true);
+        assert_eq!(table.abortive_draw(), None);
+
+        table.set_p3_riichi(true);
+        assert_eq!(table.abortive_draw(), Some(DrawReason::SuuchaRiichi));
+
+        table.set_rules(Rules::wrc());
+        assert_eq!(table.abortive_draw(), None);
+    }
+
+    #[test]
+    fn suukaikan() {
+        use super::*;
+        let kan = |text: &str| {
+            let tile = Tile::from_text(text).unwrap();
+            Shape::new(
+                ShapeType::Complete(CompleteShape::Open(OpenShape::Kan(
+                    crate::riichi::shapes::OpenKan::Daiminkan([tile; 4]),
+                ))),
+                4,
+                true,
+            )
+        };
+
+        let mut table = Table::from_map(&Map::new()).unwrap();
+        for tile in ["1z", "2z", "3z", "4z"].iter() {
+            table.add_open_shape(1, kan(tile));
+        }
+        // suukantsu is still possible
+        assert_eq!(table.abortive_draw(), None);
+
+        let mut table = Table::from_map(&Map::new()).unwrap();
+        table.add_open_shape(1, kan("1z"));
+        table.add_open_shape(1, kan("2z"));
+        table.add_open_shape(3, kan("3z"));
+        assert_eq!(table.abortive_draw(), None);
+        table.add_open_shape(2, kan("4z"));
+        assert_eq!(table.abortive_draw(), Some(DrawReason::Suukaikan));
+    }
+
+    #[test]
+    fn legal_chi_with_red_fives() {
+        use super::*;
+        let mut table = Table::from_map(&Map::new()).unwrap();
+        table.set_my_hand(Hand::from_text("4m50m123p456p789s1z", false).unwrap());
+
+        let six = Tile::from_text("6m").unwrap();
+        let chis: Vec<(Tile, Tile)> = table
+            .legal_actions(Some((3, &six)))
+            .into_iter()
+            .filter_map(|a| match a {
+                Action::Chi(first, second) => Some((first, second)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(chis.len(), 2);
+        assert!(chis.iter().any(|(_, second)| second.is_red));
+        assert!(chis.iter().any(|(_, second)| !second.is_red));
+
+        // chi only from kamicha
+        assert_eq!(table.legal_actions(Some((1, &six))), vec![Action::Pass]);
+    }
+
+    #[test]
+    fn legal_pon_and_daiminkan() {
+        use super::*;
+        let mut table = Table::from_map(&Map::new()).unwrap();
+        table.set_my_hand(Hand::from_text("550m123p456p789s1z", false).unwrap());
+
+        let five = Tile::from_text("5m").unwrap();
+        let actions = table.legal_actions(Some((2, &five)));
+        let pons = actions
+            .iter()
+            .filter(|a| matches!(a, Action::Pon(_, _)))
+            .count();
+        assert_eq!(pons, 2);
+        assert!(actions.contains(&Action::Daiminkan));
+
+        // no calls on the last discard
+        table.set_tiles_remaining(0);
+        assert_eq!(table.legal_actions(Some((2, &five))), vec![Action::Pass]);
+    }
+
+    #[test]
+    fn legal_riichi() {
+        use super::*;
+        let mut table = Table::from_map(&Map::new()).unwrap();
+        table.set_my_hand(Hand::from_text("123m456p789s11z349m", false).unwrap());
+
+        let riichi: Vec<Action> = table
+            .legal_actions(None)
+            .into_iter()
+            .filter(|a| matches!(a, Action::Riichi(_)))
+            .collect();
+        assert_eq!(riichi, vec![Action::Riichi(Tile::from_text("9m").unwrap())]);
+
+        // not enough points
+        table.set_points(0, 900);
+        assert!(!table
+            .legal_actions(None)
+            .iter()
+            .any(|a| matches!(a, Action::Riichi(_))));
+
+        // in riichi, only the drawn tile can be discarded
+        table.set_points(0, 25000);
+        table.set_my_riichi(true);
+        assert_eq!(
+            table.legal_actions(None),
+            vec![Action::Discard(Tile::from_text("9m").unwrap())]
+        );
+    }
+
+    #[test]
+    fn legal_ron_and_furiten() {
+        use super::*;
+        let mut table = Table::from_map(&Map::new()).unwrap();
+        table.set_my_hand(Hand::from_text("123m456p789s11z34m", false).unwrap());
+        let two = Tile::from_text("2m").unwrap();
+
+        // no yaku
+        assert_eq!(table.legal_actions(Some((1, &two))), vec![Action::Pass]);
+
+        table.set_my_riichi(true);
+        assert_eq!(
+            table.legal_actions(Some((1, &two))),
+            vec![Action::Pass, Action::Ron]
+        );
+
+        table.add_tile_to_discards(0, Tile::from_text("5m").unwrap());
+        assert_eq!(table.legal_actions(Some((1, &two))), vec![Action::Pass]);
+    }
+
+    #[test]
+    fn furiten() {
+        use super::*;
+        let mut table = Table::from_map(&Map::new()).unwrap();
+        table.set_my_hand(Hand::from_text("123m456p789s11z34m", false).unwrap());
+        table.set_my_riichi(true);
+        let two = Tile::from_text("2m").unwrap();
+        let five = Tile::from_text("5m").unwrap();
+
+        assert_eq!(table.get_furiten(), None);
+        assert!(table.can_ron(1, &two));
+
+        // passing on 5m, still furiten on 2m
+        table.pass_on_tile(&five);
+        assert_eq!(table.get_furiten(), Some(Furiten::Riichi));
+        assert!(!table.can_ron(1, &two));
+
+        // riichi furiten stays after my discard
+        table.add_tile_to_discards(0, Tile::from_text("9p").unwrap());
+        assert_eq!(table.get_furiten(), Some(Furiten::Riichi));
+
+        let mut table = Table::from_map(&Map::new()).unwrap();
+        table.set_my_hand(Hand::from_text("123m456p789s11z34m", false).unwrap());
+        table.pass_on_tile(&Tile::from_text("9p").unwrap());
+        assert_eq!(table.get_furiten(), None);
+        table.pass_on_tile(&two);
+        assert_eq!(table.get_furiten(), Some(Furiten::Temporary));
+        table.add_tile_to_discards(0, Tile::from_text("9p").unwrap());
+        assert_eq!(table.get_furiten(), None);
+
+        table.add_tile_to_discards(0, five);
+        assert_eq!(table.get_furiten(), Some(Furiten::Permanent));
+    }
+
+    #[test]
+    fn wait_results() {
+        use super::*;
+        let mut table = Table::from_map(&Map::new()).unwrap();
+        table.set_my_hand(Hand::from_text("234m456p678s22p34s", false).unwrap());
+        table.set_my_seat_wind(2);
+        table.set_prevalent_wind(1);
+
+        let results = table.get_my_wait_results();
+        assert_eq!(results.len(), 2);
+        for result in results.iter() {
+            assert!(result.ron.is_some());
+            assert!(result.tsumo.is_some());
+        }
+
+        table.add_tile_to_discards(0, Tile::from_text("2s").unwrap());
+        let results = table.get_my_wait_results();
+        assert!(results.iter().all(|r| r.ron.is_none() && r.tsumo.is_some()));
+    }
+
+    #[test]
+    fn kuikae() {
+        use super::*;
+        let mut table = Table::from_map(&Map::new()).unwrap();
+        table.set_my_hand(Hand::from_text("2344m(p1z1)(p2z2)(p3z3)", false).unwrap());
+        let one = Tile::from_text("1m").unwrap();
+        let chi = Action::Chi(
+            Tile::from_text("2m").unwrap(),
+            Tile::from_text("3m").unwrap(),
+        );
+
+        assert_eq!(
+            table.forbidden_discards(&one, &chi),
+            vec![one, Tile::from_text("4m").unwrap()]
+        );
+        // no ron in furiten, only 44m would be left and both are forbidden
+        table.set_my_temporary_furiten(true);
+        assert_eq!(table.legal_actions(Some((3, &one))), vec![Action::Pass]);
+
+        table.set_rules(Rules {
+            kuikae: Kuikae::Genbutsu,
+            ..Rules::tenhou()
+        });
+        assert_eq!(table.forbidden_discards(&one, &chi), vec![one]);
+        assert_eq!(
+            table.legal_actions(Some((3, &one))),
+            vec![Action::Pass, chi]
+        );
+
+        // right after the call
+        let mut hand = Hand::from_text("4m5p(123m0)(p1z1)(p2z2)(p3z3)", false).unwrap();
+        hand.reset_drawn_tiles();
+        table.set_my_hand(hand);
+        table.set_my_forbidden_discards(vec![one, Tile::from_text("4m").unwrap()]);
+        assert_eq!(
+            table.legal_actions(None),
+            vec![Action::Discard(Tile::from_text("5p").unwrap())]
+        );
+    }
 }
@@ -4,6 +4,9 @@ use std::cmp::Ordering;
 use std::fmt;
 use wasm_bindgen::__rt::core::fmt::{Display, Formatter};
 
+/// MJAI names of the winds and dragons in tile id order
+const MJAI_HONORS: [&str; 7] = ["E", "S", "W", "N", "P", "F", "C"];
+
 // '0m', '1m', '2m', '3m', '4m', '5m', '6m', '7m', '8m', '9m',
 // '0p', '1p', '2p', '3p', '4p', '5p', '6p', '7p', '8p', '9p',
 // '0s', '1s', '2s', '3s', '4s', '5s', '6s', '7s', '8s', '9s',
@@ -200,6 +203,60 @@ impl Tile {
         Ok(Tile::new(TileType::Dragon(id - 27)))
     }
 
+    /// Tile from a tenhou type 136 id (0 - 135), 4 copies of each tile in the from_id order.
+    /// The first copy of each 5 is the red one.
+    pub fn from_id_136(id_136: u8) -> Result<Tile, RiichiError> {
+        if id_136 > 135 {
+            return Err(RiichiError::new(
+                108,
+                &format!("Wrong 136 tile ID {}", id_136)[..],
+            ));
+        }
+
+        let mut tile = Tile::from_id(id_136 / 4 + 1)?;
+        tile.is_red = [16, 52, 88].contains(&id_136);
+        tile.id_136 = Some(id_136);
+
+        Ok(tile)
+    }
+
+    /// Tile from an MJAI name: "1m" - "9s" with "5mr" for a red 5, winds are "E", "S", "W", "N"
+    /// and dragons are "P" (white), "F" (green) and "C" (red)
+    pub fn from_mjai(name: &str) -> Result<Tile, RiichiError> {
+        if let Some(honor) = MJAI_HONORS.iter().position(|honor| *honor == name) {
+            return Tile::from_id(28 + honor as u8);
+        }
+
+        let chars: Vec<char> = name.chars().collect();
+        match chars[..] {
+            ['1'..='9', 'm' | 'p' | 's'] => Tile::from_text(name),
+            ['5', 'm' | 'p' | 's', 'r'] => {
+                let mut tile = Tile::from_text(&name[..2])?;
+                tile.is_red = true;
+                Ok(tile)
+            }
+            _ => Err(RiichiError::new(
+                104,
+                &format!("Invalid MJAI tile {}", name)[..],
+            )),
+        }
+    }
+
+    /// MJAI name of this tile
+    pub fn to_mjai(&self) -> String {
+        match self.tile_type {
+            TileType::Number(number, color) => format!(
+                "{}{}{}",
+                number,
+                color,
+                if self.is_red { "r" } else { "" }
+            ),
+            TileType::Wind(_) | TileType::Dragon(_) => {
+                MJAI_HONORS[(self.get_id() - 28) as usize].to_string()
+            }
+        }
+    }
+
     /// Gets the id of this tile based on its type
     pub fn get_id(&self) -> u8 {
         match &self.tile_type {
@@ -565,6 +622,33 @@ impl serde::Serialize for Tile {
 mod tests {
     use super::*;
 
+    #[test]
+    fn from_id_136() {
+        let tile = Tile::from_id_136(53).unwrap();
+        assert_eq!(tile, Tile::from_text("5p").unwrap());
+        assert_eq!(tile.id_136, Some(53));
+        assert!(!tile.is_red);
+
+        assert!(Tile::from_id_136(88).unwrap().is_red);
+        assert_eq!(Tile::from_id_136(135).unwrap().to_string(), "7z");
+        assert!(Tile::from_id_136(136).is_err());
+    }
+
+    #[test]
+    fn mjai_names() {
+        let red = Tile::from_mjai("5pr").unwrap();
+        assert_eq!(red, Tile::from_text("5p").unwrap());
+        assert!(red.is_red);
+        assert_eq!(red.to_mjai(), "5pr");
+
+        assert_eq!(Tile::from_mjai("E").unwrap().to_string(), "1z");
+        assert_eq!(Tile::from_mjai("C").unwrap().to_mjai(), "C");
+        assert_eq!(Tile::from_text("9s").unwrap().to_mjai(), "9s");
+        assert!(Tile::from_mjai("4mr").is_err());
+        assert!(Tile::from_mjai("1z").is_err());
+        assert!(Tile::from_mjai("?").is_err());
+    }
+
     #[test]
     fn next_number_less_than_9() {
         let tile = Tile::new(TileType::Number(4, TileColor::Manzu));
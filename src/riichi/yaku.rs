@@ -1,13 +1,15 @@
-use crate::riichi::scores::Score;
+use crate::riichi::fu::FuBreakdown;
+use crate::riichi::scores::{Limit, Score};
 use crate::riichi::shape_finder::ShapeFinder;
 use crate::riichi::shapes::{ClosedShape, CompleteShape, OpenKan, OpenShape, Shape, ShapeType};
-use crate::riichi::table::Table;
-use crate::riichi::tile::{Tile, TileType};
+use crate::riichi::table::{RobbedKan, Table};
+use crate::riichi::tile::{Tile, TileColor, TileType};
 use enum_iterator::IntoEnumIterator;
+use serde::Serialize;
 use std::collections::HashMap;
 use wasm_bindgen::__rt::std::collections::hash_map::Entry;
 
-#[derive(IntoEnumIterator, Debug, Clone)]
+#[derive(IntoEnumIterator, Debug, Clone, Serialize)]
 #[doc(hidden)]
 pub enum Yaku {
     // 1 han closed
@@ -51,7 +53,6 @@ pub enum Yaku {
     // 6 han
     Chinitsu,
     // Yakuman
-    Kazoe,
     Kokushi,
     Suuankou,
     Daisangen,
@@ -67,6 +68,33 @@ pub enum Yaku {
     Chiihou,
 }
 
+/// The best interpretation of a complete hand: its yaku, dora and final score
+#[derive(Debug, Serialize)]
+pub struct HandResult {
+    /// Yaku found in the hand, with their han
+    pub yaku: Vec<(Yaku, u8)>,
+    /// Number of dora from the dora indicators
+    pub dora: u8,
+    /// Number of red fives (only with aka ari)
+    pub aka_dora: u8,
+    /// Number of ura dora (only with riichi)
+    pub ura_dora: u8,
+    /// Itemized fu of the hand (not available for yakuman and hands without yaku)
+    pub fu: Option<FuBreakdown>,
+    /// The final score, dora included
+    pub score: Score,
+    /// The score before ura dora were revealed
+    pub score_before_ura: Score,
+    /// Limit reached by the final score
+    pub limit: Limit,
+    /// Payment as it is usually written, e.g. "7700" or "2000-3900"
+    pub payment: String,
+    /// What each player pays (0 = me, 1 = shimocha, 2 = toimen, 3 = kamicha)
+    pub payments: [u32; 4],
+    /// Point changes of all players with honba and riichi sticks (zeros if the dealer is unknown)
+    pub point_deltas: [i32; 4],
+}
+
 /// Finds yaku in a hand
 #[derive(Default)]
 pub struct YakuFinder {}
@@ -78,7 +106,7 @@ impl YakuFinder {
     }
 
     /// Finds the best variant of the hand + its score
-    pub fn find(&self, table: &mut Table) -> Option<(Vec<Yaku>, Score)> {
+    pub fn find(&self, table: &mut Table) -> Option<HandResult> {
         // only complete hands
         let hand = &mut table.get_my_hand().clone();
 
@@ -88,12 +116,30 @@ impl YakuFinder {
 
         let mut sf = ShapeFinder::new();
         let variants = sf.find(hand);
-        let mut best_variant: (Vec<Yaku>, Score) = (vec![], Score::new(0, 0, false, false));
-
-        for (i, variant) in variants.iter().enumerate() {
-            let mut yakus: Vec<Yaku> = vec![];
+        let mut best_variant = HandResult {
+            yaku: vec![],
+            dora: 0,
+            aka_dora: 0,
+            ura_dora: 0,
+            fu: None,
+            score: Score::new(0, 0, false, false),
+            score_before_ura: Score::new(0, 0, false, false),
+            limit: Limit::None,
+            payment: String::new(),
+            payments: [0; 4],
+            point_deltas: [0; 4],
+        };
+        let (dora, aka_dora, ura_dora) = self.count_dora(table);
+        let rules = table.get_rules().unwrap_or_default();
+        let mut found = false;
+
+        for variant in variants.iter() {
+            let mut yakus: Vec<(Yaku, u8)> = vec![];
             let mut han: u8 = 0;
             let mut fu: u8 = 0;
+            let mut yakuman: u8 = 0;
+            let mut pinfu = false;
+            let mut variant_dora: (u8, u8, u8) = (0, 0, 0);
 
             // first find potential yakumans
             for yaku_type in Yaku::into_enum_iter() {
@@ -102,12 +148,28 @@ impl YakuFinder {
                 }
 
                 if yaku_type.is_in_hand(table, variant) {
-                    yakus.push(yaku_type.clone());
+                    if rules.double_yakuman && yaku_type.is_double_yakuman(table, variant) {
+                        yakus.push((yaku_type.clone(), 26));
+                    } else {
+                        yakus.push((yaku_type.clone(), 13));
+                    }
                 }
             }
 
+            // only kokushi can win on a robbed ankan
+            if table.get_my_chankan() == Some(RobbedKan::Ankan)
+                && !yakus.iter().any(|(y, _)| matches!(y, Yaku::Kokushi))
+            {
+                continue;
+            }
+
             if !yakus.is_empty() {
-                han = 13;
+                let counts = yakus.iter().map(|(_, yaku_han)| yaku_han / 13);
+                yakuman = if rules.multiple_yakuman {
+                    counts.sum()
+                } else {
+                    counts.max().unwrap_or(1)
+                };
             } else {
                 for yaku_type in Yaku::into_enum_iter() {
                     if yaku_type.is_yakuman() {
@@ -115,183 +177,128 @@ impl YakuFinder {
                     }
 
                     if yaku_type.is_in_hand(table, variant) {
-                        match yaku_type {
-                            Yaku::Pinfu => {
-                                if table.did_i_tsumo() {
-                                    fu = 20;
-                                } else {
-                                    fu = 30;
-                                }
-                            }
-                            Yaku::Chiitoitsu => {
-                                fu = 25;
-                            }
-                            _ => (),
+                        if let Yaku::Pinfu = yaku_type {
+                            pinfu = true;
                         }
 
-                        yakus.push(yaku_type.clone());
-                        han += yaku_type.get_han(table);
+                        let yaku_han = yaku_type.get_han(table);
+                        yakus.push((yaku_type.clone(), yaku_han));
+                        han += yaku_han;
                     }
                 }
+
+                // dora only count when the hand already has a yaku
+                if han > 0 {
+                    variant_dora = (dora, aka_dora, ura_dora);
+                    han += dora + aka_dora;
+                }
+            }
+
+            let mut fu_breakdown = None;
+            if !yakus.is_empty() && !yakus[0].0.is_yakuman() {
+                let breakdown = FuBreakdown::new(table, variant, pinfu);
+                fu = breakdown.total();
+                fu_breakdown = Some(breakdown);
             }
 
             if han >= 5 {
                 fu = 0;
-            } else if han > 0 && fu == 0 {
-                // if we did not set fu based on yaku, we search shapes for fu
-                if table.did_i_tsumo() {
-                    fu = 20;
-                } else {
-                    fu = 30;
-                }
+            }
 
-                // set this for tanki, shanpon, kanchan and penchan waits
-                let mut has_value_wait = false;
+            let score_before_ura = self.score(table, han, fu, yakuman);
 
-                let winning_tile = table.get_my_winning_tile();
+            han += variant_dora.2;
+            if han >= 5 {
+                fu = 0;
+            }
 
-                for shape in variant {
-                    match shape.get_shape_type() {
-                        ShapeType::Complete(cs) => {
-                            match cs {
-                                CompleteShape::Closed(closed) => {
-                                    match closed {
-                                        ClosedShape::Shuntsu(tiles) => {
-                                            if tiles[1].eq(&winning_tile) || // kanchan
-                                                tiles[0].prev_id(false, 1) == 0 && tiles[2].eq(&winning_tile) ||
-                                                tiles[2].next_id(false, 1) == 0 && tiles[0].eq(&winning_tile)
-                                            {
-                                                // penchans
-                                                has_value_wait = true;
-                                            }
-                                        }
-                                        ClosedShape::Koutsu(tiles) => {
-                                            match &tiles[0].tile_type {
-                                                TileType::Number(value, _) => {
-                                                    if *value == 1 || *value == 9 {
-                                                        fu += 8;
-                                                    } else {
-                                                        fu += 4;
-                                                    }
-                                                }
-                                                TileType::Wind(_) | TileType::Dragon(_) => {
-                                                    fu += 8;
-                                                }
-                                            }
+            let score = self.score(table, han, fu, yakuman);
+            if !found || score.total_points() > best_variant.score.total_points() {
+                found = true;
+                best_variant = HandResult {
+                    yaku: yakus,
+                    dora: variant_dora.0,
+                    aka_dora: variant_dora.1,
+                    ura_dora: variant_dora.2,
+                    fu: fu_breakdown,
+                    limit: score.limit(),
+                    payment: score.payment_string(),
+                    payments: score.payments(table.get_dealer_player(), table.get_ron_from()),
+                    point_deltas: table.point_deltas(&score).unwrap_or([0; 4]),
+                    score,
+                    score_before_ura,
+                };
+            }
+        }
 
-                                            if tiles[0].eq(&winning_tile) {
-                                                has_value_wait = true;
-                                            }
-                                        }
-                                        ClosedShape::Kantsu(tiles) => {
-                                            match &tiles[0].tile_type {
-                                                TileType::Number(value, _) => {
-                                                    if *value == 1 || *value == 9 {
-                                                        fu += 32;
-                                                    } else {
-                                                        fu += 16;
-                                                    }
-                                                }
-                                                TileType::Wind(_) | TileType::Dragon(_) => {
-                                                    fu += 32;
-                                                }
-                                            }
+        // println!("{:#?}", best_variant);
+        if !found {
+            return None;
+        }
 
-                                            if tiles[0].eq(&winning_tile) {
-                                                panic!("Can't win on a tile in kan");
-                                            }
-                                        }
-                                        ClosedShape::Toitsu(tiles) => {
-                                            match &tiles[0].tile_type {
-                                                TileType::Number(_, _) => {}
-                                                TileType::Wind(value) => {
-                                                    // prevalent wind: +2 fu
-                                                    // my wind: another +2 fu
-                                                    match table.get_prevalent_wind() {
-                                                        None => {}
-                                                        Some(pw) => {
-                                                            if pw == *value {
-                                                                fu += 2;
-                                                            }
-                                                        }
-                                                    }
+        Some(best_variant)
+    }
 
-                                                    match table.get_my_seat_wind() {
-                                                        None => {}
-                                                        Some(msw) => {
-                                                            if msw == *value {
-                                                                fu += 2;
-                                                            }
-                                                        }
-                                                    }
-                                                }
-                                                TileType::Dragon(_) => {
-                                                    fu += 2;
-                                                }
-                                            }
+    /// Score of a hand with this han, fu and yakuman count, with the table's rules applied
+    fn score(&self, table: &Table, han: u8, fu: u8, yakuman: u8) -> Score {
+        let mut score = if yakuman > 0 {
+            Score::new_yakuman(yakuman, table.am_i_oya(), table.did_i_tsumo())
+        } else {
+            Score::new(han, fu, table.am_i_oya(), table.did_i_tsumo())
+        };
 
-                                            if tiles[0].eq(&winning_tile) {
-                                                has_value_wait = true;
-                                            }
-                                        }
-                                        ClosedShape::Single(_) => {}
-                                    }
-                                }
+        if let Some(rules) = table.get_rules() {
+            score.set_rules(rules);
+        }
 
-                                CompleteShape::Open(open) => match open {
-                                    OpenShape::Chi(_) => {}
-                                    OpenShape::Pon(tiles) => match &tiles[0].tile_type {
-                                        TileType::Number(value, _) => {
-                                            if *value == 1 || *value == 9 {
-                                                fu += 4;
-                                            } else {
-                                                fu += 2;
-                                            }
-                                        }
-                                        TileType::Wind(_) | TileType::Dragon(_) => {
-                                            fu += 4;
-                                        }
-                                    },
-                                    OpenShape::Kan(open_kan) => {
-                                        let tiles = match open_kan {
-                                            OpenKan::Daiminkan(tls) | OpenKan::Shouminkan(tls) => {
-                                                tls
-                                            }
-                                        };
-
-                                        match &tiles[0].tile_type {
-                                            TileType::Number(value, _) => {
-                                                if *value == 1 || *value == 9 {
-                                                    fu += 8;
-                                                } else {
-                                                    fu += 4;
-                                                }
-                                            }
-                                            TileType::Wind(_) | TileType::Dragon(_) => {
-                                                fu += 8;
-                                            }
-                                        }
-                                    }
-                                },
-                            }
-                        }
-                        ShapeType::Incomplete(..) => {}
-                    }
-                }
+        score
+    }
+
+    /// Counts dora, red fives and ura dora in my hand, open shapes and kans included.
+    /// Ura dora are only counted if I declared riichi.
+    fn count_dora(&self, table: &Table) -> (u8, u8, u8) {
+        let hand = table.get_my_hand();
+        let array_34 = hand.get_34_array(false);
+
+        let dora = self.count_indicated_dora(table.get_dora_indicators(), &array_34);
+
+        let mut ura_dora = 0;
+        if table.did_i_riichi() {
+            ura_dora = self.count_indicated_dora(table.get_ura_dora_indicators(), &array_34);
+        }
 
-                if has_value_wait {
-                    fu += 2;
+        // red 5s in each suit (manzu, pinzu, souzu)
+        let mut reds = [0u8; 3];
+        for tile in hand.get_tiles().iter().flatten().filter(|t| t.is_red) {
+            if let TileType::Number(_, color) = tile.tile_type {
+                match color {
+                    TileColor::Manzu => reds[0] += 1,
+                    TileColor::Pinzu => reds[1] += 1,
+                    TileColor::Souzu => reds[2] += 1,
                 }
             }
+        }
+
+        // only as many red 5s as the rules allow count
+        let aka_dora = reds
+            .iter()
+            .zip(table.get_rules().unwrap_or_default().aka.iter())
+            .map(|(red, allowed)| *red.min(allowed))
+            .sum();
+
+        (dora, aka_dora, ura_dora)
+    }
 
-            let score = Score::new(han, fu, table.am_i_oya(), table.did_i_tsumo());
-            if i == 0 || score.total_points() > best_variant.1.total_points() {
-                best_variant = (yakus, score);
+    /// How many tiles in array_34 are dora pointed to by these indicators?
+    fn count_indicated_dora(&self, indicators: &[Tile], array_34: &[u8; 34]) -> u8 {
+        let mut dora = 0;
+        for indicator in indicators.iter() {
+            if let Some(dora_tile) = indicator.next(true) {
+                dora += array_34[(dora_tile.get_id() - 1) as usize];
             }
         }
 
-        // println!("{:#?}", best_variant);
-        Some(best_variant)
+        dora
     }
 }
 
@@ -336,7 +343,6 @@ impl Yaku {
             Yaku::Junchan => "Junchan taiyao",
             Yaku::Ryanpeikou => "Ryanpeikou",
             Yaku::Chinitsu => "Chinitsu",
-            Yaku::Kazoe => "Kazoe yakuman",
             Yaku::Kokushi => "Kokushi musou",
             Yaku::Suuankou => "Suuankou",
             Yaku::Daisangen => "Daisangen",
@@ -432,7 +438,6 @@ impl Yaku {
 
                 5
             }
-            Yaku::Kazoe => 13,
             Yaku::Kokushi => 13,
             Yaku::Suuankou => 13,
             Yaku::Daisangen => 13,
@@ -452,7 +457,11 @@ impl Yaku {
     fn is_in_hand(&self, table: &mut Table, variant: &[Shape]) -> bool {
         match self {
             Yaku::MenzenTsumo => return table.get_my_hand().is_closed() && table.did_i_tsumo(),
-            Yaku::Riichi => return table.get_my_hand().is_closed() && table.did_i_riichi(),
+            Yaku::Riichi => {
+                return table.get_my_hand().is_closed()
+                    && table.did_i_riichi()
+                    && !table.did_i_double_riichi()
+            }
             Yaku::Ippatsu => {
                 if !table.get_my_hand().is_closed() || !table.did_i_riichi() {
                     return false;
@@ -465,6 +474,10 @@ impl Yaku {
                     return false;
                 }
 
+                if table.did_i_tsumo() && !table.get_rules().unwrap_or_default().tsumo_pinfu {
+                    return false;
+                }
+
                 let winning_tile = table.get_my_winning_tile();
 
                 let mut has_ryanmen_wait = false;
@@ -532,10 +545,15 @@ impl Yaku {
                 return self.find_peikou(variant) == 1;
             }
             Yaku::Haitei => {
+                // the replacement tile after a kan is never haitei
+                if table.did_i_rinshan() {
+                    return false;
+                }
+
                 return match table.get_tiles_remaining() {
                     None => false,
                     Some(remaining) => table.did_i_tsumo() && remaining == 0,
-                }
+                };
             }
             Yaku::Houtei => {
                 return match table.get_tiles_remaining() {
@@ -543,9 +561,19 @@ impl Yaku {
                     Some(remaining) => !table.did_i_tsumo() && remaining == 0,
                 }
             }
-            Yaku::Rinshan => {} // TODO
-            Yaku::Chankan => {} // TODO
+            Yaku::Rinshan => return table.did_i_tsumo() && table.did_i_rinshan(),
+            Yaku::Chankan => {
+                // robbing an ankan is only allowed for kokushi, which is a yakuman anyway
+                return !table.did_i_tsumo()
+                    && table.get_my_chankan() == Some(RobbedKan::Shouminkan);
+            }
             Yaku::Tanyao => {
+                // open tanyao only with kuitan
+                let kuitan_ari = table.get_rules().unwrap_or_default().kuitan_ari;
+                if !kuitan_ari && !table.get_my_hand().is_closed() {
+                    return false;
+                }
+
                 let array_34 = table.get_my_hand().get_34_array(false);
                 // can't contain any terminals or honors
                 for (i, count) in array_34.iter().enumerate() {
@@ -643,7 +671,9 @@ impl Yaku {
             Yaku::WhiteDragons => return self.find_yakuhai(variant, 32),
             Yaku::GreenDragons => return self.find_yakuhai(variant, 33),
             Yaku::RedDragons => return self.find_yakuhai(variant, 34),
-            Yaku::DoubleRiichi => {} // TODO
+            Yaku::DoubleRiichi => {
+                return table.get_my_hand().is_closed() && table.did_i_double_riichi()
+            }
             Yaku::Chanta => {
                 // a chanta has to have shuntsu, else it's honroutou
                 let mut has_shuntsu = false;
@@ -1260,7 +1290,6 @@ impl Yaku {
                 return true;
             }
 
-            Yaku::Kazoe => {} // TODO
             Yaku::Kokushi => {
                 if !table.get_my_hand().is_closed() {
                     return false;
@@ -1698,6 +1727,41 @@ impl Yaku {
         false
     }
 
+    /// Is this yakuman a double yakuman in this shape variant?
+    /// Suuankou tanki, kokushi 13-sided wait, junsei chuuren and daisuushii are.
+    fn is_double_yakuman(&self, table: &Table, variant: &[Shape]) -> bool {
+        let winning_tile = table.get_my_winning_tile();
+
+        match self {
+            Yaku::Suuankou => variant.iter().any(|shape| {
+                matches!(
+                    shape.get_shape_type(),
+                    ShapeType::Complete(CompleteShape::Closed(ClosedShape::Toitsu(tiles)))
+                        if tiles[0].eq(&winning_tile)
+                )
+            }),
+            Yaku::Kokushi => {
+                // all 13 tiles were waiting, so the winning tile made the pair
+                table.get_my_hand().get_34_array(true)[(winning_tile.get_id() - 1) as usize] == 2
+            }
+            Yaku::Chuuren => {
+                // 1112345678999 before the winning tile
+                let mut array_34 = table.get_my_hand().get_34_array(true);
+                let winning_id = (winning_tile.get_id() - 1) as usize;
+                if winning_id >= 27 {
+                    return false;
+                }
+
+                array_34[winning_id] -= 1;
+                let suit_start = winning_id / 9 * 9;
+
+                array_34[suit_start..suit_start + 9] == [3, 1, 1, 1, 1, 1, 1, 1, 3]
+            }
+            Yaku::Daisuushii => true,
+            _ => false,
+        }
+    }
+
     fn find_yakuhai(&self, variant: &[Shape], tile_id: u8) -> bool {
         for shape in variant.iter() {
             match shape.get_shape_type() {
@@ -1856,6 +1920,8 @@ impl Yaku {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::riichi::hand::Hand;
+    use crate::riichi::rules::Rules;
     use serde_json::{Map, Value};
 
     #[test]
@@ -1866,7 +1932,7 @@ mod tests {
         map.insert("prevalent_wind".to_string(), Value::from(1)); // east as prevalent wind = +2 fu
 
         let mut table = Table::from_map(&map).unwrap();
-        let (_yakus, score) = table.yaku().unwrap();
+        let score = table.yaku().unwrap().score;
 
         assert_eq!(score.han, 1);
         assert_eq!(score.fu, 30);
@@ -1880,7 +1946,7 @@ mod tests {
         map.insert("prevalent_wind".to_string(), Value::from(1));
 
         let mut table = Table::from_map(&map).unwrap();
-        let (_yakus, score) = table.yaku().unwrap();
+        let score = table.yaku().unwrap().score;
 
         assert_eq!(score.han, 1);
         assert_eq!(score.fu, 30);
@@ -1894,15 +1960,15 @@ mod tests {
 
         let mut table = Table::from_map(&map).unwrap();
         let res = table.yaku().unwrap();
-        assert!(match res.0.get(0).unwrap() {
+        assert!(match res.yaku.get(0).unwrap().0 {
             Yaku::MenzenTsumo => true,
             _ => false,
         });
-        assert!(match res.0.get(1).unwrap() {
+        assert!(match res.yaku.get(1).unwrap().0 {
             Yaku::Pinfu => true,
             _ => false,
         });
-        assert!(match res.0.get(2).unwrap() {
+        assert!(match res.yaku.get(2).unwrap().0 {
             Yaku::Tanyao => true,
             _ => false,
         });
@@ -1916,7 +1982,7 @@ mod tests {
 
         let mut table = Table::from_map(&map).unwrap();
         let res = table.yaku().unwrap();
-        assert!(match res.0.get(0).unwrap() {
+        assert!(match res.yaku.get(0).unwrap().0 {
             Yaku::SouthSeat => true,
             _ => false,
         });
@@ -1929,11 +1995,11 @@ mod tests {
 
         let mut table = Table::from_map(&map).unwrap();
         let res = table.yaku().unwrap();
-        assert!(match res.0.get(0).unwrap() {
+        assert!(match res.yaku.get(0).unwrap().0 {
             Yaku::Tanyao => true,
             _ => false,
         });
-        assert!(match res.0.get(1).unwrap() {
+        assert!(match res.yaku.get(1).unwrap().0 {
             Yaku::Chiitoitsu => true,
             _ => false,
         });
@@ -1946,7 +2012,7 @@ mod tests {
 
         let mut table = Table::from_map(&map).unwrap();
         let res = table.yaku().unwrap();
-        assert!(match res.0.get(0).unwrap() {
+        assert!(match res.yaku.get(0).unwrap().0 {
             Yaku::WhiteDragons => true,
             _ => false,
         });
@@ -1960,7 +2026,7 @@ mod tests {
         let mut table = Table::from_map(&map).unwrap();
 
         let res = table.yaku().unwrap();
-        assert!(match res.0.get(0).unwrap() {
+        assert!(match res.yaku.get(0).unwrap().0 {
             Yaku::WhiteDragons => true,
             _ => false,
         });
@@ -1973,7 +2039,7 @@ mod tests {
 
         let mut table = Table::from_map(&map).unwrap();
         let res = table.yaku().unwrap();
-        assert!(match res.0.get(0).unwrap() {
+        assert!(match res.yaku.get(0).unwrap().0 {
             Yaku::SanshokuDoujun => true,
             _ => false,
         });
@@ -1986,7 +2052,7 @@ mod tests {
 
         let mut table = Table::from_map(&map).unwrap();
         let res = table.yaku().unwrap();
-        assert!(match res.0.get(0).unwrap() {
+        assert!(match res.yaku.get(0).unwrap().0 {
             Yaku::SanshokuDoujun => true,
             _ => false,
         });
@@ -1999,7 +2065,7 @@ mod tests {
 
         let mut table = Table::from_map(&map).unwrap();
         let res = table.yaku().unwrap();
-        assert!(match res.0.get(0).unwrap() {
+        assert!(match res.yaku.get(0).unwrap().0 {
             Yaku::SanshokuDoukou => true,
             _ => false,
         });
@@ -2012,7 +2078,7 @@ mod tests {
 
         let mut table = Table::from_map(&map).unwrap();
         let res = table.yaku().unwrap();
-        assert!(match res.0.get(0).unwrap() {
+        assert!(match res.yaku.get(0).unwrap().0 {
             Yaku::Ittsu => true,
             _ => false,
         });
@@ -2025,7 +2091,7 @@ mod tests {
 
         let mut table = Table::from_map(&map).unwrap();
         let res = table.yaku().unwrap();
-        assert!(match res.0.get(0).unwrap() {
+        assert!(match res.yaku.get(0).unwrap().0 {
             Yaku::Ittsu => true,
             _ => false,
         });
@@ -2039,11 +2105,11 @@ mod tests {
 
         let mut table = Table::from_map(&map).unwrap();
         let res = table.yaku().unwrap();
-        assert!(match res.0.get(0).unwrap() {
+        assert!(match res.yaku.get(0).unwrap().0 {
             Yaku::Toitoi => true,
             _ => false,
         });
-        assert!(match res.0.get(1).unwrap() {
+        assert!(match res.yaku.get(1).unwrap().0 {
             Yaku::Sanankou => true,
             _ => false,
         });
@@ -2057,7 +2123,7 @@ mod tests {
 
         let mut table = Table::from_map(&map).unwrap();
         let res = table.yaku().unwrap();
-        assert!(match res.0.get(0).unwrap() {
+        assert!(match res.yaku.get(0).unwrap().0 {
             Yaku::Suuankou => true,
             _ => false,
         });
@@ -2071,15 +2137,15 @@ mod tests {
 
         let mut table = Table::from_map(&map).unwrap();
         let res = table.yaku().unwrap();
-        assert!(match res.0.get(0).unwrap() {
+        assert!(match res.yaku.get(0).unwrap().0 {
             Yaku::Toitoi => true,
             _ => false,
         });
-        assert!(match res.0.get(1).unwrap() {
+        assert!(match res.yaku.get(1).unwrap().0 {
             Yaku::Sanankou => true,
             _ => false,
         });
-        assert!(match res.0.get(2).unwrap() {
+        assert!(match res.yaku.get(2).unwrap().0 {
             Yaku::SanshokuDoukou => true,
             _ => false,
         });
@@ -2093,11 +2159,11 @@ mod tests {
 
         let mut table = Table::from_map(&map).unwrap();
         let res = table.yaku().unwrap();
-        assert!(match res.0.get(0).unwrap() {
+        assert!(match res.yaku.get(0).unwrap().0 {
             Yaku::Toitoi => true,
             _ => false,
         });
-        assert!(match res.0.get(1).unwrap() {
+        assert!(match res.yaku.get(1).unwrap().0 {
             Yaku::SanshokuDoukou => true,
             _ => false,
         });
@@ -2111,7 +2177,7 @@ mod tests {
 
         let mut table = Table::from_map(&map).unwrap();
         let res = table.yaku().unwrap();
-        assert!(match res.0.get(0).unwrap() {
+        assert!(match res.yaku.get(0).unwrap().0 {
             Yaku::Iipeikou => true,
             _ => false,
         });
@@ -2125,7 +2191,7 @@ mod tests {
 
         let mut table = Table::from_map(&map).unwrap();
         let res = table.yaku().unwrap();
-        assert!(match res.0.get(0).unwrap() {
+        assert!(match res.yaku.get(0).unwrap().0 {
             Yaku::Ryanpeikou => true,
             _ => false,
         });
@@ -2139,7 +2205,7 @@ mod tests {
 
         let mut table = Table::from_map(&map).unwrap();
         let res = table.yaku().unwrap();
-        assert!(match res.0.get(0).unwrap() {
+        assert!(match res.yaku.get(0).unwrap().0 {
             Yaku::Chinitsu => true,
             _ => false,
         });
@@ -2153,7 +2219,7 @@ mod tests {
 
         let mut table = Table::from_map(&map).unwrap();
         let res = table.yaku().unwrap();
-        assert!(match res.0.get(0).unwrap() {
+        assert!(match res.yaku.get(0).unwrap().0 {
             Yaku::Kokushi => true,
             _ => false,
         });
@@ -2166,15 +2232,15 @@ mod tests {
 
         let mut table = Table::from_map(&map).unwrap();
         let res = table.yaku().unwrap();
-        assert!(match res.0.get(0).unwrap() {
+        assert!(match res.yaku.get(0).unwrap().0 {
             Yaku::WhiteDragons => true,
             _ => false,
         });
-        assert!(match res.0.get(1).unwrap() {
+        assert!(match res.yaku.get(1).unwrap().0 {
             Yaku::GreenDragons => true,
             _ => false,
         });
-        assert!(match res.0.get(2).unwrap() {
+        assert!(match res.yaku.get(2).unwrap().0 {
             Yaku::Shousangen => true,
             _ => false,
         });
@@ -2187,7 +2253,7 @@ mod tests {
 
         let mut table = Table::from_map(&map).unwrap();
         let res = table.yaku().unwrap();
-        assert!(match res.0.get(0).unwrap() {
+        assert!(match res.yaku.get(0).unwrap().0 {
             Yaku::Daisangen => true,
             _ => false,
         });
@@ -2200,7 +2266,7 @@ mod tests {
 
         let mut table = Table::from_map(&map).unwrap();
         let res = table.yaku().unwrap();
-        assert!(match res.0.get(0).unwrap() {
+        assert!(match res.yaku.get(0).unwrap().0 {
             Yaku::Shousuushii => true,
             _ => false,
         });
@@ -2213,11 +2279,11 @@ mod tests {
 
         let mut table = Table::from_map(&map).unwrap();
         let res = table.yaku().unwrap();
-        assert!(match res.0.get(0).unwrap() {
+        assert!(match res.yaku.get(0).unwrap().0 {
             Yaku::Suuankou => true,
             _ => false,
         });
-        assert!(match res.0.get(1).unwrap() {
+        assert!(match res.yaku.get(1).unwrap().0 {
             Yaku::Daisuushii => true,
             _ => false,
         });
@@ -2230,7 +2296,7 @@ mod tests {
 
         let mut table = Table::from_map(&map).unwrap();
         let res = table.yaku().unwrap();
-        assert!(match res.0.get(0).unwrap() {
+        assert!(match res.yaku.get(0).unwrap().0 {
             Yaku::Ryuuiisou => true,
             _ => false,
         });
@@ -2243,7 +2309,7 @@ mod tests {
 
         let mut table = Table::from_map(&map).unwrap();
         let res = table.yaku().unwrap();
-        assert!(match res.0.get(0).unwrap() {
+        assert!(match res.yaku.get(0).unwrap().0 {
             Yaku::Chuuren => true,
             _ => false,
         });
@@ -2259,9 +2325,362 @@ mod tests {
 
         let mut table = Table::from_map(&map).unwrap();
         let res = table.yaku().unwrap();
-        assert!(match res.0.get(0).unwrap() {
+        assert!(match res.yaku.get(0).unwrap().0 {
             Yaku::Tanyao => true,
             _ => false,
         });
     }
+
+    #[test]
+    fn open_tanyao_needs_kuitan() {
+        let mut map = Map::new();
+        map.insert(
+            "my_hand".to_string(),
+            Value::from("234s22555p(p2m2)(345m1)"),
+        );
+
+        let mut table = Table::from_map(&map).unwrap();
+        table.set_rules(Rules::default());
+        let res = table.yaku().unwrap();
+        assert!(res.yaku.iter().any(|(yaku, _)| matches!(yaku, Yaku::Tanyao)));
+
+        table.set_rules(Rules {
+            kuitan_ari: false,
+            ..Rules::default()
+        });
+        let res = table.yaku().unwrap();
+        assert!(!res.yaku.iter().any(|(yaku, _)| matches!(yaku, Yaku::Tanyao)));
+
+        // a closed hand still has tanyao
+        table.set_my_hand(Hand::from_text("234s22555p222m345m", false).unwrap());
+        let res = table.yaku().unwrap();
+        assert!(res.yaku.iter().any(|(yaku, _)| matches!(yaku, Yaku::Tanyao)));
+    }
+
+    #[test]
+    fn find_dora() {
+        let mut map = Map::new();
+        map.insert("my_hand".to_string(), Value::from("23467m234567s88p5m"));
+        map.insert("my_tsumo".to_string(), Value::from(true));
+
+        let mut table = Table::from_map(&map).unwrap();
+        table.add_dora_indicator(Tile::from_text("7p").unwrap());
+        let res = table.yaku().unwrap();
+
+        assert_eq!(res.dora, 2);
+        assert_eq!(res.score.han, 5);
+    }
+
+    #[test]
+    fn find_dora_wrap_around() {
+        let mut map = Map::new();
+        map.insert("my_hand".to_string(), Value::from("123m99s456789p111z"));
+        map.insert("my_seat_wind".to_string(), Value::from(1));
+
+        let mut table = Table::from_map(&map).unwrap();
+        // 9s -> 1s is not in the hand, 8s -> 9s is, 4z -> 1z is
+        table.add_dora_indicator(Tile::from_text("9s").unwrap());
+        table.add_dora_indicator(Tile::from_text("8s").unwrap());
+        table.add_dora_indicator(Tile::from_text("4z").unwrap());
+        let res = table.yaku().unwrap();
+
+        assert_eq!(res.dora, 5);
+        assert_eq!(res.score.han, 6);
+    }
+
+    #[test]
+    fn find_aka_dora() {
+        let mut map = Map::new();
+        map.insert("my_hand".to_string(), Value::from("23467m234067s88p5m"));
+        map.insert("my_tsumo".to_string(), Value::from(true));
+
+        let mut table = Table::from_map(&map).unwrap();
+        let res = table.yaku().unwrap();
+
+        assert_eq!(res.aka_dora, 1);
+        assert_eq!(res.score.han, 4);
+    }
+
+    #[test]
+    fn aka_dora_by_rules() {
+        let mut map = Map::new();
+        map.insert("my_hand".to_string(), Value::from("23467m234067s88p5m"));
+        map.insert("my_tsumo".to_string(), Value::from(true));
+        map.insert("rules".to_string(), Value::from("wrc"));
+
+        let mut table = Table::from_map(&map).unwrap();
+        let res = table.yaku().unwrap();
+
+        assert_eq!(res.aka_dora, 0);
+        assert_eq!(res.score.han, 3);
+    }
+
+    #[test]
+    fn no_tsumo_pinfu() {
+        let mut map = Map::new();
+        map.insert("my_hand".to_string(), Value::from("23467m234567s88p5m"));
+        map.insert("my_tsumo".to_string(), Value::from(true));
+
+        let mut table = Table::from_map(&map).unwrap();
+        table.set_rules(Rules {
+            tsumo_pinfu: false,
+            ..Rules::default()
+        });
+        let res = table.yaku().unwrap();
+
+        assert!(!res.yaku.iter().any(|(y, _)| matches!(y, Yaku::Pinfu)));
+        assert_eq!(res.score.han, 2);
+        assert_eq!(res.score.fu, 30);
+    }
+
+    #[test]
+    fn dora_without_yaku() {
+        let mut map = Map::new();
+        map.insert("my_hand".to_string(), Value::from("123m234s456789p11z"));
+
+        let mut table = Table::from_map(&map).unwrap();
+        table.add_dora_indicator(Tile::from_text("4z").unwrap());
+        let res = table.yaku().unwrap();
+
+        assert!(res.yaku.is_empty());
+        assert_eq!(res.dora, 0);
+        assert_eq!(res.score.han, 0);
+    }
+
+    #[test]
+    fn find_ura_dora_with_riichi() {
+        let mut map = Map::new();
+        map.insert("my_hand".to_string(), Value::from("23467m234567s88p5m"));
+        map.insert("my_riichi".to_string(), Value::from(true));
+
+        let mut table = Table::from_map(&map).unwrap();
+        table.add_ura_dora_indicator(Tile::from_text("7p").unwrap());
+        let res = table.yaku().unwrap();
+
+        // riichi, pinfu, tanyao + 2 ura
+        assert_eq!(res.ura_dora, 2);
+        assert_eq!(res.score_before_ura.han, 3);
+        assert_eq!(res.score_before_ura.fu, 30);
+        assert_eq!(res.score.han, 5);
+        assert_eq!(res.score.total_points(), 8000);
+    }
+
+    #[test]
+    fn ura_dora_without_riichi() {
+        let mut map = Map::new();
+        map.insert("my_hand".to_string(), Value::from("23467m234567s88p5m"));
+        map.insert("my_tsumo".to_string(), Value::from(true));
+
+        let mut table = Table::from_map(&map).unwrap();
+        table.add_ura_dora_indicator(Tile::from_text("7p").unwrap());
+        let res = table.yaku().unwrap();
+
+        assert_eq!(res.ura_dora, 0);
+        assert_eq!(res.score.han, res.score_before_ura.han);
+    }
+
+    #[test]
+    fn find_fu_breakdown() {
+        let mut map = Map::new();
+        map.insert("my_hand".to_string(), Value::from("123m24s456789p22z3s"));
+        map.insert("my_riichi".to_string(), Value::from(true));
+
+        let mut table = Table::from_map(&map).unwrap();
+        let res = table.yaku().unwrap();
+        let fu = res.fu.unwrap();
+
+        // 20 base + 10 menzen ron + 2 kanchan
+        assert_eq!(fu.base, 20);
+        assert_eq!(fu.menzen_ron, 10);
+        assert_eq!(fu.wait_fu, 2);
+        assert_eq!(fu.total(), 32);
+        assert_eq!(res.score.fu, 40);
+    }
+
+    #[test]
+    fn hand_result_report() {
+        let mut map = Map::new();
+        map.insert("my_hand".to_string(), Value::from("123m24s456789p22z3s"));
+        map.insert("my_riichi".to_string(), Value::from(true));
+        map.insert("my_seat_wind".to_string(), Value::from(2));
+
+        let mut table = Table::from_map(&map).unwrap();
+        table.set_ron_from(3);
+        let res = table.yaku().unwrap();
+
+        assert_eq!(res.yaku.len(), 1);
+        assert_eq!(res.yaku[0].1, 1);
+        assert_eq!(res.limit, Limit::None);
+        assert_eq!(res.payment, "1300");
+        assert_eq!(res.payments, [0, 0, 0, 1300]);
+        assert_eq!(res.point_deltas, [1300, 0, 0, -1300]);
+
+        let json = serde_json::to_value(&res).unwrap();
+        assert_eq!(json["yaku"][0][0], "Riichi");
+        assert_eq!(json["yaku"][0][1], 1);
+        assert_eq!(json["payment"], "1300");
+        assert_eq!(json["fu"]["wait"], "Kanchan");
+    }
+
+    #[test]
+    fn find_rinshan() {
+        let mut map = Map::new();
+        map.insert("my_hand".to_string(), Value::from("234m456s55p78p9p(k1z)"));
+        map.insert("my_tsumo".to_string(), Value::from(true));
+        map.insert("my_rinshan".to_string(), Value::from(true));
+
+        let mut table = Table::from_map(&map).unwrap();
+        let res = table.yaku().unwrap();
+        assert!(res.yaku.iter().any(|(y, _)| matches!(y, Yaku::Rinshan)));
+    }
+
+    #[test]
+    fn rinshan_is_not_haitei() {
+        let mut map = Map::new();
+        map.insert("my_hand".to_string(), Value::from("234m456s55p78p9p(k1z)"));
+        map.insert("my_tsumo".to_string(), Value::from(true));
+        map.insert("my_rinshan".to_string(), Value::from(true));
+
+        let mut table = Table::from_map(&map).unwrap();
+        table.set_tiles_remaining(0);
+        let res = table.yaku().unwrap();
+        assert!(!res.yaku.iter().any(|(y, _)| matches!(y, Yaku::Haitei)));
+    }
+
+    #[test]
+    fn find_chankan() {
+        let mut map = Map::new();
+        map.insert("my_hand".to_string(), Value::from("111m456s789p55p23m4m"));
+        map.insert("my_chankan".to_string(), Value::from("shouminkan"));
+
+        let mut table = Table::from_map(&map).unwrap();
+        let res = table.yaku().unwrap();
+        assert_eq!(res.yaku.len(), 1);
+        assert!(matches!(res.yaku[0].0, Yaku::Chankan));
+    }
+
+    #[test]
+    fn chankan_ankan_needs_kokushi() {
+        let mut map = Map::new();
+        map.insert("my_hand".to_string(), Value::from("111m456s789p55p23m4m"));
+        map.insert("my_chankan".to_string(), Value::from("ankan"));
+
+        let mut table = Table::from_map(&map).unwrap();
+        assert!(table.yaku().is_none());
+
+        table.set_my_hand(Hand::from_text("19m19p19s1234567z1m", false).unwrap());
+        let res = table.yaku().unwrap();
+        assert!(matches!(res.yaku[0].0, Yaku::Kokushi));
+    }
+
+    #[test]
+    fn chankan_ankan_without_kokushi() {
+        let mut map = Map::new();
+        map.insert("my_hand".to_string(), Value::from("555z666z777z123m4m4m"));
+        map.insert("my_chankan".to_string(), Value::from("ankan"));
+
+        // even another yakuman can't win on a robbed ankan
+        let mut table = Table::from_map(&map).unwrap();
+        assert!(table.yaku().is_none());
+
+        table.set_my_chankan(None);
+        let res = table.yaku().unwrap();
+        assert!(matches!(res.yaku[0].0, Yaku::Daisangen));
+    }
+
+    #[test]
+    fn find_double_riichi() {
+        let mut map = Map::new();
+        map.insert("my_hand".to_string(), Value::from("111m456s789p55p23m4m"));
+        map.insert("my_double_riichi".to_string(), Value::from(true));
+
+        let mut table = Table::from_map(&map).unwrap();
+        let res = table.yaku().unwrap();
+        assert_eq!(res.yaku.len(), 1);
+        assert!(matches!(res.yaku[0].0, Yaku::DoubleRiichi));
+        assert_eq!(res.score.han, 2);
+    }
+
+    #[test]
+    fn find_kazoe_yakuman() {
+        let mut map = Map::new();
+        map.insert("my_hand".to_string(), Value::from("1122334567899m9m"));
+        map.insert("my_tsumo".to_string(), Value::from(true));
+
+        let mut table = Table::from_map(&map).unwrap();
+        table.set_dora_indicators(vec![Tile::from_text("9m").unwrap()]);
+        let res = table.yaku().unwrap();
+        assert_eq!(res.score.han, 13);
+        assert_eq!(res.limit, Limit::KazoeYakuman);
+        assert_eq!(res.score.total_points(), 32000);
+        assert!(res.yaku.iter().all(|(y, _)| !y.is_yakuman()));
+
+        table.set_rules(Rules {
+            kazoe_yakuman: false,
+            ..Rules::default()
+        });
+        let res = table.yaku().unwrap();
+        assert_eq!(res.limit, Limit::Sanbaiman);
+        assert_eq!(res.score.total_points(), 24000);
+    }
+
+    #[test]
+    fn find_multiple_yakuman() {
+        let mut map = Map::new();
+        map.insert("my_hand".to_string(), Value::from("555666777z11z22z2z"));
+
+        let mut table = Table::from_map(&map).unwrap();
+        let res = table.yaku().unwrap();
+        assert_eq!(res.yaku.len(), 2);
+        assert_eq!(res.score.yakuman, 2);
+        assert_eq!(res.score.total_points(), 64000);
+
+        table.set_rules(Rules {
+            multiple_yakuman: false,
+            ..Rules::default()
+        });
+        let res = table.yaku().unwrap();
+        assert_eq!(res.score.yakuman, 1);
+        assert_eq!(res.score.total_points(), 32000);
+    }
+
+    #[test]
+    fn find_double_yakuman_kokushi() {
+        let mut map = Map::new();
+        map.insert("my_hand".to_string(), Value::from("19m19p19s1234567z1m"));
+
+        let mut table = Table::from_map(&map).unwrap();
+        let res = table.yaku().unwrap();
+        assert_eq!(res.score.yakuman, 1);
+
+        table.set_rules(Rules {
+            double_yakuman: true,
+            ..Rules::default()
+        });
+        let res = table.yaku().unwrap();
+        assert_eq!(res.score.yakuman, 2);
+        assert_eq!(res.score.total_points(), 64000);
+    }
+
+    #[test]
+    fn find_junsei_chuuren() {
+        let mut map = Map::new();
+        map.insert("my_hand".to_string(), Value::from("1112345678999p5p"));
+
+        let mut table = Table::from_map(&map).unwrap();
+        table.set_rules(Rules {
+            double_yakuman: true,
+            ..Rules::default()
+        });
+        let res = table.yaku().unwrap();
+        assert_eq!(res.score.yakuman, 2);
+
+        table.set_my_hand(Hand::from_text("1112345678999p1p", false).unwrap());
+        let res = table.yaku().unwrap();
+        assert_eq!(res.score.yakuman, 2);
+
+        table.set_my_hand(Hand::from_text("1112355678999p4p", false).unwrap());
+        let res = table.yaku().unwrap();
+        assert_eq!(res.score.yakuman, 1);
+    }
 }